@@ -0,0 +1,121 @@
+use proc_macro::TokenStream;
+use quote::{format_ident, quote};
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::{Expr, FnArg, ItemFn, Lit, LitStr, Meta, Pat, Token, Type, parenthesized, parse_macro_input};
+
+/// Exposes a function to node graphs. It's registered into the `FunctionRegistry` when the scripting plugin builds.
+///
+/// ```ignore
+/// /// How far the entity jumps.
+/// #[script_fn(name = "Jump Height", category = "Movement", args("gravity", "time"), returns = "height", pure)]
+/// fn jump_height(gravity: f32, time: f32) -> f32 { gravity * time * time / 2.0 }
+///
+/// #[script_fn(on = Vec3)]
+/// fn length(v: &Vec3) -> f32 { v.length() }
+/// ```
+///
+/// - `name`: shown on the node and in the palette, the function's name by default.
+/// - `category`: where the palette files it, `/` separated.
+/// - `args(..)`: argument names, the parameter names by default.
+/// - `returns`: what to call the return value.
+/// - `on = Type`: lists the function with `Type`'s associated functions.
+/// - `pure`: the function has no side effects, so its node has no flow pins and runs where its value is used.
+///
+/// The doc comment becomes the function's description.
+#[proc_macro_attribute]
+pub fn script_fn(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let function = parse_macro_input!(item as ItemFn);
+    let mut name: Option<LitStr> = None;
+    let mut category: Option<LitStr> = None;
+    let mut args: Option<Vec<LitStr>> = None;
+    let mut returns: Option<LitStr> = None;
+    let mut on: Option<Type> = None;
+    let mut pure = false;
+    let parser = syn::meta::parser(|meta| {
+        if meta.path.is_ident("name") {
+            name = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("category") {
+            category = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("args") {
+            let content;
+            parenthesized!(content in meta.input);
+            args = Some(Punctuated::<LitStr, Token![,]>::parse_terminated(&content)?.into_iter().collect());
+        } else if meta.path.is_ident("returns") {
+            returns = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("on") {
+            on = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("pure") {
+            pure = true;
+        } else {
+            return Err(meta.error("expected `name`, `category`, `args`, `returns`, `on` or `pure`"));
+        }
+        Ok(())
+    });
+    if let Err(error) = parser.parse(attr) {
+        return error.to_compile_error().into();
+    }
+    if !function.sig.generics.params.is_empty() {
+        return syn::Error::new_spanned(&function.sig.generics, "script functions can't be generic").to_compile_error().into();
+    }
+
+    let mut params = vec![];
+    for input in &function.sig.inputs {
+        match input {
+            FnArg::Receiver(receiver) => return syn::Error::new_spanned(receiver, "script functions can't take `self`, use `on = Type` with a free function instead").to_compile_error().into(),
+            FnArg::Typed(typed) => params.push(match typed.pat.as_ref() {
+                Pat::Ident(ident) => ident.ident.to_string(),
+                _ => "_".to_string(),
+            }),
+        }
+    }
+    let args: Vec<String> = match args {
+        Some(args) if args.len() != params.len() => return syn::Error::new_spanned(&function.sig.inputs, format!("`args` names {} arguments but the function takes {}", args.len(), params.len())).to_compile_error().into(),
+        Some(args) => args.iter().map(LitStr::value).collect(),
+        None => params,
+    };
+
+    let doc: Vec<String> = function
+        .attrs
+        .iter()
+        .filter_map(|attr| match &attr.meta {
+            Meta::NameValue(meta) if meta.path.is_ident("doc") => match &meta.value {
+                Expr::Lit(lit) => match &lit.lit {
+                    Lit::Str(doc) => Some(doc.value().trim().to_string()),
+                    _ => None,
+                },
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    let doc = doc.join("\n");
+
+    let ident = &function.sig.ident;
+    let name = name.map(|name| name.value()).unwrap_or_else(|| ident.to_string());
+    let category = category.map(|category| category.value()).unwrap_or_default();
+    let returns = returns.map(|returns| returns.value()).unwrap_or_default();
+    let owner = match on {
+        Some(on) => quote! { ::core::option::Option::Some(<#on as ::bevy::reflect::Typed>::type_info) },
+        None => quote! { ::core::option::Option::None },
+    };
+    let helper = format_ident!("__script_fn_{}", ident);
+    quote! {
+        #function
+
+        #[doc(hidden)]
+        fn #helper() -> ::bevy::reflect::func::DynamicFunction<'static> {
+            use ::bevy::reflect::func::IntoFunction as _;
+            #ident.into_function()
+        }
+
+        ::bevy_node_scripting::script_fn::inventory::submit! {
+            ::bevy_node_scripting::script_fn::ScriptFn {
+                function: #helper,
+                owner: #owner,
+                meta: ::bevy_node_scripting::script_fn::FunctionMeta { name: #name, category: #category, args: &[#(#args),*], returns: #returns, doc: #doc, pure: #pure },
+            }
+        }
+    }
+    .into()
+}
@@ -2,7 +2,6 @@ use crate::Bytecode;
 use crate::again::{DataType, Node, Port, TypeData, Viewer};
 use bevy::prelude::World;
 use bevy::reflect::func::args::Ownership;
-use egui::Ui;
 use egui_snarl::{InPin, InPinId, NodeId, OutPin, OutPinId, Snarl};
 use std::collections::HashMap;
 
@@ -12,14 +11,14 @@ pub struct ApplyNode {
 }
 
 impl Node for ApplyNode {
-    fn inputs(node: NodeId, snarl_viewer: &mut Viewer, snarl: &Snarl<Box<dyn Node>>) -> usize
+    fn inputs(_node: NodeId, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> usize
     where
         Self: Sized,
     {
         3
     }
 
-    fn outputs(node: NodeId, snarl_viewer: &mut Viewer, snarl: &Snarl<Box<dyn Node>>) -> usize
+    fn outputs(_node: NodeId, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> usize
     where
         Self: Sized,
     {
@@ -44,10 +43,8 @@ impl Node for ApplyNode {
             let Some(remote) = pin.remotes.first().cloned() else { return Port::Data(DataType::OwnershipOnly(Ownership::Mut)) };
             let t = snarl.get_node(remote.node).unwrap().get_traits();
             let port = t.output_port_2(snarl.out_pin(remote).clone(), snarl_viewer, snarl);
-            if let Port::Data(DataType::Data(TypeData(type_info, ownership))) = port {
-                if ownership == Ownership::Mut {
-                    return Port::Data(DataType::Data(TypeData(type_info, Ownership::Mut)));
-                }
+            if let Port::Data(DataType::Data(TypeData(type_info, Ownership::Mut))) = port {
+                return Port::Data(DataType::Data(TypeData(type_info, Ownership::Mut)));
             }
             return Port::Data(DataType::OwnershipOnly(Ownership::Mut));
         }
@@ -58,7 +55,7 @@ impl Node for ApplyNode {
         Port::Data(DataType::OwnershipOnly(Ownership::Owned))
     }
 
-    fn output_port(pin: OutPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Port
+    fn output_port(_pin: OutPin, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> Port
     where
         Self: Sized,
     {
@@ -73,10 +70,19 @@ impl Node for ApplyNode {
         self.node_id.replace(node);
     }
 
-    fn compile(pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>, bytecode: &mut Vec<Bytecode>, scope_map: &mut HashMap<OutPinId, usize>, stack_ptr: &mut usize, world: &mut World) -> Option<InPinId>
+    fn compile(pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>, bytecode: &mut Vec<Bytecode>, scope_map: &mut HashMap<OutPinId, usize>, _stack_ptr: &mut usize, _world: &mut World) -> Option<InPinId>
     where
         Self: Sized,
     {
-        todo!()
+        let target = snarl_viewer.copy_input(InPinId { node: pin.id.node, input: 1 }, snarl, scope_map);
+        let value = snarl_viewer.copy_input(InPinId { node: pin.id.node, input: 2 }, snarl, scope_map);
+        let (Some(target), Some(value)) = (target, value) else {
+            return None;
+        };
+        // Apply consumes both, so the originals stay where other nodes expect them
+        bytecode.push(target);
+        bytecode.push(value);
+        bytecode.push(Bytecode::Apply);
+        snarl.out_pin(OutPinId { node: pin.id.node, output: 0 }).remotes.first().copied()
     }
 }
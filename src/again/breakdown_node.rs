@@ -0,0 +1,285 @@
+use crate::Bytecode;
+use crate::again::{DataType, Node, Port, TypeData, Viewer};
+use crate::diagnostics::Location;
+use bevy::prelude::World;
+use bevy::reflect::TypeInfo;
+use bevy::reflect::func::args::Ownership;
+use egui::{ComboBox, Ui};
+use egui_snarl::{InPin, InPinId, NodeId, OutPin, OutPinId, Snarl};
+use std::collections::HashMap;
+
+// Both nodes take a value apart into one output per field, by name for structs and by position for tuples and tuple structs.
+// The ownership picked on the node decides whether the fields are clones of the value's or borrow into it.
+
+/// The names and types of the fields of `info`, `None` if it isn't the shape the node takes apart.
+/// Fields whose type isn't registered have no type.
+fn fields(info: &TypeInfo, tuple: bool) -> Option<Vec<(String, Option<&'static TypeInfo>)>> {
+    match (info, tuple) {
+        (TypeInfo::Struct(s), false) => Some(s.iter().map(|field| (field.name().to_string(), field.type_info())).collect()),
+        (TypeInfo::TupleStruct(s), true) => Some(s.iter().map(|field| (field.index().to_string(), field.type_info())).collect()),
+        (TypeInfo::Tuple(s), true) => Some(s.iter().map(|field| (field.index().to_string(), field.type_info())).collect()),
+        _ => None,
+    }
+}
+
+/// Whether `node` takes tuples apart rather than structs, and how it hands out the fields.
+fn settings(node: NodeId, snarl: &Snarl<Box<dyn Node>>) -> (bool, Ownership) {
+    let node = snarl.get_node(node).unwrap();
+    match (node.downcast::<BreakdownNode>(), node.downcast::<TupleBreakdownNode>()) {
+        (Some(breakdown), _) => (false, breakdown.ownership),
+        (_, Some(breakdown)) => (true, breakdown.ownership),
+        _ => unreachable!("only breakdown nodes use these"),
+    }
+}
+
+/// The value wired into the node, `None` while nothing typed is.
+fn wired(node: NodeId, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Option<TypeData> {
+    match snarl_viewer.wired_port(InPinId { node, input: 1 }, snarl) {
+        Some(Port::Data(DataType::Data(type_data))) => Some(type_data),
+        _ => None,
+    }
+}
+
+fn wired_fields(node: NodeId, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Vec<(String, Option<&'static TypeInfo>)> {
+    let (tuple, _) = settings(node, snarl);
+    wired(node, snarl_viewer, snarl).and_then(|type_data| fields(type_data.0, tuple)).unwrap_or_default()
+}
+
+/// Lets the ownership be picked, returning the one picked or `ownership` again.
+pub(crate) fn show_ownership(node: NodeId, ui: &mut Ui, mut ownership: Ownership) -> Ownership {
+    let label = match ownership {
+        Ownership::Owned => "clone",
+        Ownership::Ref => "&",
+        Ownership::Mut => "&mut",
+    };
+    ComboBox::from_id_salt(node).selected_text(label).show_ui(ui, |ui| {
+        ui.selectable_value(&mut ownership, Ownership::Owned, "clone");
+        ui.selectable_value(&mut ownership, Ownership::Ref, "&");
+        ui.selectable_value(&mut ownership, Ownership::Mut, "&mut");
+    });
+    ownership
+}
+
+fn input_port(pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Port {
+    if pin.id.input == 0 {
+        return Port::Flow(0);
+    }
+    match wired(pin.id.node, snarl_viewer, snarl) {
+        Some(type_data) => Port::Data(DataType::Data(type_data)),
+        None => Port::Data(DataType::Blank),
+    }
+}
+
+fn output_port(pin: OutPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Port {
+    if pin.id.output == 0 {
+        return Port::Flow(0);
+    }
+    let (_, ownership) = settings(pin.id.node, snarl);
+    match wired_fields(pin.id.node, snarl_viewer, snarl).get(pin.id.output - 1) {
+        Some((_, Some(info))) => Port::Data(DataType::Data(TypeData(info, ownership))),
+        _ => Port::Data(DataType::Blank),
+    }
+}
+
+fn show_output_port(pin: OutPin, ui: &mut Ui, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) {
+    if let Some((name, _)) = pin.id.output.checked_sub(1).and_then(|field| wired_fields(pin.id.node, snarl_viewer, snarl).into_iter().nth(field)) {
+        ui.label(name);
+    }
+}
+
+fn compile(pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>, bytecode: &mut Vec<Bytecode>, scope_map: &mut HashMap<OutPinId, usize>, stack_ptr: &mut usize) -> Option<InPinId> {
+    let node = pin.id.node;
+    let (tuple, ownership) = settings(node, snarl);
+    let position = snarl_viewer.data_input(InPinId { node, input: 1 }, snarl, scope_map)?;
+    let type_data = wired(node, snarl_viewer, snarl)?;
+    let Some(fields) = fields(type_data.0, tuple) else {
+        let expected = if tuple { "a tuple or tuple struct" } else { "a struct" };
+        snarl_viewer.diagnostics.error(Location::Input(InPinId { node, input: 1 }), format!("expected {expected}, found {type_data}"));
+        return None;
+    };
+    if type_data.1 == Ownership::Ref && ownership == Ownership::Mut {
+        snarl_viewer.diagnostics.error(Location::Node(node), "cannot mutably borrow the fields of a shared reference");
+        return None;
+    }
+    // fields nothing reads are left alone, borrowing them would only hold the value up
+    for (field, _) in fields.iter().enumerate() {
+        let output = OutPinId { node, output: field + 1 };
+        if snarl.out_pin(output).remotes.is_empty() {
+            continue;
+        }
+        bytecode.push(match ownership {
+            Ownership::Owned => Bytecode::DupField(position, field),
+            Ownership::Ref => Bytecode::RefField(position, field),
+            Ownership::Mut => Bytecode::MutField(position, field),
+        });
+        scope_map.insert(output, *stack_ptr);
+        *stack_ptr += 1;
+    }
+    snarl.out_pin(OutPinId { node, output: 0 }).remotes.first().copied()
+}
+
+/// Takes a struct apart into one output per named field.
+pub struct BreakdownNode {
+    node_id: Option<NodeId>,
+    pub ownership: Ownership,
+}
+
+impl Default for BreakdownNode {
+    fn default() -> Self {
+        Self { node_id: None, ownership: Ownership::Ref }
+    }
+}
+
+impl Node for BreakdownNode {
+    fn inputs(_node: NodeId, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> usize
+    where
+        Self: Sized,
+    {
+        2
+    }
+
+    fn outputs(node: NodeId, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> usize
+    where
+        Self: Sized,
+    {
+        1 + wired_fields(node, snarl_viewer, snarl).len()
+    }
+
+    fn title() -> String
+    where
+        Self: Sized,
+    {
+        "Breakdown".to_string()
+    }
+
+    fn show_header(node: NodeId, ui: &mut Ui, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>)
+    where
+        Self: Sized,
+    {
+        ui.label(Self::title());
+        let ownership = snarl.get_node(node).unwrap().downcast::<BreakdownNode>().unwrap().ownership;
+        let picked = show_ownership(node, ui, ownership);
+        if picked != ownership {
+            snarl_viewer.edits.set_state(snarl, node, |breakdown: &mut BreakdownNode| breakdown.ownership = picked);
+        }
+    }
+
+    fn show_output_port(pin: OutPin, ui: &mut Ui, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>)
+    where
+        Self: Sized,
+    {
+        show_output_port(pin, ui, snarl_viewer, snarl);
+    }
+
+    fn input_port(pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Port
+    where
+        Self: Sized,
+    {
+        input_port(pin, snarl_viewer, snarl)
+    }
+
+    fn output_port(pin: OutPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Port
+    where
+        Self: Sized,
+    {
+        output_port(pin, snarl_viewer, snarl)
+    }
+
+    fn node_id(&self) -> NodeId {
+        self.node_id.unwrap()
+    }
+
+    fn set_node_id(&mut self, node: NodeId) {
+        self.node_id.replace(node);
+    }
+
+    fn compile(pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>, bytecode: &mut Vec<Bytecode>, scope_map: &mut HashMap<OutPinId, usize>, stack_ptr: &mut usize, _world: &mut World) -> Option<InPinId>
+    where
+        Self: Sized,
+    {
+        compile(pin, snarl_viewer, snarl, bytecode, scope_map, stack_ptr)
+    }
+}
+
+/// Takes a tuple or tuple struct apart into one output per field.
+pub struct TupleBreakdownNode {
+    node_id: Option<NodeId>,
+    pub ownership: Ownership,
+}
+
+impl Default for TupleBreakdownNode {
+    fn default() -> Self {
+        Self { node_id: None, ownership: Ownership::Ref }
+    }
+}
+
+impl Node for TupleBreakdownNode {
+    fn inputs(_node: NodeId, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> usize
+    where
+        Self: Sized,
+    {
+        2
+    }
+
+    fn outputs(node: NodeId, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> usize
+    where
+        Self: Sized,
+    {
+        1 + wired_fields(node, snarl_viewer, snarl).len()
+    }
+
+    fn title() -> String
+    where
+        Self: Sized,
+    {
+        "Tuple Breakdown".to_string()
+    }
+
+    fn show_header(node: NodeId, ui: &mut Ui, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>)
+    where
+        Self: Sized,
+    {
+        ui.label(Self::title());
+        let ownership = snarl.get_node(node).unwrap().downcast::<TupleBreakdownNode>().unwrap().ownership;
+        let picked = show_ownership(node, ui, ownership);
+        if picked != ownership {
+            snarl_viewer.edits.set_state(snarl, node, |breakdown: &mut TupleBreakdownNode| breakdown.ownership = picked);
+        }
+    }
+
+    fn show_output_port(pin: OutPin, ui: &mut Ui, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>)
+    where
+        Self: Sized,
+    {
+        show_output_port(pin, ui, snarl_viewer, snarl);
+    }
+
+    fn input_port(pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Port
+    where
+        Self: Sized,
+    {
+        input_port(pin, snarl_viewer, snarl)
+    }
+
+    fn output_port(pin: OutPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Port
+    where
+        Self: Sized,
+    {
+        output_port(pin, snarl_viewer, snarl)
+    }
+
+    fn node_id(&self) -> NodeId {
+        self.node_id.unwrap()
+    }
+
+    fn set_node_id(&mut self, node: NodeId) {
+        self.node_id.replace(node);
+    }
+
+    fn compile(pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>, bytecode: &mut Vec<Bytecode>, scope_map: &mut HashMap<OutPinId, usize>, stack_ptr: &mut usize, _world: &mut World) -> Option<InPinId>
+    where
+        Self: Sized,
+    {
+        compile(pin, snarl_viewer, snarl, bytecode, scope_map, stack_ptr)
+    }
+}
@@ -0,0 +1,152 @@
+use crate::again::{DataType, Node, Port, TypeData, Viewer};
+use crate::diagnostics::Location;
+use crate::{Bytecode, Literal};
+use bevy::prelude::{ReflectDefault, World};
+use bevy::reflect::TypeInfo;
+use bevy::reflect::func::args::Ownership;
+use egui::{ComboBox, Ui};
+use egui_snarl::{InPin, InPinId, NodeId, OutPin, OutPinId, Snarl};
+use std::collections::HashMap;
+
+/// Builds a struct from its default with the wired fields replaced, the opposite of a [`BreakdownNode`](crate::again::breakdown_node::BreakdownNode).
+#[derive(Default)]
+pub struct BuildupNode {
+    node_id: Option<NodeId>,
+    /// Type path of the struct, empty until one is picked.
+    pub type_path: String,
+}
+
+impl BuildupNode {
+    fn type_info(node: NodeId, snarl_viewer: &Viewer, snarl: &Snarl<Box<dyn Node>>) -> Option<&'static TypeInfo> {
+        let type_path = &snarl.get_node(node).unwrap().downcast::<BuildupNode>().unwrap().type_path;
+        snarl_viewer.registry.read().get_with_type_path(type_path).map(|registration| registration.type_info())
+    }
+
+    /// The names and types of the fields, one input each.
+    fn fields(node: NodeId, snarl_viewer: &Viewer, snarl: &Snarl<Box<dyn Node>>) -> Vec<(&'static str, Option<&'static TypeInfo>)> {
+        match Self::type_info(node, snarl_viewer, snarl) {
+            Some(TypeInfo::Struct(s)) => s.iter().map(|field| (field.name(), field.type_info())).collect(),
+            _ => vec![],
+        }
+    }
+}
+
+impl Node for BuildupNode {
+    fn inputs(node: NodeId, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> usize
+    where
+        Self: Sized,
+    {
+        1 + Self::fields(node, snarl_viewer, snarl).len()
+    }
+
+    fn outputs(_node: NodeId, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> usize
+    where
+        Self: Sized,
+    {
+        2
+    }
+
+    fn title() -> String
+    where
+        Self: Sized,
+    {
+        "Buildup".to_string()
+    }
+
+    fn show_header(node: NodeId, ui: &mut Ui, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>)
+    where
+        Self: Sized,
+    {
+        ui.label(Self::title());
+        let inputs = Self::fields(node, snarl_viewer, snarl).len();
+        let type_registry = snarl_viewer.registry.read();
+        let previous = snarl.get_node(node).unwrap().downcast::<BuildupNode>().unwrap().type_path.clone();
+        let mut picked = previous.clone();
+        let selected = type_registry.get_with_type_path(&previous).map_or("pick a struct", |registration| registration.type_info().type_path_table().short_path());
+        ComboBox::from_id_salt(node).selected_text(selected).show_ui(ui, |ui| {
+            for registration in type_registry.iter().filter(|registration| matches!(registration.type_info(), TypeInfo::Struct(_)) && registration.data::<ReflectDefault>().is_some()) {
+                let type_path = registration.type_info().type_path();
+                ui.selectable_value(&mut picked, type_path.to_string(), registration.type_info().type_path_table().short_path());
+            }
+        });
+        if picked != previous {
+            // the fields of the old type don't line up with the new one
+            for input in 1..1 + inputs {
+                snarl_viewer.edits.drop_inputs(snarl, InPinId { node, input });
+            }
+            snarl_viewer.edits.set_state(snarl, node, |buildup: &mut BuildupNode| buildup.type_path = picked);
+        }
+    }
+
+    fn show_input_port(pin: InPin, ui: &mut Ui, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>)
+    where
+        Self: Sized,
+    {
+        if let Some((name, _)) = pin.id.input.checked_sub(1).and_then(|field| Self::fields(pin.id.node, snarl_viewer, snarl).into_iter().nth(field)) {
+            ui.label(name);
+        }
+    }
+
+    fn input_port(pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Port
+    where
+        Self: Sized,
+    {
+        if pin.id.input == 0 {
+            return Port::Flow(0);
+        }
+        match Self::fields(pin.id.node, snarl_viewer, snarl).get(pin.id.input - 1) {
+            Some((_, Some(info))) => Port::Data(DataType::Data(TypeData(info, Ownership::Owned))),
+            _ => Port::Data(DataType::Blank),
+        }
+    }
+
+    fn output_port(pin: OutPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Port
+    where
+        Self: Sized,
+    {
+        if pin.id.output == 0 {
+            return Port::Flow(0);
+        }
+        match Self::type_info(pin.id.node, snarl_viewer, snarl) {
+            Some(info) => Port::Data(DataType::Data(TypeData(info, Ownership::Owned))),
+            None => Port::Data(DataType::Blank),
+        }
+    }
+
+    fn node_id(&self) -> NodeId {
+        self.node_id.unwrap()
+    }
+
+    fn set_node_id(&mut self, node: NodeId) {
+        self.node_id.replace(node);
+    }
+
+    fn compile(pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>, bytecode: &mut Vec<Bytecode>, scope_map: &mut HashMap<OutPinId, usize>, stack_ptr: &mut usize, _world: &mut World) -> Option<InPinId>
+    where
+        Self: Sized,
+    {
+        let node = pin.id.node;
+        let type_path = snarl.get_node(node).unwrap().downcast::<BuildupNode>().unwrap().type_path.clone();
+        let default = snarl_viewer.registry.read().get_with_type_path(&type_path).and_then(|registration| registration.data::<ReflectDefault>()).map(|default| default.default());
+        let Some(default) = default else {
+            snarl_viewer.diagnostics.error(Location::Node(node), format!("`{type_path}` isn't a registered struct with a default"));
+            return None;
+        };
+        let position = *stack_ptr;
+        bytecode.push(Bytecode::Push(Literal::Box(default.into_partial_reflect())));
+        *stack_ptr += 1;
+        // unwired fields keep their default
+        for field in 0..Self::fields(node, snarl_viewer, snarl).len() {
+            let input = InPinId { node, input: field + 1 };
+            if snarl.in_pin(input).remotes.is_empty() {
+                continue;
+            }
+            let value = snarl_viewer.copy_input(input, snarl, scope_map)?;
+            bytecode.push(Bytecode::MutField(position, field));
+            bytecode.push(value);
+            bytecode.push(Bytecode::Apply);
+        }
+        scope_map.insert(OutPinId { node, output: 1 }, position);
+        snarl.out_pin(OutPinId { node, output: 0 }).remotes.first().copied()
+    }
+}
@@ -0,0 +1,128 @@
+use crate::Bytecode;
+use crate::again::{DataType, Node, Port, Scope, Viewer};
+use crate::compiler::{compile_scope, leave_scope};
+use crate::diagnostics::Location;
+use bevy::prelude::World;
+use egui::Ui;
+use egui_snarl::{InPin, InPinId, NodeId, OutPin, OutPinId, Snarl};
+use std::collections::HashMap;
+
+/// Runs its body once for every item of an iterator, like the output of a [`QueryNode`](crate::again::query_node::QueryNode), then carries on from done.
+#[derive(Default)]
+pub struct ForNode {
+    node_id: Option<NodeId>,
+}
+
+impl ForNode {
+    /// What each step of the wired iterator yields, empty while nothing is wired.
+    fn items(node: NodeId, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Vec<DataType> {
+        match snarl_viewer.wired_port(InPinId { node, input: 1 }, snarl) {
+            Some(Port::Data(DataType::Iterator(items))) => items,
+            _ => vec![],
+        }
+    }
+}
+
+impl Node for ForNode {
+    fn inputs(_node: NodeId, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> usize
+    where
+        Self: Sized,
+    {
+        2
+    }
+
+    fn outputs(node: NodeId, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> usize
+    where
+        Self: Sized,
+    {
+        2 + Self::items(node, snarl_viewer, snarl).len()
+    }
+
+    fn title() -> String
+    where
+        Self: Sized,
+    {
+        "For".to_string()
+    }
+
+    fn show_output_port(pin: OutPin, ui: &mut Ui, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>)
+    where
+        Self: Sized,
+    {
+        if let Some(label) = ["body", "done"].get(pin.id.output) {
+            ui.label(*label);
+        }
+    }
+
+    fn input_port(pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Port
+    where
+        Self: Sized,
+    {
+        if pin.id.input == 0 {
+            return Port::Flow(0);
+        }
+        Port::Data(match snarl_viewer.wired_port(pin.id, snarl) {
+            Some(Port::Data(DataType::Iterator(items))) => DataType::Iterator(items),
+            _ => DataType::Blank,
+        })
+    }
+
+    fn output_port(pin: OutPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Port
+    where
+        Self: Sized,
+    {
+        match pin.id.output {
+            0 => Port::Flow(1),
+            1 => Port::Flow(0),
+            output => Port::Data(Self::items(pin.id.node, snarl_viewer, snarl).get(output - 2).cloned().unwrap_or(DataType::Blank)),
+        }
+    }
+
+    fn output_scope(pin: OutPin, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> Scope
+    where
+        Self: Sized,
+    {
+        // the items only exist while the body runs
+        if pin.id.output >= 2 { 1 } else { 0 }
+    }
+
+    fn node_id(&self) -> NodeId {
+        self.node_id.unwrap()
+    }
+
+    fn set_node_id(&mut self, node: NodeId) {
+        self.node_id.replace(node);
+    }
+
+    fn compile(pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>, bytecode: &mut Vec<Bytecode>, scope_map: &mut HashMap<OutPinId, usize>, stack_ptr: &mut usize, world: &mut World) -> Option<InPinId>
+    where
+        Self: Sized,
+    {
+        let node = pin.id.node;
+        let iterator = snarl_viewer.data_input(InPinId { node, input: 1 }, snarl, scope_map)?;
+        let Some(Port::Data(DataType::Iterator(items))) = snarl_viewer.wired_port(InPinId { node, input: 1 }, snarl) else {
+            snarl_viewer.diagnostics.error(Location::Input(InPinId { node, input: 1 }), "expected an iterator, like the output of a Query");
+            return None;
+        };
+        // stepping borrows the iterator, so the one on the stack stays where other loops over it expect it
+        bytecode.push(Bytecode::Mut(iterator));
+        *stack_ptr += 1;
+        let base = *stack_ptr;
+        let start = bytecode.len();
+        bytecode.push(Bytecode::NextMut);
+        let exit = bytecode.len();
+        bytecode.push(Bytecode::Jump(usize::MAX));
+        bytecode.push(Bytecode::ListBreakdown(items.len()));
+        for output in 2..2 + items.len() {
+            scope_map.insert(OutPinId { node, output }, *stack_ptr);
+            *stack_ptr += 1;
+        }
+        let body = snarl.out_pin(OutPinId { node, output: 0 }).remotes.first().copied();
+        compile_scope(body, snarl_viewer, snarl, bytecode, scope_map, stack_ptr, world);
+        leave_scope(base, bytecode, scope_map, stack_ptr);
+        bytecode.push(Bytecode::Jump(start));
+        bytecode[exit] = Bytecode::Jump(bytecode.len());
+        leave_scope(base - 1, bytecode, scope_map, stack_ptr);
+        snarl.out_pin(OutPinId { node, output: 1 }).remotes.first().copied()
+    }
+}
@@ -0,0 +1,338 @@
+use crate::Bytecode;
+use crate::again::{DataType, Node, Port, TypeData, Viewer, port_name, ports_compatible};
+use crate::diagnostics::Location;
+use bevy::prelude::World;
+use bevy::reflect::func::args::Ownership;
+use bevy::reflect::func::{DynamicFunction, PrettyPrintSignatureInfo, SignatureInfo};
+use egui::Ui;
+use egui_snarl::{InPin, InPinId, NodeId, OutPin, OutPinId, Snarl};
+use std::any::TypeId;
+use std::collections::HashMap;
+
+// Overloaded functions get one input per argument of their longest signature. Which overload is called
+// follows from what's wired in: only the signatures every wired input fits stay candidates, and the
+// ports show what the remaining candidates agree on until a single one is left.
+
+/// Calls a function from the [`FunctionRegistry`](crate::ui::FunctionRegistry), one input per argument and an output for what it returns.
+#[derive(Default)]
+pub struct FunctionNode {
+    node_id: Option<NodeId>,
+    /// Full name of the function, as `FunctionRegistry::get` finds it.
+    pub function: String,
+}
+
+impl FunctionNode {
+    fn function(node: NodeId, snarl_viewer: &Viewer, snarl: &Snarl<Box<dyn Node>>) -> Option<DynamicFunction<'static>> {
+        let name = &snarl.get_node(node)?.downcast::<FunctionNode>()?.function;
+        snarl_viewer.functions.read().get(name).cloned()
+    }
+
+    fn name(node: NodeId, snarl: &Snarl<Box<dyn Node>>) -> Option<String> {
+        Some(snarl.get_node(node)?.downcast::<FunctionNode>()?.function.clone())
+    }
+
+    /// How many flow pins each side has: none for `pure` functions, whose nodes run where their value is used.
+    fn flow(node: NodeId, snarl_viewer: &Viewer, snarl: &Snarl<Box<dyn Node>>) -> usize {
+        let pure = Self::name(node, snarl).is_some_and(|name| snarl_viewer.functions.read().meta(&name).is_some_and(|meta| meta.pure));
+        if pure { 0 } else { 1 }
+    }
+
+    fn arity(function: &DynamicFunction<'static>) -> usize {
+        function.info().signatures().iter().map(SignatureInfo::arg_count).max().unwrap_or(0)
+    }
+
+    fn returns_value(signature: &SignatureInfo) -> bool {
+        signature.return_info().type_id() != TypeId::of::<()>()
+    }
+
+    fn data_port(type_id: TypeId, ownership: Ownership, snarl_viewer: &Viewer) -> Port {
+        match snarl_viewer.registry.read().get_type_info(type_id) {
+            Some(type_info) => Port::Data(DataType::Data(TypeData(type_info, ownership))),
+            None => Port::Data(DataType::OwnershipOnly(ownership)),
+        }
+    }
+
+    /// What all of `ports` agree on: the type if they share it, the ownership if they share only that.
+    fn merge(ports: impl IntoIterator<Item = (TypeId, Ownership)>, snarl_viewer: &Viewer) -> Port {
+        let mut ports = ports.into_iter();
+        let Some((type_id, ownership)) = ports.next() else {
+            return Port::Data(DataType::Blank);
+        };
+        let (same_type, same_ownership) = ports.fold((true, true), |(same_type, same_ownership), (other_type, other_ownership)| (same_type && other_type == type_id, same_ownership && other_ownership == ownership));
+        match (same_type && same_ownership, same_ownership) {
+            (true, _) => Self::data_port(type_id, ownership, snarl_viewer),
+            (false, true) => Port::Data(DataType::OwnershipOnly(ownership)),
+            (false, false) => Port::Data(DataType::Blank),
+        }
+    }
+
+    /// The overloads every wired input fits, by index into the function's signatures.
+    fn candidates(node: NodeId, function: &DynamicFunction<'static>, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Vec<usize> {
+        let mut wired = vec![];
+        let flow = Self::flow(node, snarl_viewer, snarl);
+        for input in flow..flow + Self::arity(function) {
+            let Some(remote) = snarl.in_pin(InPinId { node, input }).remotes.first().copied() else {
+                continue;
+            };
+            let Some(traits) = snarl.get_node(remote.node).map(|node| node.get_traits()) else {
+                continue;
+            };
+            wired.push((input - flow, traits.output_port_2(snarl.out_pin(remote), snarl_viewer, snarl)));
+        }
+        let signatures = function.info().signatures();
+        (0..signatures.len()).filter(|&overload| wired.iter().all(|(arg, port)| signatures[overload].args().get(*arg).is_some_and(|arg| ports_compatible(port, &Self::data_port(arg.type_id(), arg.ownership(), snarl_viewer))))).collect()
+    }
+
+    /// The candidates, or every overload when none fit so the ports still show what the function takes.
+    fn shown_overloads(node: NodeId, function: &DynamicFunction<'static>, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Vec<usize> {
+        let candidates = Self::candidates(node, function, snarl_viewer, snarl);
+        if candidates.is_empty() { (0..function.info().signatures().len()).collect() } else { candidates }
+    }
+}
+
+impl Node for FunctionNode {
+    fn inputs(node: NodeId, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> usize
+    where
+        Self: Sized,
+    {
+        Self::flow(node, snarl_viewer, snarl) + Self::function(node, snarl_viewer, snarl).map(|function| Self::arity(&function)).unwrap_or(0)
+    }
+
+    fn outputs(node: NodeId, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> usize
+    where
+        Self: Sized,
+    {
+        Self::flow(node, snarl_viewer, snarl) + Self::function(node, snarl_viewer, snarl).is_some_and(|function| function.info().signatures().iter().any(Self::returns_value)) as usize
+    }
+
+    fn title() -> String
+    where
+        Self: Sized,
+    {
+        "Function".to_string()
+    }
+
+    fn show_header(node: NodeId, ui: &mut Ui, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>)
+    where
+        Self: Sized,
+    {
+        let Some(name) = Self::name(node, snarl) else {
+            return;
+        };
+        let (label, doc) = {
+            let functions = snarl_viewer.functions.read();
+            let listing = functions.listed().into_iter().find(|listing| listing.function.name().is_some_and(|n| *n == name));
+            (listing.as_ref().map(|listing| listing.label.clone()).unwrap_or_else(|| name.clone()), listing.and_then(|listing| listing.meta).map(|meta| meta.doc).unwrap_or_default())
+        };
+        let Some(function) = Self::function(node, snarl_viewer, snarl) else {
+            ui.label(label).on_hover_text(name);
+            return;
+        };
+        let mut hover = if doc.is_empty() { name } else { format!("{doc}\n\n{name}") };
+        // every overload, with the ones the wired inputs still allow marked
+        let candidates = Self::candidates(node, &function, snarl_viewer, snarl);
+        for (overload, signature) in function.info().signatures().iter().enumerate() {
+            let marker = if candidates.contains(&overload) { "▶" } else { "  " };
+            hover.push_str(&format!("\n{marker} {:?}", PrettyPrintSignatureInfo::new(signature)));
+        }
+        ui.label(label).on_hover_text(hover);
+    }
+
+    fn show_input_port(pin: InPin, ui: &mut Ui, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>)
+    where
+        Self: Sized,
+    {
+        let flow = Self::flow(pin.id.node, snarl_viewer, snarl);
+        if pin.id.input < flow {
+            return;
+        }
+        let Some(function) = Self::function(pin.id.node, snarl_viewer, snarl) else {
+            return;
+        };
+        let meta = function.name().and_then(|name| snarl_viewer.functions.read().meta(name).cloned());
+        let arg_name = match &meta {
+            Some(meta) => meta.args.get(pin.id.input - flow).map(|arg| arg.to_string()),
+            None => {
+                let signatures = function.info().signatures();
+                Self::shown_overloads(pin.id.node, &function, snarl_viewer, snarl).into_iter().find_map(|overload| signatures[overload].args().get(pin.id.input - flow)?.name().map(str::to_string))
+            }
+        };
+        if let Some(arg_name) = arg_name {
+            let port = Self::input_port(pin.clone(), snarl_viewer, snarl);
+            ui.label(&arg_name).on_hover_text(format!("{arg_name}: {}", port_name(&port)));
+        }
+    }
+
+    fn show_output_port(pin: OutPin, ui: &mut Ui, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>)
+    where
+        Self: Sized,
+    {
+        if pin.id.output < Self::flow(pin.id.node, snarl_viewer, snarl) {
+            return;
+        }
+        let Some(function) = Self::function(pin.id.node, snarl_viewer, snarl) else {
+            return;
+        };
+        let Some(returns) = function.name().and_then(|name| snarl_viewer.functions.read().meta(name).map(|meta| meta.returns)).filter(|returns| !returns.is_empty()) else {
+            return;
+        };
+        let port = Self::output_port(pin.clone(), snarl_viewer, snarl);
+        ui.label(returns).on_hover_text(format!("{returns}: {}", port_name(&port)));
+    }
+
+    fn input_port(pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Port
+    where
+        Self: Sized,
+    {
+        let flow = Self::flow(pin.id.node, snarl_viewer, snarl);
+        if pin.id.input < flow {
+            return Port::Flow(0);
+        }
+        let Some(function) = Self::function(pin.id.node, snarl_viewer, snarl) else {
+            return Port::Data(DataType::Blank);
+        };
+        let overloads = Self::shown_overloads(pin.id.node, &function, snarl_viewer, snarl);
+        let signatures = function.info().signatures();
+        Self::merge(overloads.iter().filter_map(|overload| signatures[*overload].args().get(pin.id.input - flow)).map(|arg| (arg.type_id(), arg.ownership())), snarl_viewer)
+    }
+
+    fn output_port(pin: OutPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Port
+    where
+        Self: Sized,
+    {
+        if pin.id.output < Self::flow(pin.id.node, snarl_viewer, snarl) {
+            return Port::Flow(0);
+        }
+        let Some(function) = Self::function(pin.id.node, snarl_viewer, snarl) else {
+            return Port::Data(DataType::Blank);
+        };
+        let overloads = Self::shown_overloads(pin.id.node, &function, snarl_viewer, snarl);
+        let signatures = function.info().signatures();
+        Self::merge(overloads.iter().map(|overload| &signatures[*overload]).filter(|signature| Self::returns_value(signature)).map(|signature| (signature.return_info().type_id(), signature.return_info().ownership())), snarl_viewer)
+    }
+
+    fn node_id(&self) -> NodeId {
+        self.node_id.unwrap()
+    }
+
+    fn set_node_id(&mut self, node: NodeId) {
+        self.node_id.replace(node);
+    }
+
+    fn compile(pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>, bytecode: &mut Vec<Bytecode>, scope_map: &mut HashMap<OutPinId, usize>, stack_ptr: &mut usize, _world: &mut World) -> Option<InPinId>
+    where
+        Self: Sized,
+    {
+        let node = pin.id.node;
+        let name = Self::name(node, snarl)?;
+        let Some(function) = Self::function(node, snarl_viewer, snarl) else {
+            snarl_viewer.diagnostics.error(Location::Node(node), format!("no function named `{name}` is registered"));
+            return None;
+        };
+        let candidates = Self::candidates(node, &function, snarl_viewer, snarl);
+        let signatures = function.info().signatures();
+        let overload = match candidates.as_slice() {
+            [overload] => Some(*overload),
+            [] => {
+                snarl_viewer.diagnostics.error(Location::Node(node), format!("no overload of `{name}` takes the wired inputs"));
+                None
+            }
+            _ => None,
+        };
+        // look every argument up before giving up, so each missing one gets its own diagnostic
+        let flow = Self::flow(node, snarl_viewer, snarl);
+        let arity = overload.map_or(Self::arity(&function), |overload| signatures[overload].arg_count());
+        let args: Vec<Option<Bytecode>> = (flow..flow + arity).map(|input| snarl_viewer.copy_input(InPinId { node, input }, snarl, scope_map)).collect();
+        let args: Option<Vec<Bytecode>> = args.into_iter().collect();
+        if candidates.len() > 1 && args.is_some() {
+            let fitting: Vec<String> = candidates.iter().map(|overload| format!("{:?}", PrettyPrintSignatureInfo::new(&signatures[*overload]))).collect();
+            snarl_viewer.diagnostics.error(Location::Node(node), format!("call to `{name}` is ambiguous, the wired inputs fit {}", fitting.join(" and ")));
+        }
+        let (Some(overload), Some(args)) = (overload, args) else {
+            return None;
+        };
+        // the call consumes its arguments, so the originals stay where other nodes expect them
+        bytecode.extend(args);
+        let returns_value = Self::returns_value(&signatures[overload]);
+        bytecode.push(Bytecode::Call(function.clone(), overload));
+        if returns_value {
+            scope_map.insert(OutPinId { node, output: flow }, *stack_ptr);
+        }
+        *stack_ptr += 1;
+        if flow == 0 {
+            return None;
+        }
+        snarl.out_pin(OutPinId { node, output: 0 }).remotes.first().copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::again::primitive_node::{PrimitiveNode, PrimitiveType};
+    use crate::again::start_node::StartNode;
+    use crate::compiler::compile;
+    use crate::ui::ScriptFunctions;
+    use bevy::prelude::{AppTypeRegistry, IntoFunction};
+
+    fn world() -> World {
+        let mut world = World::new();
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<f32>();
+        registry.write().register::<i32>();
+        registry.write().register::<String>();
+        world.insert_resource(registry);
+        world.init_resource::<ScriptFunctions>();
+        world.resource::<ScriptFunctions>().write().register_freestanding((|value: f32| value * 2.0).into_function().with_name("double").with_overload(|value: i32| value * 2));
+        world
+    }
+
+    /// Start, a primitive and a call to `double` with the primitive wired into it, if there is one.
+    fn call_double(primitive: Option<PrimitiveType>) -> (Snarl<Box<dyn Node>>, NodeId) {
+        let mut snarl: Snarl<Box<dyn Node>> = Snarl::new();
+        let start = snarl.insert_node(egui::Pos2::ZERO, Box::<StartNode>::default());
+        let function = snarl.insert_node(egui::Pos2::ZERO, Box::new(FunctionNode { function: "double".to_string(), ..Default::default() }));
+        match primitive {
+            Some(primitive_type) => {
+                let value = snarl.insert_node(egui::Pos2::ZERO, Box::new(PrimitiveNode { primitive_type, node_id: None }));
+                snarl.connect(OutPinId { node: start, output: 0 }, InPinId { node: value, input: 0 });
+                snarl.connect(OutPinId { node: value, output: 0 }, InPinId { node: function, input: 0 });
+                snarl.connect(OutPinId { node: value, output: 1 }, InPinId { node: function, input: 1 });
+            }
+            None => {
+                snarl.connect(OutPinId { node: start, output: 0 }, InPinId { node: function, input: 0 });
+            }
+        }
+        (snarl, function)
+    }
+
+    fn candidates(world: &World, primitive: Option<PrimitiveType>) -> Vec<usize> {
+        let (mut snarl, node) = call_double(primitive);
+        let mut viewer = Viewer::new(world.resource::<AppTypeRegistry>().clone(), world.resource::<ScriptFunctions>().clone(), &mut snarl);
+        let function = FunctionNode::function(node, &viewer, &snarl).unwrap();
+        FunctionNode::candidates(node, &function, &mut viewer, &mut snarl)
+    }
+
+    #[test]
+    fn wired_inputs_narrow_down_the_overloads() {
+        let world = world();
+        assert_eq!(candidates(&world, None), [0, 1]);
+        assert_eq!(candidates(&world, Some(PrimitiveType::F32(1.0))), [0]);
+        assert_eq!(candidates(&world, Some(PrimitiveType::I32(1))), [1]);
+        assert!(candidates(&world, Some(PrimitiveType::String("one".to_string()))).is_empty());
+    }
+
+    #[test]
+    fn calls_compile_to_the_one_overload_that_fits() {
+        let mut world = world();
+        let compiled = |world: &mut World, primitive| {
+            let (mut snarl, node) = call_double(primitive);
+            let mut viewer = Viewer::new(world.resource::<AppTypeRegistry>().clone(), world.resource::<ScriptFunctions>().clone(), &mut snarl);
+            let entries = compile(world, &mut viewer, &mut snarl);
+            (entries.map(|entries| entries[0].bytecode.iter().find_map(|op| if let Bytecode::Call(_, overload) = op { Some(*overload) } else { None })), viewer.diagnostics.for_node(node).count())
+        };
+        assert_eq!(compiled(&mut world, Some(PrimitiveType::I32(1))), (Some(Some(1)), 0));
+        assert_eq!(compiled(&mut world, Some(PrimitiveType::String("one".to_string()))).1, 1);
+        assert_eq!(compiled(&mut world, None).1, 1);
+    }
+}
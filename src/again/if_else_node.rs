@@ -0,0 +1,99 @@
+use crate::Bytecode;
+use crate::again::{DataType, Node, Port, TypeData, Viewer};
+use crate::compiler::compile_scope;
+use bevy::prelude::World;
+use bevy::reflect::Typed;
+use bevy::reflect::func::args::Ownership;
+use egui::Ui;
+use egui_snarl::{InPin, InPinId, NodeId, OutPin, OutPinId, Snarl};
+use std::collections::HashMap;
+
+/// Runs one of two arms depending on a `bool`, then carries on from done.
+#[derive(Default)]
+pub struct IfElseNode {
+    node_id: Option<NodeId>,
+}
+
+impl Node for IfElseNode {
+    fn inputs(_node: NodeId, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> usize
+    where
+        Self: Sized,
+    {
+        2
+    }
+
+    fn outputs(_node: NodeId, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> usize
+    where
+        Self: Sized,
+    {
+        3
+    }
+
+    fn title() -> String
+    where
+        Self: Sized,
+    {
+        "If Else".to_string()
+    }
+
+    fn show_output_port(pin: OutPin, ui: &mut Ui, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>)
+    where
+        Self: Sized,
+    {
+        ui.label(match pin.id.output {
+            0 => "true",
+            1 => "false",
+            _ => "done",
+        });
+    }
+
+    fn input_port(pin: InPin, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> Port
+    where
+        Self: Sized,
+    {
+        match pin.id.input {
+            0 => Port::Flow(0),
+            _ => Port::Data(DataType::Data(TypeData(bool::type_info(), Ownership::Owned))),
+        }
+    }
+
+    fn output_port(pin: OutPin, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> Port
+    where
+        Self: Sized,
+    {
+        // each arm is a scope of its own, so values from one can't be used in the other or after both
+        match pin.id.output {
+            0 => Port::Flow(1),
+            1 => Port::Flow(2),
+            _ => Port::Flow(0),
+        }
+    }
+
+    fn node_id(&self) -> NodeId {
+        self.node_id.unwrap()
+    }
+
+    fn set_node_id(&mut self, node: NodeId) {
+        self.node_id.replace(node);
+    }
+
+    fn compile(pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>, bytecode: &mut Vec<Bytecode>, scope_map: &mut HashMap<OutPinId, usize>, stack_ptr: &mut usize, world: &mut World) -> Option<InPinId>
+    where
+        Self: Sized,
+    {
+        let node = pin.id.node;
+        let condition = snarl_viewer.data_input(InPinId { node, input: 1 }, snarl, scope_map)?;
+        bytecode.push(Bytecode::Dup(condition));
+        let branch = bytecode.len();
+        bytecode.push(Bytecode::JumpIfNot(usize::MAX));
+        let then = snarl.out_pin(OutPinId { node, output: 0 }).remotes.first().copied();
+        compile_scope(then, snarl_viewer, snarl, bytecode, scope_map, stack_ptr, world);
+        let skip = bytecode.len();
+        bytecode.push(Bytecode::Jump(usize::MAX));
+        bytecode[branch] = Bytecode::JumpIfNot(bytecode.len());
+        let otherwise = snarl.out_pin(OutPinId { node, output: 1 }).remotes.first().copied();
+        compile_scope(otherwise, snarl_viewer, snarl, bytecode, scope_map, stack_ptr, world);
+        bytecode[skip] = Bytecode::Jump(bytecode.len());
+        snarl.out_pin(OutPinId { node, output: 2 }).remotes.first().copied()
+    }
+}
@@ -0,0 +1,131 @@
+use crate::Bytecode;
+use crate::again::{DataType, Node, Port, Scope, Viewer};
+use crate::compiler::{compile_scope, leave_scope};
+use crate::diagnostics::Location;
+use bevy::prelude::World;
+use egui::Ui;
+use egui_snarl::{InPin, InPinId, NodeId, OutPin, OutPinId, Snarl};
+use std::collections::HashMap;
+
+/// Runs some with the value if an optional query term found its component and none if it didn't, then carries on from done.
+#[derive(Default)]
+pub struct IfSomeNode {
+    node_id: Option<NodeId>,
+}
+
+impl IfSomeNode {
+    /// The value inside the wired optional, `None` while nothing optional is wired.
+    fn value(node: NodeId, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Option<DataType> {
+        match snarl_viewer.wired_port(InPinId { node, input: 1 }, snarl) {
+            Some(Port::Data(DataType::Iterator(items))) if items.len() == 1 => items.into_iter().next(),
+            _ => None,
+        }
+    }
+}
+
+impl Node for IfSomeNode {
+    fn inputs(_node: NodeId, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> usize
+    where
+        Self: Sized,
+    {
+        2
+    }
+
+    fn outputs(_node: NodeId, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> usize
+    where
+        Self: Sized,
+    {
+        4
+    }
+
+    fn title() -> String
+    where
+        Self: Sized,
+    {
+        "If Some".to_string()
+    }
+
+    fn show_output_port(pin: OutPin, ui: &mut Ui, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>)
+    where
+        Self: Sized,
+    {
+        if let Some(label) = ["some", "none", "done"].get(pin.id.output) {
+            ui.label(*label);
+        }
+    }
+
+    fn input_port(pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Port
+    where
+        Self: Sized,
+    {
+        if pin.id.input == 0 {
+            return Port::Flow(0);
+        }
+        match Self::value(pin.id.node, snarl_viewer, snarl) {
+            Some(value) => Port::Data(DataType::Iterator(vec![value])),
+            None => Port::Data(DataType::Blank),
+        }
+    }
+
+    fn output_port(pin: OutPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Port
+    where
+        Self: Sized,
+    {
+        match pin.id.output {
+            0 => Port::Flow(1),
+            1 => Port::Flow(2),
+            2 => Port::Flow(0),
+            _ => Port::Data(Self::value(pin.id.node, snarl_viewer, snarl).unwrap_or(DataType::Blank)),
+        }
+    }
+
+    fn output_scope(pin: OutPin, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> Scope
+    where
+        Self: Sized,
+    {
+        // the value only exists in the some arm
+        if pin.id.output == 3 { 1 } else { 0 }
+    }
+
+    fn node_id(&self) -> NodeId {
+        self.node_id.unwrap()
+    }
+
+    fn set_node_id(&mut self, node: NodeId) {
+        self.node_id.replace(node);
+    }
+
+    fn compile(pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>, bytecode: &mut Vec<Bytecode>, scope_map: &mut HashMap<OutPinId, usize>, stack_ptr: &mut usize, world: &mut World) -> Option<InPinId>
+    where
+        Self: Sized,
+    {
+        let node = pin.id.node;
+        let optional = snarl_viewer.data_input(InPinId { node, input: 1 }, snarl, scope_map)?;
+        if Self::value(node, snarl_viewer, snarl).is_none() {
+            snarl_viewer.diagnostics.error(Location::Input(InPinId { node, input: 1 }), "expected an optional, like an `Option<&T>` query term");
+            return None;
+        }
+        // an optional is an iterator that yields its value at most once, stepping it once tells the arms apart
+        bytecode.push(Bytecode::Mut(optional));
+        *stack_ptr += 1;
+        let base = *stack_ptr;
+        bytecode.push(Bytecode::NextMut);
+        let none = bytecode.len();
+        bytecode.push(Bytecode::Jump(usize::MAX));
+        bytecode.push(Bytecode::ListBreakdown(1));
+        scope_map.insert(OutPinId { node, output: 3 }, *stack_ptr);
+        *stack_ptr += 1;
+        let some = snarl.out_pin(OutPinId { node, output: 0 }).remotes.first().copied();
+        compile_scope(some, snarl_viewer, snarl, bytecode, scope_map, stack_ptr, world);
+        leave_scope(base - 1, bytecode, scope_map, stack_ptr);
+        let skip = bytecode.len();
+        bytecode.push(Bytecode::Jump(usize::MAX));
+        bytecode[none] = Bytecode::Jump(bytecode.len());
+        // the other arm already forgot the handle, this one pops it before running
+        bytecode.push(Bytecode::Pop);
+        let otherwise = snarl.out_pin(OutPinId { node, output: 1 }).remotes.first().copied();
+        compile_scope(otherwise, snarl_viewer, snarl, bytecode, scope_map, stack_ptr, world);
+        bytecode[skip] = Bytecode::Jump(bytecode.len());
+        snarl.out_pin(OutPinId { node, output: 2 }).remotes.first().copied()
+    }
+}
@@ -1,23 +1,34 @@
-mod apply_node;
-mod ownership_node;
-mod primitive_node;
-mod start_node;
+pub(crate) mod apply_node;
+pub(crate) mod breakdown_node;
+pub(crate) mod buildup_node;
+pub(crate) mod for_node;
+pub(crate) mod function_node;
+pub(crate) mod if_else_node;
+pub(crate) mod if_some_node;
+pub(crate) mod ownership_node;
+mod palette;
+pub(crate) mod primitive_node;
+pub(crate) mod query_node;
+pub(crate) mod self_node;
+pub(crate) mod start_node;
+pub(crate) mod variable_node;
 
 use crate::Bytecode;
-use crate::again::apply_node::ApplyNode;
-use crate::again::ownership_node::OwnershipNode;
-use crate::again::primitive_node::PrimitiveNode;
 use crate::again::start_node::StartNode;
-use crate::ui::split_u64_to_u8s;
+use crate::again::variable_node::GraphVariable;
+use crate::diagnostics::{Diagnostics, Location};
+use crate::graph_file::GraphFile;
+use crate::history::Edits;
+use crate::ui::{ScriptFunctions, split_u64_to_u8s};
 use bevy::prelude::{AppTypeRegistry, World};
+use bevy::reflect::TypeInfo;
 use bevy::reflect::func::args::Ownership;
-use bevy::reflect::{DynamicTypePath, TypeInfo};
-use egui::{Color32, Pos2, Ui};
-use egui_snarl::ui::{PinInfo, SnarlPin, SnarlViewer, WireStyle};
+use egui::{Color32, Frame, Pos2, Stroke, Ui};
+use egui_snarl::ui::{AnyPins, PinInfo, SnarlPin, SnarlViewer, WireStyle};
 use egui_snarl::{InPin, InPinId, NodeId, OutPin, OutPinId, Snarl};
 use std::any::Any;
-use std::collections::HashMap;
-use std::fmt::{Display, Formatter, Write};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
 use std::hash::{DefaultHasher, Hash, Hasher};
 // All input flow nodes should be pure RED unless all data inputs are satifised
 // Data input types must match or connections do not work
@@ -37,11 +48,13 @@ pub enum DataType {
     Blank,
     Data(TypeData),
     OwnershipOnly(Ownership),
+    /// Something a loop steps through, yielding a list of these on every step, like a query.
+    Iterator(Vec<DataType>),
 }
 
-impl Into<PinInfo> for DataType {
-    fn into(self) -> PinInfo {
-        match self {
+impl From<DataType> for PinInfo {
+    fn from(data_type: DataType) -> Self {
+        match data_type {
             DataType::Blank => PinInfo::circle().with_fill(Color32::WHITE),
             DataType::Data(data) => {
                 let mut hasher = DefaultHasher::new();
@@ -61,12 +74,18 @@ impl Into<PinInfo> for DataType {
                 Ownership::Owned => PinInfo::circle(),
             }
             .with_fill(Color32::WHITE),
+            DataType::Iterator(items) => {
+                let mut hasher = DefaultHasher::new();
+                port_name(&Port::Data(DataType::Iterator(items))).hash(&mut hasher);
+                let (r, g, b) = split_u64_to_u8s(hasher.finish());
+                PinInfo::square().with_fill(Color32::from_rgb(r, g, b))
+            }
         }
     }
 }
 
 #[derive(Clone, Debug)]
-pub struct TypeData(TypeInfo, Ownership);
+pub struct TypeData(&'static TypeInfo, Ownership);
 
 impl Display for TypeData {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
@@ -102,13 +121,17 @@ impl PartialEq for Port {
 /// This is an ever-incrementing value that determines the scope of a flow node.
 /// For a for loop for example, it increments when inside it, and then decrements when it ends
 /// But the next for loop it is actually the current scope + 2 instead of reusing the value
+///
+/// Nodes only ever return scopes relative to themselves: `0` is the scope the node itself runs in,
+/// anything else is one of the scopes the node opens, like a loop body or a branch arm.
+/// [`Viewer`] turns those into the ever-incrementing ids by walking the flow from each start node.
 pub type Scope = usize;
 
 pub trait Node: Node2 + Any {
-    fn inputs(node: NodeId, snarl_viewer: &mut Viewer, snarl: &Snarl<Box<dyn Node>>) -> usize
+    fn inputs(node: NodeId, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> usize
     where
         Self: Sized;
-    fn outputs(node: NodeId, snarl_viewer: &mut Viewer, snarl: &Snarl<Box<dyn Node>>) -> usize
+    fn outputs(node: NodeId, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> usize
     where
         Self: Sized;
 
@@ -121,12 +144,12 @@ pub trait Node: Node2 + Any {
     {
         ui.label(Self::title());
     }
-    fn show_input_port(pin: InPin, ui: &mut Ui, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>)
+    fn show_input_port(_pin: InPin, _ui: &mut Ui, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>)
     where
         Self: Sized,
     {
     }
-    fn show_output_port(pin: OutPin, ui: &mut Ui, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>)
+    fn show_output_port(_pin: OutPin, _ui: &mut Ui, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>)
     where
         Self: Sized,
     {
@@ -137,6 +160,13 @@ pub trait Node: Node2 + Any {
     fn output_port(pin: OutPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Port
     where
         Self: Sized;
+    /// The scope a data output is produced in, `0` unless it only exists inside one of the scopes this node opens.
+    fn output_scope(_pin: OutPin, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> Scope
+    where
+        Self: Sized,
+    {
+        0
+    }
     fn node_id(&self) -> NodeId;
     fn set_node_id(&mut self, node: NodeId);
     fn compile(pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>, bytecode: &mut Vec<Bytecode>, scope_map: &mut HashMap<OutPinId, usize>, stack_ptr: &mut usize, world: &mut World) -> Option<InPinId>
@@ -153,10 +183,10 @@ impl dyn Node {
     }
 }
 
-pub(crate) trait Node2 {
-    fn inputs_2(&self, node: NodeId, snarl_viewer: &mut Viewer, snarl: &Snarl<Box<dyn Node>>) -> usize;
+pub trait Node2 {
+    fn inputs_2(&self, node: NodeId, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> usize;
 
-    fn outputs_2(&self, node: NodeId, snarl_viewer: &mut Viewer, snarl: &Snarl<Box<dyn Node>>) -> usize;
+    fn outputs_2(&self, node: NodeId, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> usize;
 
     fn title_2(&self) -> String;
     fn show_header_2(&self, node: NodeId, ui: &mut Ui, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>);
@@ -164,16 +194,19 @@ pub(crate) trait Node2 {
     fn show_output_port_2(&self, pin: OutPin, ui: &mut Ui, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>);
     fn input_port_2(&self, pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Port;
     fn output_port_2(&self, pin: OutPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Port;
+    fn output_scope_2(&self, pin: OutPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Scope;
+
+    fn compile_2(&self, pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>, bytecode: &mut Vec<Bytecode>, scope_map: &mut HashMap<OutPinId, usize>, stack_ptr: &mut usize, world: &mut World) -> Option<InPinId>;
 
     fn get_traits(&self) -> Box<dyn Node2>;
 }
 
 impl<T: 'static + Node + Default> Node2 for T {
-    fn inputs_2(&self, node: NodeId, snarl_viewer: &mut Viewer, snarl: &Snarl<Box<dyn Node>>) -> usize {
+    fn inputs_2(&self, node: NodeId, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> usize {
         T::inputs(node, snarl_viewer, snarl)
     }
 
-    fn outputs_2(&self, node: NodeId, snarl_viewer: &mut Viewer, snarl: &Snarl<Box<dyn Node>>) -> usize {
+    fn outputs_2(&self, node: NodeId, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> usize {
         T::outputs(node, snarl_viewer, snarl)
     }
 
@@ -201,6 +234,14 @@ impl<T: 'static + Node + Default> Node2 for T {
         T::output_port(pin, snarl_viewer, snarl)
     }
 
+    fn output_scope_2(&self, pin: OutPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Scope {
+        T::output_scope(pin, snarl_viewer, snarl)
+    }
+
+    fn compile_2(&self, pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>, bytecode: &mut Vec<Bytecode>, scope_map: &mut HashMap<OutPinId, usize>, stack_ptr: &mut usize, world: &mut World) -> Option<InPinId> {
+        T::compile(pin, snarl_viewer, snarl, bytecode, scope_map, stack_ptr, world)
+    }
+
     fn get_traits(&self) -> Box<dyn Node2> {
         Box::new(T::default())
     }
@@ -210,6 +251,273 @@ pub struct Viewer {
     pub(crate) inputs_list: HashMap<NodeId, usize>,
     pub(crate) outputs_list: HashMap<NodeId, usize>,
     pub registry: AppTypeRegistry,
+    /// What the last compile found wrong, drawn on the nodes it points at.
+    pub diagnostics: Diagnostics,
+    /// For every node the flow reaches, the scope it runs in followed by the scopes enclosing it.
+    pub(crate) scopes: HashMap<NodeId, Vec<Scope>>,
+    /// The id given to each scope a node opens, keyed by the node and the relative scope of its flow output.
+    pub(crate) child_scopes: HashMap<(NodeId, Scope), Scope>,
+    /// The last copied selection, offered by the graph menu.
+    pub clipboard: Option<GraphFile>,
+    pub functions: ScriptFunctions,
+    /// The nodes the entry being compiled already ran, to catch flow that loops back.
+    pub(crate) compiled: HashSet<NodeId>,
+    /// The node each instruction of the entry being compiled came from.
+    pub(crate) compiled_from: HashMap<usize, NodeId>,
+    /// The pure nodes being compiled, innermost last, to catch values that feed back into themselves.
+    pub(crate) compiling_pure: Vec<NodeId>,
+    /// The variables the last compile found in the graph, in the order their slots follow [`SELF_SLOT`](crate::compiler::SELF_SLOT).
+    pub(crate) variables: Vec<GraphVariable>,
+    /// Where each variable sits on the stack, by name.
+    pub(crate) variable_slots: HashMap<String, usize>,
+    /// What the editor changed in the graph this frame, for the undo history.
+    pub edits: Edits,
+}
+
+impl Viewer {
+    pub fn new(registry: AppTypeRegistry, functions: ScriptFunctions, snarl: &mut Snarl<Box<dyn Node>>) -> Self {
+        let mut viewer = Viewer {
+            registry,
+            functions,
+            inputs_list: Default::default(),
+            outputs_list: Default::default(),
+            diagnostics: Default::default(),
+            scopes: Default::default(),
+            child_scopes: Default::default(),
+            clipboard: None,
+            compiled: Default::default(),
+            compiled_from: Default::default(),
+            compiling_pure: Default::default(),
+            variables: Default::default(),
+            variable_slots: Default::default(),
+            edits: Default::default(),
+        };
+        for (id, node) in snarl.nodes_ids_mut() {
+            node.set_node_id(id);
+        }
+        let mut input_list = HashMap::new();
+        let mut output_list = HashMap::new();
+        let nodes: Vec<(NodeId, Box<dyn Node2>)> = snarl.node_ids().map(|(id, node)| (id, node.get_traits())).collect();
+        for (id, traits) in nodes {
+            input_list.insert(id, traits.inputs_2(id, &mut viewer, snarl));
+            output_list.insert(id, traits.outputs_2(id, &mut viewer, snarl));
+        }
+        viewer.inputs_list = input_list;
+        viewer.outputs_list = output_list;
+        viewer.compute_scopes(snarl);
+        viewer
+    }
+
+    fn compute_scopes(&mut self, snarl: &mut Snarl<Box<dyn Node>>) {
+        let mut next_scope: Scope = 0;
+        let mut pending: Vec<(NodeId, Vec<Scope>)> = vec![];
+        for (id, node) in snarl.node_ids() {
+            if node.downcast::<StartNode>().is_some() {
+                pending.push((id, vec![next_scope]));
+                next_scope += 1;
+            }
+        }
+        while let Some((node, chain)) = pending.pop() {
+            // a node reached along several paths only sees what all of them have in common
+            let chain = match self.scopes.get(&node) {
+                Some(existing) => {
+                    let common = existing.iter().rev().zip(chain.iter().rev()).take_while(|(a, b)| a == b).count();
+                    if common == existing.len() {
+                        continue;
+                    }
+                    existing[existing.len() - common..].to_vec()
+                }
+                None => chain,
+            };
+            self.scopes.insert(node, chain.clone());
+            let Some(traits) = snarl.get_node(node).map(|node| node.get_traits()) else {
+                continue;
+            };
+            for output in 0..traits.outputs_2(node, self, snarl) {
+                let out_pin = snarl.out_pin(OutPinId { node, output });
+                let Port::Flow(relative) = traits.output_port_2(out_pin.clone(), self, snarl) else {
+                    continue;
+                };
+                let chain = if relative == 0 {
+                    chain.clone()
+                } else {
+                    let scope = *self.child_scopes.entry((node, relative)).or_insert_with(|| {
+                        next_scope += 1;
+                        next_scope - 1
+                    });
+                    std::iter::once(scope).chain(chain.iter().copied()).collect()
+                };
+                for remote in &out_pin.remotes {
+                    pending.push((remote.node, chain.clone()));
+                }
+            }
+        }
+    }
+
+    /// The scope the value of `pin` lives in, if the flow reaches its node.
+    pub fn data_scope(&mut self, pin: OutPinId, snarl: &mut Snarl<Box<dyn Node>>) -> Option<Scope> {
+        let own = *self.scopes.get(&pin.node)?.first()?;
+        let traits = snarl.get_node(pin.node)?.get_traits();
+        match traits.output_scope_2(snarl.out_pin(pin), self, snarl) {
+            0 => Some(own),
+            relative => self.child_scopes.get(&(pin.node, relative)).copied(),
+        }
+    }
+
+    /// Whether `node` has no flow pins, so it runs wherever one of its values is used instead of where the flow reaches it.
+    pub fn is_pure(&mut self, node: NodeId, snarl: &mut Snarl<Box<dyn Node>>) -> bool {
+        let Some(traits) = snarl.get_node(node).map(|node| node.get_traits()) else {
+            return false;
+        };
+        let flow_input = (0..traits.inputs_2(node, self, snarl)).any(|input| matches!(traits.input_port_2(snarl.in_pin(InPinId { node, input }), self, snarl), Port::Flow(_)));
+        let flow_output = (0..traits.outputs_2(node, self, snarl)).any(|output| matches!(traits.output_port_2(snarl.out_pin(OutPinId { node, output }), self, snarl), Port::Flow(_)));
+        !flow_input && !flow_output
+    }
+
+    /// Whether the value of `from` can be seen by a node running in `chain`. Values whose scope isn't known yet are allowed, compiling catches those.
+    pub fn visible_in(&mut self, from: OutPinId, chain: &[Scope], snarl: &mut Snarl<Box<dyn Node>>) -> bool {
+        self.visible_through(from, chain, snarl, &mut vec![])
+    }
+
+    fn visible_through(&mut self, from: OutPinId, chain: &[Scope], snarl: &mut Snarl<Box<dyn Node>>, visited: &mut Vec<NodeId>) -> bool {
+        if self.is_pure(from.node, snarl) {
+            // a pure node runs where its value is used, so it sees whatever its inputs can
+            if visited.contains(&from.node) {
+                return true;
+            }
+            visited.push(from.node);
+            let inputs = snarl.get_node(from.node).unwrap().get_traits().inputs_2(from.node, self, snarl);
+            for input in 0..inputs {
+                for remote in snarl.in_pin(InPinId { node: from.node, input }).remotes {
+                    if !self.visible_through(remote, chain, snarl, visited) {
+                        return false;
+                    }
+                }
+            }
+            return true;
+        }
+        match self.data_scope(from, snarl) {
+            Some(scope) => chain.contains(&scope),
+            None => true,
+        }
+    }
+
+    /// Whether the value of `from` can be wired into node `to`.
+    pub fn visible(&mut self, from: OutPinId, to: NodeId, snarl: &mut Snarl<Box<dyn Node>>) -> bool {
+        match self.scopes.get(&to).cloned() {
+            Some(chain) => self.visible_in(from, &chain, snarl),
+            None => true,
+        }
+    }
+
+    /// The scopes a node would run in if its flow input were wired to `from`.
+    fn chain_through(&mut self, from: &OutPin, snarl: &mut Snarl<Box<dyn Node>>) -> Option<Vec<Scope>> {
+        let chain = self.scopes.get(&from.id.node)?.clone();
+        let traits = snarl.get_node(from.id.node)?.get_traits();
+        match traits.output_port_2(from.clone(), self, snarl) {
+            Port::Flow(0) => Some(chain),
+            // a scope that hasn't been entered yet has no values in it
+            Port::Flow(relative) => Some(std::iter::once(self.child_scopes.get(&(from.id.node, relative)).copied().unwrap_or(Scope::MAX)).chain(chain).collect()),
+            Port::Data(_) => None,
+        }
+    }
+
+    /// Whether every data input of `node` is wired to a value `node` can see.
+    fn inputs_visible(&mut self, node: NodeId, chain: Option<Vec<Scope>>, snarl: &mut Snarl<Box<dyn Node>>) -> bool {
+        let Some(chain) = chain.or_else(|| self.scopes.get(&node).cloned()) else {
+            return true;
+        };
+        let inputs = self.inputs_list.get(&node).copied().unwrap_or(0);
+        (0..inputs).all(|input| snarl.in_pin(InPinId { node, input }).remotes.iter().all(|remote| self.visible_in(*remote, &chain, snarl)))
+    }
+
+    /// Whether every data input of `node` knows its type, which it needs before flow can be wired into it.
+    fn data_inputs_typed(&mut self, node: NodeId, snarl: &mut Snarl<Box<dyn Node>>) -> bool {
+        let Some(traits) = snarl.get_node(node).map(|node| node.get_traits()) else {
+            return false;
+        };
+        (0..traits.inputs_2(node, self, snarl)).all(|input| !matches!(traits.input_port_2(snarl.in_pin(InPinId { node, input }), self, snarl), Port::Data(DataType::Blank) | Port::Data(DataType::OwnershipOnly(_))))
+    }
+
+    /// The port wired into `pin`, `None` if nothing is.
+    pub fn wired_port(&mut self, pin: InPinId, snarl: &mut Snarl<Box<dyn Node>>) -> Option<Port> {
+        let remote = snarl.in_pin(pin).remotes.first().copied()?;
+        let traits = snarl.get_node(remote.node)?.get_traits();
+        Some(traits.output_port_2(snarl.out_pin(remote), self, snarl))
+    }
+
+    /// Stack position of the value flowing into `pin`, reporting a diagnostic if it is unconnected or hasn't run yet.
+    pub fn data_input(&mut self, pin: InPinId, snarl: &Snarl<Box<dyn Node>>, scope_map: &HashMap<OutPinId, usize>) -> Option<usize> {
+        let Some(remote) = snarl.in_pin(pin).remotes.first().copied() else {
+            self.diagnostics.error(Location::Input(pin), "required input is not connected");
+            return None;
+        };
+        let position = scope_map.get(&remote).copied();
+        if position.is_none() {
+            self.diagnostics.error(Location::Input(pin), "this value isn't available here, the node producing it hasn't run yet");
+        }
+        position
+    }
+
+    /// Copies the value flowing into `pin` to the top of the stack for an instruction that consumes it.
+    /// A `&mut` is reborrowed rather than duplicated, so only one handle to it can be written through.
+    pub fn copy_input(&mut self, pin: InPinId, snarl: &mut Snarl<Box<dyn Node>>, scope_map: &HashMap<OutPinId, usize>) -> Option<Bytecode> {
+        let position = self.data_input(pin, snarl, scope_map)?;
+        Some(match self.wired_port(pin, snarl) {
+            Some(Port::Data(DataType::Data(TypeData(_, Ownership::Mut)))) => Bytecode::Mut(position),
+            _ => Bytecode::Dup(position),
+        })
+    }
+}
+
+/// Whether a wire from `from` to `to` carries a value `to` accepts.
+pub fn ports_compatible(from: &Port, to: &Port) -> bool {
+    match (from, to) {
+        (Port::Flow(_), Port::Flow(_)) => true,
+        (Port::Data(DataType::Data(_)), Port::Data(DataType::Blank)) => true,
+        (Port::Data(DataType::Data(a)), Port::Data(DataType::Data(b))) => a == b,
+        (Port::Data(DataType::Data(a)), Port::Data(DataType::OwnershipOnly(b))) => a.1 == *b,
+        (Port::Data(DataType::Iterator(_)), Port::Data(DataType::Blank)) => true,
+        (Port::Data(DataType::Iterator(a)), Port::Data(DataType::Iterator(b))) => a == b,
+        _ => false,
+    }
+}
+
+fn port_name(port: &Port) -> String {
+    match port {
+        Port::Flow(_) => "flow".to_string(),
+        Port::Data(DataType::Blank) => "anything".to_string(),
+        Port::Data(DataType::Data(type_data)) => type_data.to_string(),
+        Port::Data(DataType::OwnershipOnly(ownership)) => match ownership {
+            Ownership::Ref => "&_".to_string(),
+            Ownership::Mut => "&mut _".to_string(),
+            Ownership::Owned => "_".to_string(),
+        },
+        Port::Data(DataType::Iterator(items)) => format!("iterator of ({})", items.iter().map(|item| port_name(&Port::Data(item.clone()))).collect::<Vec<_>>().join(", ")),
+    }
+}
+
+/// Reports a diagnostic for every connected input of `node` whose wire no longer carries what the input accepts.
+pub fn check_inputs(node: NodeId, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) {
+    let Some(traits) = snarl.get_node(node).map(|node| node.get_traits()) else {
+        return;
+    };
+    for input in 0..traits.inputs_2(node, snarl_viewer, snarl) {
+        let in_pin = snarl.in_pin(InPinId { node, input });
+        for remote in in_pin.remotes.clone() {
+            let Some(from) = snarl.get_node(remote.node).map(|node| node.get_traits()) else {
+                continue;
+            };
+            let from_port = from.output_port_2(snarl.out_pin(remote), snarl_viewer, snarl);
+            let to_port = traits.input_port_2(in_pin.clone(), snarl_viewer, snarl);
+            if !ports_compatible(&from_port, &to_port) {
+                snarl_viewer.diagnostics.error(Location::Input(in_pin.id), format!("type mismatch: expected {}, found {}", port_name(&to_port), port_name(&from_port)));
+            }
+            if matches!(to_port, Port::Data(_)) && !snarl_viewer.visible(remote, node, snarl) {
+                snarl_viewer.diagnostics.error(Location::Input(in_pin.id), "this value is out of scope here");
+            }
+        }
+    }
 }
 
 impl SnarlViewer<Box<dyn Node>> for Viewer {
@@ -217,57 +525,77 @@ impl SnarlViewer<Box<dyn Node>> for Viewer {
         node.title_2()
     }
 
-    fn show_header(&mut self, node: NodeId, inputs: &[InPin], outputs: &[OutPin], ui: &mut Ui, snarl: &mut Snarl<Box<dyn Node>>) {
+    fn show_header(&mut self, node: NodeId, _inputs: &[InPin], _outputs: &[OutPin], ui: &mut Ui, snarl: &mut Snarl<Box<dyn Node>>) {
         let traits = snarl.get_node(node).unwrap().get_traits();
         traits.show_header_2(node, ui, self, snarl);
+        if let Some(severity) = Diagnostics::severity(self.diagnostics.for_node(node)) {
+            ui.colored_label(severity.color(), "⚠").on_hover_text(Diagnostics::tooltip(self.diagnostics.for_node(node)));
+        }
+    }
+
+    fn node_frame(&mut self, default: Frame, node: NodeId, _inputs: &[InPin], _outputs: &[OutPin], _snarl: &Snarl<Box<dyn Node>>) -> Frame {
+        match Diagnostics::severity(self.diagnostics.for_node(node)) {
+            Some(severity) => default.stroke(Stroke::new(2.0, severity.color())),
+            None => default,
+        }
     }
 
     fn inputs(&mut self, node: &Box<dyn Node>) -> usize {
-        *self.inputs_list.get(&node.node_id()).unwrap()
+        // a node the viewer hasn't counted yet shows no pins until it has
+        self.inputs_list.get(&node.node_id()).copied().unwrap_or(0)
     }
 
-    fn has_node_menu(&mut self, node: &Box<dyn Node>) -> bool {
+    fn has_node_menu(&mut self, _node: &Box<dyn Node>) -> bool {
         true
     }
 
-    fn show_node_menu(&mut self, node: NodeId, inputs: &[InPin], outputs: &[OutPin], ui: &mut Ui, snarl: &mut Snarl<Box<dyn Node>>) {
+    fn show_node_menu(&mut self, node: NodeId, _inputs: &[InPin], _outputs: &[OutPin], ui: &mut Ui, snarl: &mut Snarl<Box<dyn Node>>) {
         if ui.button("Delete").clicked() {
-            snarl.remove_node(node);
-            ui.close_menu();
-            return;
+            self.edits.remove_node(snarl, node);
+            ui.close();
         }
     }
 
-    fn has_graph_menu(&mut self, pos: Pos2, snarl: &mut Snarl<Box<dyn Node>>) -> bool {
+    fn has_graph_menu(&mut self, _pos: Pos2, _snarl: &mut Snarl<Box<dyn Node>>) -> bool {
         true
     }
 
     fn show_graph_menu(&mut self, pos: Pos2, ui: &mut Ui, snarl: &mut Snarl<Box<dyn Node>>) {
-        let nodes: Vec<Box<dyn Node>> = vec![Box::new(StartNode::default()), Box::new(PrimitiveNode::default()), Box::new(OwnershipNode::default()), Box::new(ApplyNode::default())];
-        for node in nodes {
-            if ui.button(node.title_2()).clicked() {
-                snarl.insert_node(pos, node);
-                ui.close_menu();
+        self.show_node_palette(pos, ui, snarl);
+        if let Some(clipboard) = &self.clipboard {
+            ui.separator();
+            if ui.button("Paste").clicked() {
+                // only fails on files from another version, which never make it into the clipboard
+                let _ = self.edits.insert_file(snarl, clipboard, pos - clipboard.origin());
+                ui.close();
             }
         }
     }
 
+    fn has_dropped_wire_menu(&mut self, _src_pins: AnyPins, _snarl: &mut Snarl<Box<dyn Node>>) -> bool {
+        true
+    }
+
+    fn show_dropped_wire_menu(&mut self, pos: Pos2, ui: &mut Ui, src_pins: AnyPins, snarl: &mut Snarl<Box<dyn Node>>) {
+        self.show_wire_palette(pos, ui, src_pins, snarl);
+    }
+
     fn show_input(&mut self, pin: &InPin, ui: &mut Ui, snarl: &mut Snarl<Box<dyn Node>>) -> impl SnarlPin + 'static {
-        let node = snarl.get_node(pin.id.node).unwrap();
-        let inputs = self.inputs(node);
-        let traits = node.get_traits();
+        let traits = snarl.get_node(pin.id.node).unwrap().get_traits();
         let ret = match traits.input_port_2(pin.clone(), self, snarl) {
-            //TODO do scope matching
             Port::Flow(_) => {
-                for input in 0..inputs {
-                    let in_pin = snarl.in_pin(InPinId { node: pin.id.node, input });
-                    match traits.input_port_2(in_pin, self, snarl) {
-                        Port::Data(DataType::Blank) | Port::Data(DataType::OwnershipOnly(_)) => return PinInfo::triangle().with_fill(Color32::RED),
-                        _ => {}
-                    }
+                if !self.data_inputs_typed(pin.id.node, snarl) {
+                    return PinInfo::triangle().with_fill(Color32::RED);
+                }
+                if !self.inputs_visible(pin.id.node, None, snarl) {
+                    return PinInfo::triangle().with_fill(Color32::RED);
                 }
                 PinInfo::triangle().with_fill(Color32::GREEN)
             }
+            Port::Data(_) if !pin.remotes.iter().all(|remote| self.visible(*remote, pin.id.node, snarl)) => {
+                ui.colored_label(Color32::RED, "out of scope");
+                PinInfo::circle().with_fill(Color32::RED)
+            }
             Port::Data(data_type) => {
                 if let DataType::Data(type_data) = &data_type {
                     ui.label(type_data.to_string());
@@ -280,16 +608,22 @@ impl SnarlViewer<Box<dyn Node>> for Viewer {
                     };
                     ui.label(s);
                 }
+                if let DataType::Iterator(_) = &data_type {
+                    ui.label(port_name(&Port::Data(data_type.clone())));
+                }
                 data_type.into()
             }
         }
         .with_wire_style(WireStyle::AxisAligned { corner_radius: 30.0 });
         traits.show_input_port_2(pin.clone(), ui, self, snarl);
+        if let Some(severity) = Diagnostics::severity(self.diagnostics.for_input(pin.id)) {
+            ui.colored_label(severity.color(), "⚠").on_hover_text(Diagnostics::tooltip(self.diagnostics.for_input(pin.id)));
+        }
         ret
     }
 
     fn outputs(&mut self, node: &Box<dyn Node>) -> usize {
-        *self.outputs_list.get(&node.node_id()).unwrap()
+        self.outputs_list.get(&node.node_id()).copied().unwrap_or(0)
     }
 
     fn show_output(&mut self, pin: &OutPin, ui: &mut Ui, snarl: &mut Snarl<Box<dyn Node>>) -> impl SnarlPin + 'static {
@@ -297,7 +631,6 @@ impl SnarlViewer<Box<dyn Node>> for Viewer {
         let outputs = self.outputs(node);
         let traits = node.get_traits();
         let ret = match traits.output_port_2(pin.clone(), self, snarl) {
-            //TODO do scope matching
             Port::Flow(_) => {
                 for output in 0..outputs {
                     let out_pin = snarl.out_pin(OutPinId { node: pin.id.node, output });
@@ -320,11 +653,17 @@ impl SnarlViewer<Box<dyn Node>> for Viewer {
                     };
                     ui.label(s);
                 }
+                if let DataType::Iterator(_) = &data_type {
+                    ui.label(port_name(&Port::Data(data_type.clone())));
+                }
                 data_type.into()
             }
         }
         .with_wire_style(WireStyle::AxisAligned { corner_radius: 30.0 });
         traits.show_output_port_2(pin.clone(), ui, self, snarl);
+        if let Some(severity) = Diagnostics::severity(self.diagnostics.for_output(pin.id)) {
+            ui.colored_label(severity.color(), "⚠").on_hover_text(Diagnostics::tooltip(self.diagnostics.for_output(pin.id)));
+        }
         ret
     }
 
@@ -332,26 +671,30 @@ impl SnarlViewer<Box<dyn Node>> for Viewer {
         let from_node = snarl.get_node(from.id.node).unwrap().get_traits();
         let to_node = snarl.get_node(to.id.node).unwrap().get_traits();
         if let Port::Flow(_) = to_node.input_port_2(to.clone(), self, snarl) {
-            let inputs = self.inputs(&snarl.get_node(to.id.node).unwrap());
-            for input in 0..inputs {
-                let in_pin = snarl.in_pin(InPinId { node: to.id.node, input });
-                match to_node.input_port_2(in_pin, self, snarl) {
-                    Port::Data(DataType::Blank) | Port::Data(DataType::OwnershipOnly(_)) => {
-                        return;
-                    }
-                    _ => {}
-                }
+            if !self.data_inputs_typed(to.id.node, snarl) {
+                return;
+            }
+            let chain = self.chain_through(from, snarl);
+            if !self.inputs_visible(to.id.node, chain, snarl) {
+                return;
             }
-            snarl.connect(from.id, to.id);
+            self.edits.connect(snarl, from.id, to.id);
         } else {
-            if match (from_node.output_port_2(from.clone(), self, snarl), to_node.input_port_2(to.clone(), self, snarl)) {
-                ((Port::Data(DataType::Data(_))), Port::Data(DataType::Blank)) => true,
-                (Port::Data(DataType::Data(a)), Port::Data(DataType::Data(b))) => a == b,
-                (Port::Data(DataType::Data(a)), Port::Data(DataType::OwnershipOnly(b))) => a.1 == b,
-                _ => false,
-            } {
-                snarl.connect(from.id, to.id);
+            if ports_compatible(&from_node.output_port_2(from.clone(), self, snarl), &to_node.input_port_2(to.clone(), self, snarl)) && self.visible(from.id, to.id.node, snarl) {
+                self.edits.connect(snarl, from.id, to.id);
             }
         }
     }
+
+    fn disconnect(&mut self, from: &OutPin, to: &InPin, snarl: &mut Snarl<Box<dyn Node>>) {
+        self.edits.disconnect(snarl, from.id, to.id);
+    }
+
+    fn drop_inputs(&mut self, pin: &InPin, snarl: &mut Snarl<Box<dyn Node>>) {
+        self.edits.drop_inputs(snarl, pin.id);
+    }
+
+    fn drop_outputs(&mut self, pin: &OutPin, snarl: &mut Snarl<Box<dyn Node>>) {
+        self.edits.drop_outputs(snarl, pin.id);
+    }
 }
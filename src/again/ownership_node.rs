@@ -29,13 +29,13 @@ impl Default for OwnershipNode {
 }
 
 impl Node for OwnershipNode {
-    fn inputs(node: NodeId, snarl_viewer: &mut Viewer, snarl: &Snarl<Box<dyn Node>>) -> usize
+    fn inputs(_node: NodeId, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> usize
     where
         Self: Sized,
     {
         2
     }
-    fn outputs(node: NodeId, snarl_viewer: &mut Viewer, snarl: &Snarl<Box<dyn Node>>) -> usize
+    fn outputs(_node: NodeId, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> usize
     where
         Self: Sized,
     {
@@ -49,18 +49,21 @@ impl Node for OwnershipNode {
         "Ownership".to_string()
     }
 
-    fn show_header(node: NodeId, ui: &mut Ui, _snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>)
+    fn show_header(node: NodeId, ui: &mut Ui, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>)
     where
         Self: Sized,
     {
-        let ownership = snarl.get_node_mut(node).unwrap().downcast_mut::<OwnershipNode>().unwrap();
+        let ownership = snarl.get_node(node).unwrap().downcast::<OwnershipNode>().unwrap();
         let f = format!("{}", ownership);
-        let ownership = &mut ownership.ownership;
-        ComboBox::from_label("Ownership").selected_text(format!("{}", f).as_str()).show_ui(ui, |ui| {
-            ui.selectable_value(ownership, Ownership::Owned, "clone");
-            ui.selectable_value(ownership, Ownership::Ref, "&");
-            ui.selectable_value(ownership, Ownership::Mut, "&mut");
+        let mut picked = ownership.ownership;
+        ComboBox::from_label("Ownership").selected_text(f.as_str()).show_ui(ui, |ui| {
+            ui.selectable_value(&mut picked, Ownership::Owned, "clone");
+            ui.selectable_value(&mut picked, Ownership::Ref, "&");
+            ui.selectable_value(&mut picked, Ownership::Mut, "&mut");
         });
+        if picked != ownership.ownership {
+            snarl_viewer.edits.set_state(snarl, node, |ownership: &mut OwnershipNode| ownership.ownership = picked);
+        }
     }
 
     fn input_port(pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Port
@@ -85,7 +88,7 @@ impl Node for OwnershipNode {
             return Port::Flow(0);
         }
         let in_pin = snarl.in_pin(InPinId { node: pin.id.node, input: 1 });
-        let ownership = snarl.get_node(pin.id.node).unwrap().downcast::<Self>().unwrap().ownership.clone();
+        let ownership = snarl.get_node(pin.id.node).unwrap().downcast::<Self>().unwrap().ownership;
         match Self::input_port(in_pin, snarl_viewer, snarl) {
             Port::Data(DataType::Data(data)) => Port::Data(DataType::Data(TypeData(data.0, ownership))),
             _ => Port::Data(DataType::OwnershipOnly(ownership)),
@@ -100,12 +103,19 @@ impl Node for OwnershipNode {
         self.node_id.replace(node);
     }
 
-    fn compile(pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>, bytecode: &mut Vec<Bytecode>, scope_map: &mut HashMap<OutPinId, usize>, stack_ptr: &mut usize, world: &mut World) -> Option<InPinId>
+    fn compile(pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>, bytecode: &mut Vec<Bytecode>, scope_map: &mut HashMap<OutPinId, usize>, stack_ptr: &mut usize, _world: &mut World) -> Option<InPinId>
     where
         Self: Sized,
     {
-        let input_port = Self::input_port(snarl.in_pin(InPinId { node: pin.id.node, input: 1 }).clone(), snarl_viewer, snarl);
-
-        todo!()
+        let position = snarl_viewer.data_input(InPinId { node: pin.id.node, input: 1 }, snarl, scope_map)?;
+        let ownership = snarl.get_node(pin.id.node).unwrap().downcast::<OwnershipNode>().unwrap().ownership;
+        bytecode.push(match ownership {
+            Ownership::Owned => Bytecode::Clone(position),
+            Ownership::Ref => Bytecode::Ref(position),
+            Ownership::Mut => Bytecode::Mut(position),
+        });
+        scope_map.insert(OutPinId { node: pin.id.node, output: 1 }, *stack_ptr);
+        *stack_ptr += 1;
+        snarl.out_pin(OutPinId { node: pin.id.node, output: 0 }).remotes.first().copied()
     }
 }
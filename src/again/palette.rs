@@ -0,0 +1,248 @@
+use crate::again::apply_node::ApplyNode;
+use crate::again::breakdown_node::{BreakdownNode, TupleBreakdownNode};
+use crate::again::buildup_node::BuildupNode;
+use crate::again::for_node::ForNode;
+use crate::again::if_else_node::IfElseNode;
+use crate::again::if_some_node::IfSomeNode;
+use crate::again::ownership_node::OwnershipNode;
+use crate::again::primitive_node::PrimitiveNode;
+use crate::again::query_node::QueryNode;
+use crate::again::self_node::SelfNode;
+use crate::again::start_node::StartNode;
+use crate::again::variable_node::VariableNode;
+use crate::again::{Node, Port, Viewer, ports_compatible};
+use crate::graph_file::NodeKind;
+use egui::{CollapsingHeader, Id, Key, Pos2, Response, ScrollArea, Ui};
+use egui_snarl::ui::{AnyPins, SnarlViewer};
+use egui_snarl::{InPinId, NodeId, OutPinId, Snarl};
+
+// The palette lists every node kind and every registered function. Opened by dropping a wire it only offers
+// entries with a pin the wire could connect to, found by building each candidate in a scratch graph and asking it for its ports.
+// With nothing typed in the plain palette, functions are browsed by category instead.
+
+/// Where the search text lives between frames, the viewer doesn't outlive one.
+const SEARCH_ID: &str = "node-palette-search";
+
+/// Something the palette can add to the graph.
+struct PaletteEntry {
+    label: String,
+    /// `/` separated, empty for node kinds.
+    category: String,
+    doc: String,
+    kind: NodeKind,
+}
+
+/// How well `query` matches `candidate`, if every character of it appears in order.
+/// Characters matched in a row or at the start of a word score higher, skipped ones lower.
+pub(crate) fn fuzzy_score(query: &str, candidate: &str) -> Option<i32> {
+    let candidate: Vec<char> = candidate.chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous: Option<usize> = None;
+    for q in query.chars().filter(|c| !c.is_whitespace()) {
+        let found = (position..candidate.len()).find(|&i| candidate[i].to_lowercase().eq(q.to_lowercase()))?;
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !candidate[found - 1].is_alphanumeric() || (candidate[found].is_uppercase() && candidate[found - 1].is_lowercase()) {
+            score += 3;
+        }
+        if previous.is_some() {
+            score -= (found - position) as i32;
+        }
+        previous = Some(found);
+        position = found + 1;
+    }
+    Some(score)
+}
+
+fn entry_button(ui: &mut Ui, entry: &PaletteEntry) -> Response {
+    let button = ui.button(&entry.label);
+    if entry.doc.is_empty() { button } else { button.on_hover_text(&entry.doc) }
+}
+
+/// Lists the entries filed directly under `path` and a collapsible header for each subcategory, returning what got clicked.
+fn show_category(ui: &mut Ui, path: &str, entries: &[&PaletteEntry]) -> Option<NodeKind> {
+    let mut picked = None;
+    let mut subcategories: Vec<&str> = vec![];
+    for entry in entries {
+        let rest = if path.is_empty() { Some(entry.category.as_str()) } else { entry.category.strip_prefix(path).and_then(|rest| rest.strip_prefix('/')) };
+        match rest {
+            Some("") | None => {}
+            Some(rest) => {
+                let subcategory = rest.split('/').next().unwrap();
+                if !subcategories.contains(&subcategory) {
+                    subcategories.push(subcategory);
+                }
+            }
+        }
+    }
+    subcategories.sort();
+    for subcategory in subcategories {
+        let subpath = if path.is_empty() { subcategory.to_string() } else { format!("{path}/{subcategory}") };
+        CollapsingHeader::new(subcategory).id_salt(&subpath).show(ui, |ui| {
+            if let Some(kind) = show_category(ui, &subpath, entries) {
+                picked = Some(kind);
+            }
+        });
+    }
+    for entry in entries.iter().filter(|entry| entry.category == path) {
+        if entry_button(ui, entry).clicked() {
+            picked = Some(entry.kind.clone());
+        }
+    }
+    picked
+}
+
+impl Viewer {
+    fn palette_entries(&self) -> Vec<PaletteEntry> {
+        let nodes: Vec<Box<dyn Node>> = vec![
+            Box::new(StartNode::default()),
+            Box::new(PrimitiveNode::default()),
+            Box::new(OwnershipNode::default()),
+            Box::new(ApplyNode::default()),
+            Box::new(SelfNode::default()),
+            Box::new(QueryNode::default()),
+            Box::new(ForNode::default()),
+            Box::new(IfElseNode::default()),
+            Box::new(IfSomeNode::default()),
+            Box::new(BreakdownNode::default()),
+            Box::new(TupleBreakdownNode::default()),
+            Box::new(BuildupNode::default()),
+            Box::new(VariableNode::default()),
+        ];
+        let mut entries: Vec<PaletteEntry> = nodes
+            .iter()
+            .filter_map(|node| {
+                Some(PaletteEntry {
+                    label: node.title_2(),
+                    category: String::new(),
+                    doc: String::new(),
+                    kind: NodeKind::of(node.as_ref()).ok()?,
+                })
+            })
+            .collect();
+        entries.extend(self.functions.read().listed().into_iter().map(|listing| PaletteEntry {
+            label: listing.label,
+            category: listing.category,
+            doc: listing.meta.map(|meta| meta.doc.to_string()).unwrap_or_default(),
+            kind: NodeKind::Function(listing.function.name().unwrap().to_string()),
+        }));
+        entries
+    }
+
+    /// The first pin of a new `kind` node a wire from `port` can connect to, an input if the wire leaves an output and the other way around.
+    fn compatible_pin(&mut self, kind: &NodeKind, port: &Port, from_output: bool) -> Option<usize> {
+        let mut scratch = Snarl::new();
+        let node = scratch.insert_node(Pos2::ZERO, kind.clone().into_node());
+        scratch.get_node_mut(node).unwrap().set_node_id(node);
+        let traits = scratch.get_node(node).unwrap().get_traits();
+        if !from_output {
+            return (0..traits.outputs_2(node, self, &mut scratch)).find(|&output| ports_compatible(&traits.output_port_2(scratch.out_pin(OutPinId { node, output }), self, &mut scratch), port));
+        }
+        if matches!(port, Port::Flow(_)) && !self.data_inputs_typed(node, &mut scratch) {
+            return None;
+        }
+        (0..traits.inputs_2(node, self, &mut scratch)).find(|&input| ports_compatible(port, &traits.input_port_2(scratch.in_pin(InPinId { node, input }), self, &mut scratch)))
+    }
+
+    /// Searches the palette and inserts the picked entry at `pos`, returning the new node and, when `wire` is given, the pin the wire fits.
+    fn show_palette(&mut self, pos: Pos2, ui: &mut Ui, snarl: &mut Snarl<Box<dyn Node>>, wire: Option<(&Port, bool)>) -> Option<(NodeId, Option<usize>)> {
+        let id = Id::new(SEARCH_ID);
+        let mut query = ui.data_mut(|data| data.get_temp::<String>(id)).unwrap_or_default();
+        ui.text_edit_singleline(&mut query).request_focus();
+        ui.data_mut(|data| data.insert_temp(id, query.clone()));
+        let mut matches: Vec<(i32, PaletteEntry, Option<usize>)> = vec![];
+        for entry in self.palette_entries() {
+            let Some(score) = fuzzy_score(&query, &entry.label) else {
+                continue;
+            };
+            let pin = match wire {
+                Some((port, from_output)) => match self.compatible_pin(&entry.kind, port, from_output) {
+                    Some(pin) => Some(pin),
+                    None => continue,
+                },
+                None => None,
+            };
+            matches.push((score, entry, pin));
+        }
+        matches.sort_by(|(a, a_entry, _), (b, b_entry, _)| b.cmp(a).then_with(|| a_entry.label.cmp(&b_entry.label)));
+        let mut picked = None;
+        if ui.input(|input| input.key_pressed(Key::Enter)) {
+            picked = matches.first().map(|(_, entry, pin)| (entry.kind.clone(), *pin));
+        }
+        ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+            if query.trim().is_empty() && wire.is_none() {
+                let entries: Vec<&PaletteEntry> = matches.iter().map(|(_, entry, _)| entry).collect();
+                if let Some(kind) = show_category(ui, "", &entries) {
+                    picked = Some((kind, None));
+                }
+                return;
+            }
+            for (_, entry, pin) in &matches {
+                ui.horizontal(|ui| {
+                    if entry_button(ui, entry).clicked() {
+                        picked = Some((entry.kind.clone(), *pin));
+                    }
+                    if !entry.category.is_empty() {
+                        ui.weak(&entry.category);
+                    }
+                });
+            }
+            if matches.is_empty() {
+                ui.label("nothing matches");
+            }
+        });
+        let (kind, pin) = picked?;
+        ui.data_mut(|data| data.remove::<String>(id));
+        ui.close();
+        let node = self.edits.insert_node(snarl, pos, kind.into_node());
+        Some((node, pin))
+    }
+
+    pub(crate) fn show_node_palette(&mut self, pos: Pos2, ui: &mut Ui, snarl: &mut Snarl<Box<dyn Node>>) {
+        self.show_palette(pos, ui, snarl, None);
+    }
+
+    /// The palette for a wire dropped on empty space, connecting the wire to whatever gets picked.
+    pub(crate) fn show_wire_palette(&mut self, pos: Pos2, ui: &mut Ui, src_pins: AnyPins, snarl: &mut Snarl<Box<dyn Node>>) {
+        let (outputs, inputs): (Vec<OutPinId>, Vec<InPinId>) = match src_pins {
+            AnyPins::Out(pins) => (pins.to_vec(), vec![]),
+            AnyPins::In(pins) => (vec![], pins.to_vec()),
+        };
+        let port = match (outputs.first(), inputs.first()) {
+            (Some(pin), _) => {
+                let Some(traits) = snarl.get_node(pin.node).map(|node| node.get_traits()) else {
+                    return;
+                };
+                traits.output_port_2(snarl.out_pin(*pin), self, snarl)
+            }
+            (None, Some(pin)) => {
+                let Some(traits) = snarl.get_node(pin.node).map(|node| node.get_traits()) else {
+                    return;
+                };
+                traits.input_port_2(snarl.in_pin(*pin), self, snarl)
+            }
+            (None, None) => return,
+        };
+        let Some((node, Some(pin))) = self.show_palette(pos, ui, snarl, Some((&port, !outputs.is_empty()))) else {
+            return;
+        };
+        // connecting asks the viewer about the new node, which it otherwise only learns about next frame
+        snarl.get_node_mut(node).unwrap().set_node_id(node);
+        let traits = snarl.get_node(node).unwrap().get_traits();
+        let node_inputs = traits.inputs_2(node, self, snarl);
+        let node_outputs = traits.outputs_2(node, self, snarl);
+        self.inputs_list.insert(node, node_inputs);
+        self.outputs_list.insert(node, node_outputs);
+        for output in outputs {
+            let (from, to) = (snarl.out_pin(output), snarl.in_pin(InPinId { node, input: pin }));
+            self.connect(&from, &to, snarl);
+        }
+        for input in inputs {
+            let (from, to) = (snarl.out_pin(OutPinId { node, output: pin }), snarl.in_pin(input));
+            self.connect(&from, &to, snarl);
+        }
+    }
+}
@@ -1,12 +1,11 @@
-#![feature(trait_upcasting)]
 use crate::again::{DataType, Node, Port, TypeData, Viewer};
-use crate::{Bytecode, Value};
+use crate::{Bytecode, Literal};
 use bevy::prelude::{PartialReflect, World};
 use bevy::reflect::DynamicTyped;
 use bevy::reflect::func::args::Ownership;
 use egui::{DragValue, Ui, Widget};
 use egui_snarl::{InPin, InPinId, NodeId, OutPin, OutPinId, Snarl};
-use std::any::Any;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 
@@ -16,7 +15,7 @@ pub struct PrimitiveNode {
     pub node_id: Option<NodeId>,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum PrimitiveType {
     I32(i32),
     F32(f32),
@@ -47,15 +46,24 @@ impl Default for PrimitiveType {
     }
 }
 
+impl PrimitiveNode {
+    /// Gives the node `primitive_type` if its widgets changed it.
+    fn set(node: NodeId, primitive_type: PrimitiveType, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) {
+        if snarl.get_node(node).unwrap().downcast::<PrimitiveNode>().unwrap().primitive_type != primitive_type {
+            snarl_viewer.edits.set_state(snarl, node, |primitive_node: &mut PrimitiveNode| primitive_node.primitive_type = primitive_type);
+        }
+    }
+}
+
 impl Node for PrimitiveNode {
-    fn inputs(node: NodeId, snarl_viewer: &mut Viewer, snarl: &Snarl<Box<dyn Node>>) -> usize
+    fn inputs(_node: NodeId, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> usize
     where
         Self: Sized,
     {
         1
     }
 
-    fn outputs(node: NodeId, snarl_viewer: &mut Viewer, snarl: &Snarl<Box<dyn Node>>) -> usize
+    fn outputs(_node: NodeId, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> usize
     where
         Self: Sized,
     {
@@ -69,27 +77,29 @@ impl Node for PrimitiveNode {
         "Primitive".to_string()
     }
 
-    fn show_header(node: NodeId, ui: &mut Ui, _snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>)
+    fn show_header(node: NodeId, ui: &mut Ui, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>)
     where
         Self: Sized,
     {
-        let node = snarl.get_node_mut(node).unwrap();
-        let primitive_node = node.downcast_mut::<PrimitiveNode>().unwrap();
+        let primitive_node = snarl.get_node(node).unwrap().downcast::<PrimitiveNode>().unwrap();
+        let mut picked = primitive_node.primitive_type.clone();
         egui::ComboBox::from_label("Primitive").selected_text(format!("{}", primitive_node.primitive_type)).show_ui(ui, |ui| {
-            ui.selectable_value(&mut primitive_node.primitive_type, PrimitiveType::I32(0), "i32");
-            ui.selectable_value(&mut primitive_node.primitive_type, PrimitiveType::F32(0.0), "f32");
-            ui.selectable_value(&mut primitive_node.primitive_type, PrimitiveType::String(String::new()), "String");
+            ui.selectable_value(&mut picked, PrimitiveType::I32(0), "i32");
+            ui.selectable_value(&mut picked, PrimitiveType::F32(0.0), "f32");
+            ui.selectable_value(&mut picked, PrimitiveType::String(String::new()), "String");
         });
+        Self::set(node, picked, snarl_viewer, snarl);
     }
 
-    fn show_output_port(pin: OutPin, ui: &mut Ui, _snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>)
+    fn show_output_port(pin: OutPin, ui: &mut Ui, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>)
     where
         Self: Sized,
     {
         if pin.id.output == 0 {
             return;
         }
-        match &mut snarl.get_node_mut(pin.id.node).unwrap().downcast_mut::<PrimitiveNode>().unwrap().primitive_type {
+        let mut value = snarl.get_node(pin.id.node).unwrap().downcast::<PrimitiveNode>().unwrap().primitive_type.clone();
+        match &mut value {
             PrimitiveType::I32(val) => {
                 DragValue::new(val).ui(ui);
             }
@@ -100,6 +110,7 @@ impl Node for PrimitiveNode {
                 ui.text_edit_singleline(val);
             }
         }
+        Self::set(pin.id.node, value, snarl_viewer, snarl);
     }
 
     fn input_port(_pin: InPin, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> Port
@@ -117,9 +128,9 @@ impl Node for PrimitiveNode {
             return Port::Flow(0);
         }
         match &snarl.get_node(pin.id.node).unwrap().downcast::<PrimitiveNode>().unwrap().primitive_type {
-            PrimitiveType::I32(val) => Port::Data(DataType::Data(TypeData(val.reflect_type_info().clone(), Ownership::Owned))),
-            PrimitiveType::F32(val) => Port::Data(DataType::Data(TypeData(val.reflect_type_info().clone(), Ownership::Owned))),
-            PrimitiveType::String(val) => Port::Data(DataType::Data(TypeData(val.reflect_type_info().clone(), Ownership::Owned))),
+            PrimitiveType::I32(val) => Port::Data(DataType::Data(TypeData(val.reflect_type_info(), Ownership::Owned))),
+            PrimitiveType::F32(val) => Port::Data(DataType::Data(TypeData(val.reflect_type_info(), Ownership::Owned))),
+            PrimitiveType::String(val) => Port::Data(DataType::Data(TypeData(val.reflect_type_info(), Ownership::Owned))),
         }
     }
 
@@ -131,14 +142,14 @@ impl Node for PrimitiveNode {
         self.node_id.replace(node);
     }
 
-    fn compile(pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>, bytecode: &mut Vec<Bytecode>, scope_map: &mut HashMap<OutPinId, usize>, stack_ptr: &mut usize, world: &mut World) -> Option<InPinId>
+    fn compile(pin: InPin, _snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>, bytecode: &mut Vec<Bytecode>, scope_map: &mut HashMap<OutPinId, usize>, stack_ptr: &mut usize, _world: &mut World) -> Option<InPinId>
     where
         Self: Sized,
     {
         scope_map.insert(OutPinId { node: pin.id.node, output: 1 }, *stack_ptr);
         let val = snarl.get_node(pin.id.node).unwrap().downcast::<PrimitiveNode>().unwrap().primitive_type.clone();
-        bytecode.push(Bytecode::Push(Value::Box(val.into_reflect())));
+        bytecode.push(Bytecode::Push(Literal::Box(val.into_reflect())));
         *stack_ptr += 1;
-        Some(snarl.out_pin(OutPinId { node: pin.id.node, output: 0 }).remotes.first().unwrap().clone())
+        snarl.out_pin(OutPinId { node: pin.id.node, output: 0 }).remotes.first().copied()
     }
 }
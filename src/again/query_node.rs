@@ -0,0 +1,214 @@
+use crate::again::{DataType, Node, Port, TypeData, Viewer};
+use crate::assembly::{parse_query_filter, parse_query_term};
+use crate::diagnostics::Location;
+use crate::{Bytecode, QueryDataType, QueryFilterType, QueryWrapper};
+use bevy::prelude::{Entity, ReflectComponent, World};
+use bevy::reflect::func::args::Ownership;
+use bevy::reflect::{TypeRegistry, Typed};
+use egui::Ui;
+use egui_snarl::{InPin, InPinId, NodeId, OutPin, OutPinId, Snarl};
+use std::collections::HashMap;
+
+/// Every entity with the picked components, as an iterator a [`ForNode`](crate::again::for_node::ForNode) steps through.
+#[derive(Default)]
+pub struct QueryNode {
+    node_id: Option<NodeId>,
+    /// The terms as the assembler writes them, like `&mut bevy_transform::components::transform::Transform`.
+    pub terms: Vec<String>,
+    /// Each filter as the alternatives an entity has to match one of, like `without my_game::Frozen`. More than one is an `or`.
+    pub filters: Vec<Vec<String>>,
+}
+
+/// Lists every component under each kind of filter, returning the picked one as the assembler writes it.
+fn filter_menu(ui: &mut Ui, type_registry: &TypeRegistry) -> Option<String> {
+    let mut picked = None;
+    for kind in ["with", "without", "changed", "added"] {
+        ui.menu_button(kind, |ui| {
+            for registration in type_registry.iter().filter(|registration| registration.data::<ReflectComponent>().is_some()) {
+                if ui.button(registration.type_info().type_path_table().short_path()).clicked() {
+                    picked = Some(format!("{kind} {}", registration.type_info().type_path()));
+                    ui.close();
+                }
+            }
+        });
+    }
+    picked
+}
+
+/// A term or filter with its type path shortened, for showing on the node, like `Option<&mut Transform>`.
+fn short(text: &str) -> String {
+    let Some((prefix, name)) = text.rsplit_once("::") else {
+        return text.to_string();
+    };
+    let start = prefix.rfind(['<', '&', ' ']).map_or(0, |i| i + 1);
+    format!("{}{name}", &prefix[..start])
+}
+
+impl QueryNode {
+    /// What each step of the query yields, `Blank` for terms naming a type that isn't registered.
+    fn items(node: NodeId, snarl_viewer: &Viewer, snarl: &Snarl<Box<dyn Node>>) -> Vec<DataType> {
+        let type_registry = snarl_viewer.registry.read();
+        let query_node = snarl.get_node(node).unwrap().downcast::<QueryNode>().unwrap();
+        query_node
+            .terms
+            .iter()
+            .map(|term| match parse_query_term(term, &type_registry) {
+                Ok(QueryDataType::Ref(info)) => DataType::Data(TypeData(info, Ownership::Ref)),
+                Ok(QueryDataType::Mut(info)) => DataType::Data(TypeData(info, Ownership::Mut)),
+                Ok(QueryDataType::Entity) => DataType::Data(TypeData(Entity::type_info(), Ownership::Owned)),
+                // yields the component once if the entity has it, see `IfSomeNode`
+                Ok(QueryDataType::OptionalRef(info)) => DataType::Iterator(vec![DataType::Data(TypeData(info, Ownership::Ref))]),
+                Ok(QueryDataType::OptionalMut(info)) => DataType::Iterator(vec![DataType::Data(TypeData(info, Ownership::Mut))]),
+                Err(_) => DataType::Blank,
+            })
+            .collect()
+    }
+}
+
+impl Node for QueryNode {
+    fn inputs(_node: NodeId, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> usize
+    where
+        Self: Sized,
+    {
+        1
+    }
+
+    fn outputs(_node: NodeId, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> usize
+    where
+        Self: Sized,
+    {
+        2
+    }
+
+    fn title() -> String
+    where
+        Self: Sized,
+    {
+        "Query".to_string()
+    }
+
+    fn show_header(node: NodeId, ui: &mut Ui, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>)
+    where
+        Self: Sized,
+    {
+        ui.label(Self::title());
+        let type_registry = snarl_viewer.registry.read();
+        let query_node = snarl.get_node(node).unwrap().downcast::<QueryNode>().unwrap();
+        let mut terms = query_node.terms.clone();
+        let mut filters = query_node.filters.clone();
+        ui.menu_button("+", |ui| {
+            if !terms.iter().any(|term| term == "entity") && ui.button("entity").clicked() {
+                terms.push("entity".to_string());
+                ui.close();
+            }
+            for registration in type_registry.iter().filter(|registration| registration.data::<ReflectComponent>().is_some()) {
+                let type_path = registration.type_info().type_path();
+                let short_path = registration.type_info().type_path_table().short_path();
+                let offered = [(format!("&{short_path}"), format!("&{type_path}")), (format!("&mut {short_path}"), format!("&mut {type_path}")), (format!("Option<&{short_path}>"), format!("Option<&{type_path}>")), (format!("Option<&mut {short_path}>"), format!("Option<&mut {type_path}>"))];
+                for (label, term) in offered {
+                    if !terms.contains(&term) && ui.button(label).clicked() {
+                        terms.push(term);
+                        ui.close();
+                    }
+                }
+            }
+        });
+        // terms aren't pins, the iterator carries all of them, so removing one doesn't move any wires
+        let mut removed = None;
+        for (i, term) in query_node.terms.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(short(term)).on_hover_text(term);
+                if ui.small_button("🗙").clicked() {
+                    removed = Some(i);
+                }
+            });
+        }
+        if let Some(removed) = removed {
+            terms.remove(removed);
+        }
+        ui.menu_button("+ filter", |ui| {
+            if let Some(filter) = filter_menu(ui, &type_registry) {
+                filters.push(vec![filter]);
+            }
+        });
+        let mut removed = None;
+        for (i, alternatives) in filters.iter_mut().enumerate() {
+            ui.horizontal(|ui| {
+                let text = alternatives.iter().map(|filter| short(filter)).collect::<Vec<_>>().join(" | ");
+                ui.label(if alternatives.len() > 1 { format!("or({text})") } else { text }).on_hover_text(alternatives.join(" | "));
+                ui.menu_button("or", |ui| {
+                    if let Some(filter) = filter_menu(ui, &type_registry) {
+                        alternatives.push(filter);
+                    }
+                });
+                if ui.small_button("🗙").clicked() {
+                    removed = Some(i);
+                }
+            });
+        }
+        if let Some(removed) = removed {
+            filters.remove(removed);
+        }
+        if terms != query_node.terms || filters != query_node.filters {
+            snarl_viewer.edits.set_state(snarl, node, |query_node: &mut QueryNode| {
+                query_node.terms = terms;
+                query_node.filters = filters;
+            });
+        }
+    }
+
+    fn input_port(_pin: InPin, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> Port
+    where
+        Self: Sized,
+    {
+        Port::Flow(0)
+    }
+
+    fn output_port(pin: OutPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Port
+    where
+        Self: Sized,
+    {
+        match pin.id.output {
+            0 => Port::Flow(0),
+            _ => Port::Data(DataType::Iterator(Self::items(pin.id.node, snarl_viewer, snarl))),
+        }
+    }
+
+    fn node_id(&self) -> NodeId {
+        self.node_id.unwrap()
+    }
+
+    fn set_node_id(&mut self, node: NodeId) {
+        self.node_id.replace(node);
+    }
+
+    fn compile(pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>, bytecode: &mut Vec<Bytecode>, scope_map: &mut HashMap<OutPinId, usize>, stack_ptr: &mut usize, _world: &mut World) -> Option<InPinId>
+    where
+        Self: Sized,
+    {
+        let node = pin.id.node;
+        let query_node = snarl.get_node(node).unwrap().downcast::<QueryNode>().unwrap();
+        let type_registry = snarl_viewer.registry.read();
+        let terms: Result<Vec<_>, _> = query_node.terms.iter().map(|term| parse_query_term(term, &type_registry)).collect();
+        let filters: Result<Vec<_>, _> = query_node
+            .filters
+            .iter()
+            .map(|alternatives| match alternatives.as_slice() {
+                [filter] => parse_query_filter(filter, &type_registry),
+                alternatives => alternatives.iter().map(|filter| parse_query_filter(filter, &type_registry)).collect::<Result<_, _>>().map(QueryFilterType::Or),
+            })
+            .collect();
+        drop(type_registry);
+        let (terms, filters) = match (terms, filters) {
+            (Ok(terms), Ok(filters)) => (terms, filters),
+            (Err(error), _) | (_, Err(error)) => {
+                snarl_viewer.diagnostics.error(Location::Node(node), error.to_string());
+                return None;
+            }
+        };
+        bytecode.push(Bytecode::Query(QueryWrapper::new(terms).with_filters(filters)));
+        scope_map.insert(OutPinId { node, output: 1 }, *stack_ptr);
+        *stack_ptr += 1;
+        snarl.out_pin(OutPinId { node, output: 0 }).remotes.first().copied()
+    }
+}
@@ -0,0 +1,140 @@
+use crate::again::{DataType, Node, Port, TypeData, Viewer};
+use crate::assembly::parse_query_term;
+use crate::compiler::SELF_SLOT;
+use crate::diagnostics::Location;
+use crate::{Bytecode, QueryWrapper};
+use bevy::prelude::{Entity, ReflectComponent, World};
+use bevy::reflect::Typed;
+use bevy::reflect::func::args::Ownership;
+use egui::Ui;
+use egui_snarl::{InPin, InPinId, NodeId, OutPin, OutPinId, Snarl};
+use std::collections::HashMap;
+
+/// The entity the graph is attached to, and `&mut` access to the components picked on the node.
+/// It has no flow pins, it's compiled wherever one of its outputs is used.
+#[derive(Default)]
+pub struct SelfNode {
+    node_id: Option<NodeId>,
+    /// Type paths of the components, one output each after the entity.
+    pub components: Vec<String>,
+}
+
+impl Node for SelfNode {
+    fn inputs(_node: NodeId, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> usize
+    where
+        Self: Sized,
+    {
+        0
+    }
+
+    fn outputs(node: NodeId, _snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> usize
+    where
+        Self: Sized,
+    {
+        1 + snarl.get_node(node).unwrap().downcast::<SelfNode>().unwrap().components.len()
+    }
+
+    fn title() -> String
+    where
+        Self: Sized,
+    {
+        "Self".to_string()
+    }
+
+    fn show_header(node: NodeId, ui: &mut Ui, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>)
+    where
+        Self: Sized,
+    {
+        ui.label(Self::title());
+        let type_registry = snarl_viewer.registry.read();
+        let self_node = snarl.get_node(node).unwrap().downcast::<SelfNode>().unwrap();
+        let mut added = None;
+        ui.menu_button("+", |ui| {
+            for registration in type_registry.iter().filter(|registration| registration.data::<ReflectComponent>().is_some()) {
+                let type_path = registration.type_info().type_path();
+                if !self_node.components.iter().any(|component| component == type_path) && ui.button(registration.type_info().type_path_table().short_path()).clicked() {
+                    added = Some(type_path.to_string());
+                    ui.close();
+                }
+            }
+        });
+        if let Some(added) = added {
+            snarl_viewer.edits.set_state(snarl, node, |self_node: &mut SelfNode| self_node.components.push(added));
+        }
+    }
+
+    fn show_output_port(pin: OutPin, ui: &mut Ui, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>)
+    where
+        Self: Sized,
+    {
+        if pin.id.output < 1 || !ui.small_button("🗙").clicked() {
+            return;
+        }
+        // the outputs after the removed one shift down, so their wires no longer line up
+        let outputs = 1 + snarl.get_node(pin.id.node).unwrap().downcast::<SelfNode>().unwrap().components.len();
+        for output in pin.id.output..outputs {
+            snarl_viewer.edits.drop_outputs(snarl, OutPinId { node: pin.id.node, output });
+        }
+        snarl_viewer.edits.set_state(snarl, pin.id.node, |self_node: &mut SelfNode| {
+            self_node.components.remove(pin.id.output - 1);
+        });
+    }
+
+    fn input_port(_pin: InPin, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> Port
+    where
+        Self: Sized,
+    {
+        unreachable!()
+    }
+
+    fn output_port(pin: OutPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Port
+    where
+        Self: Sized,
+    {
+        match pin.id.output {
+            0 => Port::Data(DataType::Data(TypeData(Entity::type_info(), Ownership::Owned))),
+            output => {
+                let self_node = snarl.get_node(pin.id.node).unwrap().downcast::<SelfNode>().unwrap();
+                match self_node.components.get(output - 1).and_then(|type_path| snarl_viewer.registry.read().get_with_type_path(type_path).map(|registration| registration.type_info())) {
+                    Some(type_info) => Port::Data(DataType::Data(TypeData(type_info, Ownership::Mut))),
+                    None => Port::Data(DataType::Blank),
+                }
+            }
+        }
+    }
+
+    fn node_id(&self) -> NodeId {
+        self.node_id.unwrap()
+    }
+
+    fn set_node_id(&mut self, node: NodeId) {
+        self.node_id.replace(node);
+    }
+
+    fn compile(pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>, bytecode: &mut Vec<Bytecode>, scope_map: &mut HashMap<OutPinId, usize>, stack_ptr: &mut usize, _world: &mut World) -> Option<InPinId>
+    where
+        Self: Sized,
+    {
+        let node = pin.id.node;
+        // the entity never moves, readers copy it out of its slot
+        scope_map.insert(OutPinId { node, output: 0 }, SELF_SLOT);
+        let components = snarl.get_node(node).unwrap().downcast::<SelfNode>().unwrap().components.clone();
+        // borrowing the components when only the entity is read would only get in the way of queries over them
+        if (1..1 + components.len()).any(|output| !snarl.out_pin(OutPinId { node, output }).remotes.is_empty()) {
+            let terms: Result<Vec<_>, _> = components.iter().map(|type_path| parse_query_term(&format!("&mut {type_path}"), &snarl_viewer.registry.read())).collect();
+            match terms {
+                Ok(terms) => {
+                    bytecode.push(Bytecode::Clone(SELF_SLOT));
+                    bytecode.push(Bytecode::Get(QueryWrapper::new(terms)));
+                    bytecode.push(Bytecode::ListBreakdown(components.len()));
+                    for output in 1..1 + components.len() {
+                        scope_map.insert(OutPinId { node, output }, *stack_ptr);
+                        *stack_ptr += 1;
+                    }
+                }
+                Err(error) => snarl_viewer.diagnostics.error(Location::Node(node), error.to_string()),
+            }
+        }
+        None
+    }
+}
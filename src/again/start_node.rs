@@ -1,27 +1,78 @@
 use crate::Bytecode;
-use crate::again::{Node, Port, Viewer};
-use bevy::prelude::World;
+use crate::again::{DataType, Node, Port, TypeData, Viewer};
+use crate::runtime::ReflectScriptEvent;
+use bevy::prelude::{Entity, ReflectComponent, World};
+use bevy::reflect::func::args::Ownership;
+use bevy::reflect::{TypeInfo, TypeRegistry, Typed};
+use egui::{ComboBox, Ui};
 use egui_snarl::{InPin, InPinId, NodeId, OutPin, OutPinId, Snarl};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+/// When the flow out of a [`StartNode`] runs. Events and components are named by type path.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Entry {
+    /// Runs when the graph is run from the editor.
+    #[default]
+    Run,
+    Startup,
+    Update,
+    FixedUpdate,
+    Event(String),
+    ComponentAdded(String),
+    ComponentRemoved(String),
+}
+
+impl Display for Entry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let short = |type_path: &str| type_path.rsplit_once("::").map(|(_, short)| short.to_string()).unwrap_or_else(|| type_path.to_string());
+        match self {
+            Entry::Run => f.write_str("Start"),
+            Entry::Startup => f.write_str("On Startup"),
+            Entry::Update => f.write_str("On Update"),
+            Entry::FixedUpdate => f.write_str("On FixedUpdate"),
+            Entry::Event(type_path) => write!(f, "On Event<{}>", short(type_path)),
+            Entry::ComponentAdded(type_path) => write!(f, "On Component Added<{}>", short(type_path)),
+            Entry::ComponentRemoved(type_path) => write!(f, "On Component Removed<{}>", short(type_path)),
+        }
+    }
+}
+
+impl Entry {
+    pub fn has_payload(&self) -> bool {
+        matches!(self, Entry::Event(_) | Entry::ComponentAdded(_) | Entry::ComponentRemoved(_))
+    }
+
+    /// The type of the value the program starts with: the event, or the entity the component was added to or removed from.
+    pub fn payload(&self, type_registry: &TypeRegistry) -> Option<&'static TypeInfo> {
+        match self {
+            Entry::Event(type_path) => type_registry.get_with_type_path(type_path).map(|registration| registration.type_info()),
+            Entry::ComponentAdded(_) | Entry::ComponentRemoved(_) => Some(Entity::type_info()),
+            _ => None,
+        }
+    }
+}
 
 #[derive(Default)]
 pub struct StartNode {
     node_id: Option<NodeId>,
+    pub entry: Entry,
 }
 
 impl Node for StartNode {
-    fn inputs(node: NodeId, snarl_viewer: &mut Viewer, snarl: &Snarl<Box<dyn Node>>) -> usize
+    fn inputs(_node: NodeId, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> usize
     where
         Self: Sized,
     {
         0
     }
 
-    fn outputs(node: NodeId, snarl_viewer: &mut Viewer, snarl: &Snarl<Box<dyn Node>>) -> usize
+    fn outputs(node: NodeId, _snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> usize
     where
         Self: Sized,
     {
-        1
+        if snarl.get_node(node).unwrap().downcast::<StartNode>().unwrap().entry.has_payload() { 2 } else { 1 }
     }
 
     fn title() -> String
@@ -31,7 +82,40 @@ impl Node for StartNode {
         "Start".to_string()
     }
 
-    fn input_port(pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Port
+    fn show_header(node: NodeId, ui: &mut Ui, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>)
+    where
+        Self: Sized,
+    {
+        let type_registry = snarl_viewer.registry.read();
+        let start = snarl.get_node(node).unwrap().downcast::<StartNode>().unwrap();
+        let mut picked = start.entry.clone();
+        ComboBox::from_label("Entry").selected_text(start.entry.to_string()).show_ui(ui, |ui| {
+            for entry in [Entry::Run, Entry::Startup, Entry::Update, Entry::FixedUpdate] {
+                let text = entry.to_string();
+                ui.selectable_value(&mut picked, entry, text);
+            }
+            for registration in type_registry.iter() {
+                let type_path = registration.type_info().type_path().to_string();
+                let mut entries = vec![];
+                if registration.data::<ReflectScriptEvent>().is_some() {
+                    entries.push(Entry::Event(type_path.clone()));
+                }
+                if registration.data::<ReflectComponent>().is_some() {
+                    entries.push(Entry::ComponentAdded(type_path.clone()));
+                    entries.push(Entry::ComponentRemoved(type_path));
+                }
+                for entry in entries {
+                    let text = entry.to_string();
+                    ui.selectable_value(&mut picked, entry, text);
+                }
+            }
+        });
+        if picked != start.entry {
+            snarl_viewer.edits.set_state(snarl, node, |start: &mut StartNode| start.entry = picked);
+        }
+    }
+
+    fn input_port(_pin: InPin, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> Port
     where
         Self: Sized,
     {
@@ -42,7 +126,14 @@ impl Node for StartNode {
     where
         Self: Sized,
     {
-        Port::Flow(0)
+        if pin.id.output == 0 {
+            return Port::Flow(0);
+        }
+        let entry = &snarl.get_node(pin.id.node).unwrap().downcast::<StartNode>().unwrap().entry;
+        match entry.payload(&snarl_viewer.registry.read()) {
+            Some(type_info) => Port::Data(DataType::Data(TypeData(type_info, Ownership::Owned))),
+            None => Port::Data(DataType::Blank),
+        }
     }
 
     fn node_id(&self) -> NodeId {
@@ -53,10 +144,15 @@ impl Node for StartNode {
         self.node_id.replace(node);
     }
 
-    fn compile(pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>, bytecode: &mut Vec<Bytecode>, scope_map: &mut HashMap<OutPinId, usize>, stack_ptr: &mut usize, world: &mut World) -> Option<InPinId>
+    fn compile(pin: InPin, _snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>, _bytecode: &mut Vec<Bytecode>, scope_map: &mut HashMap<OutPinId, usize>, stack_ptr: &mut usize, _world: &mut World) -> Option<InPinId>
     where
         Self: Sized,
     {
-        unreachable!()
+        // the payload is already on the stack when the program starts
+        if snarl.get_node(pin.id.node).unwrap().downcast::<StartNode>().unwrap().entry.has_payload() {
+            scope_map.insert(OutPinId { node: pin.id.node, output: 1 }, *stack_ptr);
+            *stack_ptr += 1;
+        }
+        snarl.out_pin(OutPinId { node: pin.id.node, output: 0 }).remotes.first().copied()
     }
 }
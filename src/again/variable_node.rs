@@ -0,0 +1,162 @@
+use crate::Bytecode;
+use crate::again::breakdown_node::show_ownership;
+use crate::again::{DataType, Node, Port, TypeData, Viewer};
+use bevy::prelude::{PartialReflect, ReflectDefault, World};
+use bevy::reflect::func::args::Ownership;
+use bevy::reflect::{TypeInfo, TypeRegistry};
+use egui::{ComboBox, Ui};
+use egui_snarl::{InPin, InPinId, NodeId, OutPin, OutPinId, Snarl};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A value that belongs to the graph rather than to one run of it. It keeps what the graph's programs leave in it
+/// between runs, and across reloads of the graph for as long as its name and type stay the same.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GraphVariable {
+    pub name: String,
+    pub type_path: String,
+}
+
+impl GraphVariable {
+    pub fn type_info(&self, type_registry: &TypeRegistry) -> Option<&'static TypeInfo> {
+        type_registry.get_with_type_path(&self.type_path).map(|registration| registration.type_info())
+    }
+
+    /// What the variable holds before anything sets it, `None` if its type has no registered default.
+    pub fn default_value(&self, type_registry: &TypeRegistry) -> Option<Box<dyn PartialReflect>> {
+        let default = type_registry.get_with_type_path(&self.type_path)?.data::<ReflectDefault>()?;
+        Some(default.default().into_partial_reflect())
+    }
+}
+
+/// Reads or sets a [`GraphVariable`]. Every Variable node with the same name is the same variable.
+/// It has no flow pins, it's compiled wherever its output is used.
+pub struct VariableNode {
+    node_id: Option<NodeId>,
+    pub name: String,
+    /// Type path of the value, which needs a registered default.
+    pub type_path: String,
+    /// A clone of the value, or a borrow to read it or set it through.
+    pub ownership: Ownership,
+}
+
+impl Default for VariableNode {
+    fn default() -> Self {
+        Self { node_id: None, name: String::new(), type_path: String::new(), ownership: Ownership::Ref }
+    }
+}
+
+impl VariableNode {
+    fn get(node: NodeId, snarl: &Snarl<Box<dyn Node>>) -> &VariableNode {
+        snarl.get_node(node).unwrap().downcast::<VariableNode>().unwrap()
+    }
+
+    /// The variable the node refers to.
+    pub fn variable(&self) -> GraphVariable {
+        GraphVariable { name: self.name.clone(), type_path: self.type_path.clone() }
+    }
+}
+
+impl Node for VariableNode {
+    fn inputs(_node: NodeId, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> usize
+    where
+        Self: Sized,
+    {
+        0
+    }
+
+    fn outputs(_node: NodeId, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> usize
+    where
+        Self: Sized,
+    {
+        1
+    }
+
+    fn title() -> String
+    where
+        Self: Sized,
+    {
+        "Variable".to_string()
+    }
+
+    fn show_header(node: NodeId, ui: &mut Ui, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>)
+    where
+        Self: Sized,
+    {
+        ui.label(Self::title());
+        let type_registry = snarl_viewer.registry.read();
+        let variable = snarl.get_node(node).unwrap().downcast::<VariableNode>().unwrap();
+        let mut name = variable.name.clone();
+        ui.text_edit_singleline(&mut name);
+        let mut type_path = variable.type_path.clone();
+        let selected = type_registry.get_with_type_path(&variable.type_path).map_or("pick a type", |registration| registration.type_info().type_path_table().short_path());
+        ComboBox::from_id_salt((node, "type")).selected_text(selected).show_ui(ui, |ui| {
+            for registration in type_registry.iter().filter(|registration| registration.data::<ReflectDefault>().is_some()) {
+                ui.selectable_value(&mut type_path, registration.type_info().type_path().to_string(), registration.type_info().type_path_table().short_path());
+            }
+        });
+        let ownership = show_ownership(node, ui, variable.ownership);
+        if name != variable.name || type_path != variable.type_path || ownership != variable.ownership {
+            snarl_viewer.edits.set_state(snarl, node, |variable: &mut VariableNode| {
+                variable.name = name;
+                variable.type_path = type_path;
+                variable.ownership = ownership;
+            });
+        }
+    }
+
+    fn input_port(_pin: InPin, _snarl_viewer: &mut Viewer, _snarl: &mut Snarl<Box<dyn Node>>) -> Port
+    where
+        Self: Sized,
+    {
+        unreachable!("variables have no inputs")
+    }
+
+    fn output_port(pin: OutPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Port
+    where
+        Self: Sized,
+    {
+        let variable = Self::get(pin.id.node, snarl);
+        match variable.variable().type_info(&snarl_viewer.registry.read()) {
+            Some(info) => Port::Data(DataType::Data(TypeData(info, variable.ownership))),
+            None => Port::Data(DataType::Blank),
+        }
+    }
+
+    fn node_id(&self) -> NodeId {
+        self.node_id.unwrap()
+    }
+
+    fn set_node_id(&mut self, node: NodeId) {
+        self.node_id.replace(node);
+    }
+
+    fn compile(pin: InPin, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>, bytecode: &mut Vec<Bytecode>, scope_map: &mut HashMap<OutPinId, usize>, stack_ptr: &mut usize, _world: &mut World) -> Option<InPinId>
+    where
+        Self: Sized,
+    {
+        let node = pin.id.node;
+        let variable = Self::get(node, snarl);
+        let ownership = variable.ownership;
+        // the compiler gave every variable of the graph its slot before compiling the entry, and reported the ones it couldn't
+        let slot = snarl_viewer.variable_slots.get(&variable.name).copied()?;
+        let output = OutPinId { node, output: 0 };
+        match ownership {
+            // consumers copy what they take, so the slot itself can stand in for a clone
+            Ownership::Owned => {
+                scope_map.insert(output, slot);
+            }
+            Ownership::Ref => {
+                bytecode.push(Bytecode::Ref(slot));
+                scope_map.insert(output, *stack_ptr);
+                *stack_ptr += 1;
+            }
+            Ownership::Mut => {
+                bytecode.push(Bytecode::Mut(slot));
+                scope_map.insert(output, *stack_ptr);
+                *stack_ptr += 1;
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,316 @@
+use bevy::reflect::PartialReflect;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Formatter};
+use std::rc::Rc;
+
+// Every `Value::Ref`/`Value::Mut` the interpreter hands out is a `Handle` registered here.
+// A new borrow is refused if it overlaps a live borrow and either of them is exclusive,
+// unless the live borrow is the one it was derived from (a reborrow).
+// A handle with live reborrows is frozen until they are gone: it can't be written through,
+// and can't be read through while one of them is exclusive.
+// Two borrows overlap if they point into the same root along a common field path,
+// or if the memory they point at intersects.
+
+pub type BorrowId = usize;
+
+#[derive(Debug)]
+pub enum BorrowError {
+    AlreadyBorrowed(String),
+    AlreadyMutablyBorrowed(String),
+    StillBorrowed(String),
+    NotExclusive(String),
+}
+
+impl Display for BorrowError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BorrowError::AlreadyBorrowed(type_path) => write!(f, "`{type_path}` is already borrowed"),
+            BorrowError::AlreadyMutablyBorrowed(type_path) => write!(f, "`{type_path}` is already mutably borrowed"),
+            BorrowError::StillBorrowed(type_path) => write!(f, "`{type_path}` is dropped while still borrowed"),
+            BorrowError::NotExclusive(type_path) => write!(f, "`{type_path}` is behind a shared reference"),
+        }
+    }
+}
+
+impl std::error::Error for BorrowError {}
+
+#[derive(Clone, Debug)]
+struct Place {
+    root: usize,
+    path: Vec<usize>,
+    start: usize,
+    len: usize,
+}
+
+impl Place {
+    fn new(root: usize, path: Vec<usize>, ptr: *const dyn PartialReflect) -> Self {
+        let len = unsafe { std::mem::size_of_val(&*ptr) };
+        Place { root, path, start: ptr as *const () as usize, len }
+    }
+
+    fn overlaps(&self, other: &Place) -> bool {
+        if self.root == other.root && (self.path.starts_with(&other.path) || other.path.starts_with(&self.path)) {
+            return true;
+        }
+        self.len > 0 && other.len > 0 && self.start < other.start + other.len && other.start < self.start + self.len
+    }
+}
+
+struct BorrowRecord {
+    place: Place,
+    exclusive: bool,
+    parent: Option<BorrowId>,
+}
+
+#[derive(Default)]
+struct ArenaInner {
+    borrows: HashMap<BorrowId, BorrowRecord>,
+    next_id: BorrowId,
+}
+
+impl ArenaInner {
+    fn is_ancestor(&self, ancestor: BorrowId, mut of: Option<BorrowId>) -> bool {
+        while let Some(id) = of {
+            if id == ancestor {
+                return true;
+            }
+            of = self.borrows.get(&id).and_then(|record| record.parent);
+        }
+        false
+    }
+
+    /// The error for using `id` while a borrow derived from it is live, with `exclusive` access if written through.
+    fn frozen(&self, id: BorrowId, exclusive: bool, type_path: impl FnOnce() -> String) -> Result<(), BorrowError> {
+        let mut derived = self.borrows.values().filter(|record| self.is_ancestor(id, record.parent));
+        match derived.find(|record| exclusive || record.exclusive) {
+            Some(record) if record.exclusive => Err(BorrowError::AlreadyMutablyBorrowed(type_path())),
+            Some(_) => Err(BorrowError::AlreadyBorrowed(type_path())),
+            None => Ok(()),
+        }
+    }
+}
+
+/// The borrows live during one run of a program.
+#[derive(Clone, Default)]
+pub struct Arena(Rc<RefCell<ArenaInner>>);
+
+impl Arena {
+    fn track(&self, ptr: *mut dyn PartialReflect, place: Place, exclusive: bool, parent: Option<BorrowId>, frame: Option<Rc<Frame>>) -> Result<Handle, BorrowError> {
+        let mut inner = self.0.borrow_mut();
+        for (id, record) in &inner.borrows {
+            if (record.exclusive || exclusive) && record.place.overlaps(&place) && !inner.is_ancestor(*id, parent) {
+                let type_path = unsafe { &*ptr }.reflect_type_path().to_string();
+                return Err(if record.exclusive { BorrowError::AlreadyMutablyBorrowed(type_path) } else { BorrowError::AlreadyBorrowed(type_path) });
+            }
+        }
+        let id = inner.next_id;
+        inner.next_id += 1;
+        inner.borrows.insert(id, BorrowRecord { place, exclusive, parent });
+        Ok(Handle { arena: self.clone(), id, ptr, exclusive, frame })
+    }
+
+    /// Borrows data that isn't reachable through another handle, like a stack value or a component.
+    pub fn borrow_root(&self, ptr: *mut dyn PartialReflect, exclusive: bool) -> Result<Handle, BorrowError> {
+        let place = Place::new(ptr as *const () as usize, vec![], ptr);
+        self.track(ptr, place, exclusive, None, None)
+    }
+
+    /// Borrows the field `field` of `root`, where `ptr` points at that field.
+    pub fn borrow_field(&self, root: *const dyn PartialReflect, field: usize, ptr: *mut dyn PartialReflect, exclusive: bool) -> Result<Handle, BorrowError> {
+        let place = Place::new(root as *const () as usize, vec![field], ptr);
+        self.track(ptr, place, exclusive, None, None)
+    }
+
+    /// Borrows a value returned by a call, keeping the values lent to that call alive as long as the handle.
+    pub fn borrow_returned(&self, ptr: *mut dyn PartialReflect, exclusive: bool, frame: Frame) -> Result<Handle, BorrowError> {
+        let place = Place::new(ptr as *const () as usize, vec![], ptr);
+        let frame = if frame.0.is_empty() { None } else { Some(Rc::new(frame)) };
+        self.track(ptr, place, exclusive, None, frame)
+    }
+
+    /// Whether any live handle still points into `value`.
+    pub fn is_borrowed(&self, value: &dyn PartialReflect) -> bool {
+        let ptr = value as *const dyn PartialReflect;
+        let place = Place::new(ptr as *const () as usize, vec![], ptr);
+        self.0.borrow().borrows.values().any(|record| record.place.overlaps(&place))
+    }
+
+    /// Whether a live exclusive handle points into `value`, so it can't be read.
+    pub fn is_mutably_borrowed(&self, value: &dyn PartialReflect) -> bool {
+        let ptr = value as *const dyn PartialReflect;
+        let place = Place::new(ptr as *const () as usize, vec![], ptr);
+        self.0.borrow().borrows.values().any(|record| record.exclusive && record.place.overlaps(&place))
+    }
+}
+
+/// Owned values lent to a call by reference. They are freed when the call returns,
+/// or once the last handle into a returned reference is gone.
+#[derive(Default)]
+pub struct Frame(Vec<Box<dyn PartialReflect>>);
+
+impl Frame {
+    pub fn lend(&mut self, mut value: Box<dyn PartialReflect>) -> *mut dyn PartialReflect {
+        let ptr = value.as_mut() as *mut dyn PartialReflect;
+        self.0.push(value);
+        ptr
+    }
+}
+
+/// A tracked `&` or `&mut` to reflected data, released from its [`Arena`] when dropped.
+pub struct Handle {
+    arena: Arena,
+    id: BorrowId,
+    ptr: *mut dyn PartialReflect,
+    exclusive: bool,
+    frame: Option<Rc<Frame>>,
+}
+
+impl Handle {
+    pub fn is_exclusive(&self) -> bool {
+        self.exclusive
+    }
+
+    pub fn get(&self) -> &dyn PartialReflect {
+        unsafe { &*self.ptr }
+    }
+
+    /// Where the data is, for finding a field to [`Handle::project`] without checking this handle can be written through.
+    pub fn as_ptr(&self) -> *mut dyn PartialReflect {
+        self.ptr
+    }
+
+    /// The data, unless an exclusive reborrow of it is live.
+    pub fn read(&self) -> Result<&dyn PartialReflect, BorrowError> {
+        self.arena.0.borrow().frozen(self.id, false, || self.get().reflect_type_path().to_string())?;
+        Ok(self.get())
+    }
+
+    pub fn get_mut(&mut self) -> Result<&mut dyn PartialReflect, BorrowError> {
+        if !self.exclusive {
+            return Err(BorrowError::NotExclusive(self.get().reflect_type_path().to_string()));
+        }
+        self.arena.0.borrow().frozen(self.id, true, || self.get().reflect_type_path().to_string())?;
+        Ok(unsafe { &mut *self.ptr })
+    }
+
+    fn place(&self) -> Place {
+        self.arena.0.borrow().borrows.get(&self.id).map(|record| record.place.clone()).unwrap_or_else(|| Place::new(self.ptr as *const () as usize, vec![], self.ptr))
+    }
+
+    /// Borrows the same data again for as long as the new handle lives.
+    pub fn reborrow(&self, exclusive: bool) -> Result<Handle, BorrowError> {
+        if exclusive && !self.exclusive {
+            return Err(BorrowError::NotExclusive(self.get().reflect_type_path().to_string()));
+        }
+        self.arena.track(self.ptr, self.place(), exclusive, Some(self.id), self.frame.clone())
+    }
+
+    /// Borrows the field `field` of this handle's data, where `ptr` points at that field.
+    pub fn project(&self, field: usize, ptr: *mut dyn PartialReflect, exclusive: bool) -> Result<Handle, BorrowError> {
+        if exclusive && !self.exclusive {
+            return Err(BorrowError::NotExclusive(self.get().reflect_type_path().to_string()));
+        }
+        let mut place = self.place();
+        place.path.push(field);
+        let place = Place::new(place.root, place.path, ptr);
+        self.arena.track(ptr, place, exclusive, Some(self.id), self.frame.clone())
+    }
+
+    /// Turns an exclusive handle into a shared one.
+    pub fn into_shared(mut self) -> Handle {
+        if let Some(record) = self.arena.0.borrow_mut().borrows.get_mut(&self.id) {
+            record.exclusive = false;
+        }
+        self.exclusive = false;
+        self
+    }
+}
+
+impl Drop for Handle {
+    fn drop(&mut self) {
+        let mut inner = self.arena.0.borrow_mut();
+        let parent = inner.borrows.remove(&self.id).and_then(|record| record.parent);
+        // borrows derived from this one now derive from its parent, so that stays frozen
+        inner.borrows.values_mut().filter(|record| record.parent == Some(self.id)).for_each(|record| record.parent = parent);
+    }
+}
+
+impl Debug for Handle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple(if self.exclusive { "Mut" } else { "Ref" }).field(&self.id).field(&self.get()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::reflect::Reflect;
+
+    #[derive(Reflect)]
+    struct Pair(f32, f32);
+
+    fn ptr(value: &mut dyn PartialReflect) -> *mut dyn PartialReflect {
+        value as *mut dyn PartialReflect
+    }
+
+    #[test]
+    fn shared_borrows_coexist_and_exclusive_ones_dont() {
+        let arena = Arena::default();
+        let mut value = 1.0f32;
+        let value = ptr(&mut value);
+        let first = arena.borrow_root(value, false).unwrap();
+        let second = arena.borrow_root(value, false).unwrap();
+        assert!(matches!(arena.borrow_root(value, true), Err(BorrowError::AlreadyBorrowed(_))));
+        drop((first, second));
+        let exclusive = arena.borrow_root(value, true).unwrap();
+        assert!(matches!(arena.borrow_root(value, false), Err(BorrowError::AlreadyMutablyBorrowed(_))));
+        drop(exclusive);
+        assert!(arena.borrow_root(value, true).is_ok());
+    }
+
+    #[test]
+    fn reborrows_and_disjoint_fields_are_allowed() {
+        let arena = Arena::default();
+        let mut pair = Pair(1.0, 2.0);
+        let (first, second) = (ptr(&mut pair.0), ptr(&mut pair.1));
+        let root = arena.borrow_root(ptr(&mut pair), true).unwrap();
+        let reborrow = root.reborrow(true).unwrap();
+        let first = reborrow.project(0, first, true).unwrap();
+        assert!(reborrow.project(1, second, true).is_ok());
+        assert!(matches!(reborrow.project(0, first.ptr, false), Err(BorrowError::AlreadyMutablyBorrowed(_))));
+        assert!(matches!(arena.borrow_root(root.ptr, false), Err(BorrowError::AlreadyMutablyBorrowed(_))));
+    }
+
+    #[test]
+    fn handles_are_frozen_while_reborrowed() {
+        let arena = Arena::default();
+        let mut value = 1.0f32;
+        let mut root = arena.borrow_root(ptr(&mut value), true).unwrap();
+        let shared = root.reborrow(false).unwrap();
+        assert!(matches!(root.get_mut(), Err(BorrowError::AlreadyBorrowed(_))));
+        assert!(root.read().is_ok());
+        drop(shared);
+        let child = root.reborrow(true).unwrap();
+        let grandchild = child.reborrow(true).unwrap();
+        drop(child);
+        assert!(matches!(root.read(), Err(BorrowError::AlreadyMutablyBorrowed(_))));
+        drop(grandchild);
+        assert!(root.get_mut().is_ok());
+    }
+
+    #[test]
+    fn shared_handles_only_read() {
+        let arena = Arena::default();
+        let mut value = 1.0f32;
+        let mut exclusive = arena.borrow_root(ptr(&mut value), true).unwrap();
+        exclusive.get_mut().unwrap().apply(&2.0f32);
+        let mut shared = exclusive.into_shared();
+        assert!(matches!(shared.get_mut(), Err(BorrowError::NotExclusive(_))));
+        assert!(matches!(shared.reborrow(true), Err(BorrowError::NotExclusive(_))));
+        assert!(shared.reborrow(false).is_ok());
+        assert!(arena.is_borrowed(shared.get()));
+        drop(shared);
+        assert_eq!(value, 2.0);
+    }
+}
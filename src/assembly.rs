@@ -0,0 +1,427 @@
+use crate::ui::FunctionRegistry;
+use crate::{Bytecode, Literal, QueryDataType, QueryFilterType, QueryWrapper};
+use bevy::reflect::serde::{ReflectDeserializer, ReflectSerializer};
+use bevy::reflect::{PartialReflect, ReflectFromReflect, TypeRegistry};
+use serde::de::DeserializeSeed;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::{Display, Formatter};
+
+// One instruction per line, labels on their own line ending in `:` and comments starting with `;`.
+//
+//     push {"f32": 1.0}
+//     query &mut bevy_transform::components::transform::Transform where without my_game::Frozen, or(changed my_game::Speed | added my_game::Speed)
+// L0:
+//     next_mut
+//     jump L1
+//     ...
+//     jump L0
+// L1:
+//
+// Pushed values are written as RON through `ReflectSerializer`, lists as `[value, value]`.
+
+#[derive(Debug)]
+pub enum AssemblyErrorKind {
+    UnknownInstruction(String),
+    MissingOperand,
+    InvalidOperand(String),
+    UnknownLabel(String),
+    DuplicateLabel(String),
+    UnknownFunction(String),
+    UnknownOverload(String, usize),
+    UnknownType(String),
+    InvalidValue(String),
+    ReferenceLiteral,
+}
+
+impl Display for AssemblyErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AssemblyErrorKind::UnknownInstruction(name) => write!(f, "unknown instruction `{name}`"),
+            AssemblyErrorKind::MissingOperand => f.write_str("missing operand"),
+            AssemblyErrorKind::InvalidOperand(operand) => write!(f, "invalid operand `{operand}`"),
+            AssemblyErrorKind::UnknownLabel(label) => write!(f, "unknown label `{label}`"),
+            AssemblyErrorKind::DuplicateLabel(label) => write!(f, "label `{label}` is defined twice"),
+            AssemblyErrorKind::UnknownFunction(name) => write!(f, "no function named `{name}` is registered"),
+            AssemblyErrorKind::UnknownOverload(name, overload) => write!(f, "`{name}` has no overload {overload}"),
+            AssemblyErrorKind::UnknownType(type_path) => write!(f, "`{type_path}` is not registered"),
+            AssemblyErrorKind::InvalidValue(error) => write!(f, "invalid value: {error}"),
+            AssemblyErrorKind::ReferenceLiteral => f.write_str("references can't be pushed as literals"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct AssemblyError {
+    pub line: usize,
+    pub kind: AssemblyErrorKind,
+}
+
+impl Display for AssemblyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.kind)
+    }
+}
+
+impl std::error::Error for AssemblyError {}
+
+/// Renders `bytecode` as text that [`assemble`] turns back into the same program.
+pub fn disassemble(bytecode: &[Bytecode], type_registry: &TypeRegistry) -> String {
+    let targets: BTreeSet<usize> = bytecode
+        .iter()
+        .filter_map(|op| match op {
+            Bytecode::Jump(target) | Bytecode::JumpIfNot(target) => Some(*target),
+            _ => None,
+        })
+        .collect();
+    let labels: BTreeMap<usize, String> = targets.into_iter().enumerate().map(|(i, target)| (target, format!("L{i}"))).collect();
+    let mut out = String::new();
+    for (ip, op) in bytecode.iter().enumerate() {
+        if let Some(label) = labels.get(&ip) {
+            out.push_str(&format!("{label}:\n"));
+        }
+        let line = match op {
+            Bytecode::Pop => "pop".to_string(),
+            Bytecode::Push(value) => format!("push {}", render_value(value, type_registry)),
+            Bytecode::Clone(index) => format!("clone {index}"),
+            Bytecode::Dup(index) => format!("dup {index}"),
+            Bytecode::Ref(index) => format!("ref {index}"),
+            Bytecode::Mut(index) => format!("mut {index}"),
+            Bytecode::DupField(index, field) => format!("dup_field {index} {field}"),
+            Bytecode::RefField(index, field) => format!("ref_field {index} {field}"),
+            Bytecode::MutField(index, field) => format!("mut_field {index} {field}"),
+            Bytecode::ListBreakdown(length) => format!("list_breakdown {length}"),
+            Bytecode::Call(function, overload) => {
+                let name = function.name().map(|name| name.to_string()).unwrap_or_else(|| "<anonymous>".to_string());
+                match overload {
+                    0 => format!("call {name}"),
+                    overload => format!("call {name}@{overload}"),
+                }
+            }
+            Bytecode::Query(QueryWrapper { queries, filters }) => {
+                let terms = queries.iter().map(QueryDataType::to_string).collect::<Vec<_>>().join(", ");
+                match filters.is_empty() {
+                    true => format!("query {terms}"),
+                    false => format!("query {terms} where {}", filters.iter().map(QueryFilterType::to_string).collect::<Vec<_>>().join(", ")),
+                }
+            }
+            Bytecode::Get(QueryWrapper { queries, .. }) => format!("get {}", queries.iter().map(QueryDataType::to_string).collect::<Vec<_>>().join(", ")),
+            Bytecode::IterRef => "iter_ref".to_string(),
+            Bytecode::NextMut => "next_mut".to_string(),
+            Bytecode::Apply => "apply".to_string(),
+            Bytecode::Jump(target) => format!("jump {}", labels[target]),
+            Bytecode::JumpIfNot(target) => format!("jump_if_not {}", labels[target]),
+        };
+        out.push_str(&format!("    {line}\n"));
+    }
+    if let Some(label) = labels.get(&bytecode.len()) {
+        out.push_str(&format!("{label}:\n"));
+    }
+    out
+}
+
+fn render_value(literal: &Literal, type_registry: &TypeRegistry) -> String {
+    match literal {
+        Literal::Box(val) => render_reflect(val.as_ref(), type_registry),
+        Literal::List(vals) => format!("[{}]", vals.iter().map(|val| render_value(val, type_registry)).collect::<Vec<_>>().join(", ")),
+    }
+}
+
+fn render_reflect(val: &dyn PartialReflect, type_registry: &TypeRegistry) -> String {
+    ron::to_string(&ReflectSerializer::new(val, type_registry)).unwrap_or_else(|_| format!("<{}>", val.reflect_type_path()))
+}
+
+/// Parses the text format written by [`disassemble`], resolving functions and types by name.
+pub fn assemble(source: &str, functions: &FunctionRegistry, type_registry: &TypeRegistry) -> Result<Vec<Bytecode>, AssemblyError> {
+    let mut labels = BTreeMap::new();
+    let mut instructions = vec![];
+    for (line, text) in source.lines().enumerate() {
+        let text = text.trim();
+        if text.is_empty() || text.starts_with(';') {
+            continue;
+        }
+        if let Some(label) = text.strip_suffix(':').filter(|label| label.chars().all(|c| c.is_alphanumeric() || c == '_')) {
+            if labels.insert(label.to_string(), instructions.len()).is_some() {
+                return Err(AssemblyError { line: line + 1, kind: AssemblyErrorKind::DuplicateLabel(label.to_string()) });
+            }
+            continue;
+        }
+        instructions.push((line + 1, text));
+    }
+    instructions.into_iter().map(|(line, text)| parse_instruction(text, &labels, functions, type_registry).map_err(|kind| AssemblyError { line, kind })).collect()
+}
+
+fn parse_instruction(text: &str, labels: &BTreeMap<String, usize>, functions: &FunctionRegistry, type_registry: &TypeRegistry) -> Result<Bytecode, AssemblyErrorKind> {
+    let (name, rest) = text.split_once(char::is_whitespace).map(|(name, rest)| (name, rest.trim())).unwrap_or((text, ""));
+    let mut operands = rest.split_whitespace();
+    let mut number = || {
+        let operand = operands.next().ok_or(AssemblyErrorKind::MissingOperand)?;
+        operand.parse::<usize>().map_err(|_| AssemblyErrorKind::InvalidOperand(operand.to_string()))
+    };
+    Ok(match name {
+        "pop" => Bytecode::Pop,
+        "push" => Bytecode::Push(parse_value(rest, type_registry)?),
+        "clone" => Bytecode::Clone(number()?),
+        "dup" => Bytecode::Dup(number()?),
+        "ref" => Bytecode::Ref(number()?),
+        "mut" => Bytecode::Mut(number()?),
+        "dup_field" => Bytecode::DupField(number()?, number()?),
+        "ref_field" => Bytecode::RefField(number()?, number()?),
+        "mut_field" => Bytecode::MutField(number()?, number()?),
+        "list_breakdown" => Bytecode::ListBreakdown(number()?),
+        "call" => {
+            // `name@2` calls the third overload, a plain name the first
+            let (name, overload) = rest.rsplit_once('@').and_then(|(name, overload)| Some((name, overload.parse().ok()?))).unwrap_or((rest, 0));
+            let function = functions.get(name).ok_or_else(|| AssemblyErrorKind::UnknownFunction(name.to_string()))?;
+            if overload >= function.info().signatures().len() {
+                return Err(AssemblyErrorKind::UnknownOverload(name.to_string(), overload));
+            }
+            Bytecode::Call(function.clone(), overload)
+        }
+        "query" => {
+            let (terms, filters) = rest.split_once(" where ").unwrap_or((rest, ""));
+            let terms = split_top_level(terms, ',').into_iter().map(str::trim).filter(|term| !term.is_empty()).map(|term| parse_query_term(term, type_registry)).collect::<Result<_, _>>()?;
+            let filters = split_top_level(filters, ',').into_iter().map(str::trim).filter(|filter| !filter.is_empty()).map(|filter| parse_query_filter(filter, type_registry)).collect::<Result<_, _>>()?;
+            Bytecode::Query(QueryWrapper::new(terms).with_filters(filters))
+        }
+        "get" => Bytecode::Get(QueryWrapper::new(split_top_level(rest, ',').into_iter().map(str::trim).filter(|term| !term.is_empty()).map(|term| parse_query_term(term, type_registry)).collect::<Result<_, _>>()?)),
+        "iter_ref" => Bytecode::IterRef,
+        "next_mut" => Bytecode::NextMut,
+        "apply" => Bytecode::Apply,
+        "jump" => match labels.get(rest) {
+            Some(target) => Bytecode::Jump(*target),
+            None => Bytecode::Jump(rest.parse().map_err(|_| AssemblyErrorKind::UnknownLabel(rest.to_string()))?),
+        },
+        "jump_if_not" => match labels.get(rest) {
+            Some(target) => Bytecode::JumpIfNot(*target),
+            None => Bytecode::JumpIfNot(rest.parse().map_err(|_| AssemblyErrorKind::UnknownLabel(rest.to_string()))?),
+        },
+        name => return Err(AssemblyErrorKind::UnknownInstruction(name.to_string())),
+    })
+}
+
+pub(crate) fn parse_query_term(term: &str, type_registry: &TypeRegistry) -> Result<QueryDataType, AssemblyErrorKind> {
+    if term == "entity" {
+        return Ok(QueryDataType::Entity);
+    }
+    if let Some(inner) = term.strip_prefix("Option<").and_then(|inner| inner.strip_suffix('>')) {
+        return Ok(match parse_query_term(inner.trim(), type_registry)? {
+            QueryDataType::Ref(val) => QueryDataType::OptionalRef(val),
+            QueryDataType::Mut(val) => QueryDataType::OptionalMut(val),
+            _ => return Err(AssemblyErrorKind::InvalidOperand(term.to_string())),
+        });
+    }
+    let (mutable, type_path) = match term.strip_prefix("&mut ") {
+        Some(type_path) => (true, type_path.trim()),
+        None => (false, term.strip_prefix('&').ok_or_else(|| AssemblyErrorKind::InvalidOperand(term.to_string()))?.trim()),
+    };
+    let info = type_registry.get_with_type_path(type_path).ok_or_else(|| AssemblyErrorKind::UnknownType(type_path.to_string()))?.type_info();
+    Ok(if mutable { QueryDataType::Mut(info) } else { QueryDataType::Ref(info) })
+}
+
+/// Reads a filter as [`QueryFilterType`]'s `Display` writes it, like `with my_game::Player` or `or(changed A | added B)`.
+pub(crate) fn parse_query_filter(filter: &str, type_registry: &TypeRegistry) -> Result<QueryFilterType, AssemblyErrorKind> {
+    let filter = filter.trim();
+    if let Some(group) = filter.strip_prefix("or(").and_then(|group| group.strip_suffix(')')) {
+        return Ok(QueryFilterType::Or(split_top_level(group, '|').into_iter().map(|filter| parse_query_filter(filter, type_registry)).collect::<Result<_, _>>()?));
+    }
+    let (kind, type_path) = filter.split_once(' ').ok_or_else(|| AssemblyErrorKind::InvalidOperand(filter.to_string()))?;
+    let type_path = type_path.trim();
+    let info = type_registry.get_with_type_path(type_path).ok_or_else(|| AssemblyErrorKind::UnknownType(type_path.to_string()))?.type_info();
+    Ok(match kind {
+        "with" => QueryFilterType::With(info),
+        "without" => QueryFilterType::Without(info),
+        "changed" => QueryFilterType::Changed(info),
+        "added" => QueryFilterType::Added(info),
+        _ => return Err(AssemblyErrorKind::InvalidOperand(filter.to_string())),
+    })
+}
+
+pub(crate) fn parse_value(text: &str, type_registry: &TypeRegistry) -> Result<Literal, AssemblyErrorKind> {
+    let text = text.trim();
+    if text.starts_with('&') {
+        return Err(AssemblyErrorKind::ReferenceLiteral);
+    }
+    if let Some(inner) = text.strip_prefix('[').and_then(|inner| inner.strip_suffix(']')) {
+        return split_top_level(inner, ',').into_iter().filter(|item| !item.trim().is_empty()).map(|item| parse_value(item, type_registry)).collect::<Result<_, _>>().map(Literal::List);
+    }
+    let invalid = |error: ron::Error| AssemblyErrorKind::InvalidValue(error.to_string());
+    let mut deserializer = ron::Deserializer::from_str(text).map_err(|error| invalid(error.code))?;
+    let value = ReflectDeserializer::new(type_registry).deserialize(&mut deserializer).map_err(invalid)?;
+    deserializer.end().map_err(invalid)?;
+    // structs and enums without `ReflectDeserialize` come back as dynamic types
+    if value.try_as_reflect().is_some() {
+        return Ok(Literal::Box(value));
+    }
+    let concrete = value.get_represented_type_info().and_then(|info| type_registry.get_type_data::<ReflectFromReflect>(info.type_id())).and_then(|from_reflect| from_reflect.from_reflect(value.as_ref()));
+    Ok(Literal::Box(concrete.map(|value| value.into_partial_reflect()).unwrap_or(value)))
+}
+
+/// Splits on `separator` where it isn't nested inside brackets or strings.
+fn split_top_level(text: &str, separator: char) -> Vec<&str> {
+    let mut items = vec![];
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '(' | '[' | '{' | '<' => depth += 1,
+            ')' | ']' | '}' | '>' => depth = depth.saturating_sub(1),
+            c if c == separator && depth == 0 => {
+                items.push(&text[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    items.push(&text[start..]);
+    items
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::VmErrorKind;
+    use crate::arena::BorrowError;
+    use crate::verifier::VerifyErrorKind;
+    use bevy::prelude::*;
+    use bevy::reflect::TypePath;
+
+    #[derive(Component, Reflect, Debug, PartialEq)]
+    #[reflect(Component)]
+    struct Speed(f32);
+
+    #[derive(Component, Reflect)]
+    #[reflect(Component)]
+    struct Frozen;
+
+    fn world() -> World {
+        let mut world = World::new();
+        let registry = AppTypeRegistry::default();
+        {
+            let mut registry = registry.write();
+            registry.register::<f32>();
+            registry.register::<Speed>();
+            registry.register::<Frozen>();
+        }
+        world.insert_resource(registry);
+        world.register_component::<Speed>();
+        world.register_component::<Frozen>();
+        world
+    }
+
+    fn assemble_in(world: &World, source: &str) -> Vec<Bytecode> {
+        assemble(source, &FunctionRegistry::default(), &world.resource::<AppTypeRegistry>().read()).unwrap()
+    }
+
+    #[test]
+    fn assembled_query_verifies_and_runs() {
+        let mut world = world();
+        let moving = world.spawn(Speed(1.0)).id();
+        let frozen = world.spawn((Speed(1.0), Frozen)).id();
+        let speed = Speed::type_path();
+        let source = format!(
+            "
+                query &mut {speed} where without {frozen}
+            L0:
+                next_mut
+                jump L1
+                list_breakdown 1
+                push {{\"{speed}\": (2.0)}}
+                apply
+                jump L0
+            L1:
+                pop
+            ",
+            frozen = Frozen::type_path()
+        );
+        let bytecode = assemble_in(&world, &source);
+        Bytecode::verify(&world, &bytecode, &[]).unwrap();
+        Bytecode::run(&mut world, &bytecode).unwrap();
+        assert_eq!(world.get::<Speed>(moving), Some(&Speed(2.0)));
+        assert_eq!(world.get::<Speed>(frozen), Some(&Speed(1.0)));
+    }
+
+    #[test]
+    fn disassembly_assembles_back() {
+        let world = world();
+        let source = format!("query entity, Option<&{}> where or(with {frozen} | or(added {frozen} | changed {frozen}))\n", Speed::type_path(), frozen = Frozen::type_path());
+        let bytecode = assemble_in(&world, &source);
+        let text = disassemble(&bytecode, &world.resource::<AppTypeRegistry>().read());
+        assert_eq!(text.trim(), source.trim());
+        assert_eq!(disassemble(&assemble_in(&world, &text), &world.resource::<AppTypeRegistry>().read()), text);
+    }
+
+    #[test]
+    fn every_instruction_assembles_back() {
+        let world = world();
+        let mut functions = FunctionRegistry::default();
+        functions.register_freestanding((|value: f32| value * 2.0).into_function().with_name("double").with_overload(|a: f32, b: f32| a * b));
+        let registry = world.resource::<AppTypeRegistry>().read();
+        let source = format!(
+            "L0:\n    push {{\"f32\":1.0}}\n    clone 0\n    dup 0\n    ref 0\n    mut 1\n    dup_field 0 0\n    ref_field 0 0\n    mut_field 0 0\n    list_breakdown 2\n    call double@1\n    get entity, &{speed}\n    iter_ref\n    next_mut\n    apply\n    pop\n    jump_if_not L1\n    jump L0\nL1:\n",
+            speed = Speed::type_path()
+        );
+        let bytecode = assemble(&source, &functions, &registry).unwrap();
+        assert_eq!(bytecode.len(), 17);
+        assert_eq!(disassemble(&bytecode, &registry), source);
+        assert!(matches!(assemble("call double@2\n", &functions, &registry), Err(AssemblyError { kind: AssemblyErrorKind::UnknownOverload(..), .. })));
+    }
+
+    #[test]
+    fn terms_split_around_generic_arguments() {
+        let world = world();
+        let registry = world.resource::<AppTypeRegistry>().read();
+        let terms = split_top_level("entity, Option<&mut a::B<c::D, e::F>>, &g::H", ',');
+        assert_eq!(terms, ["entity", " Option<&mut a::B<c::D, e::F>>", " &g::H"]);
+        assert!(matches!(parse_query_term(&format!("Option<&mut {}>", Speed::type_path()), &registry), Ok(QueryDataType::OptionalMut(_))));
+    }
+
+    #[test]
+    fn query_terms_need_no_default() {
+        let world = world();
+        #[derive(Component, Reflect)]
+        #[reflect(Component)]
+        struct Health(u32);
+        world.resource::<AppTypeRegistry>().write().register::<Health>();
+        let term = format!("&{}", Health::type_path());
+        assert!(matches!(parse_query_term(&term, &world.resource::<AppTypeRegistry>().read()), Ok(QueryDataType::Ref(_))));
+    }
+
+    #[test]
+    fn mutably_borrowing_a_reference_fails_to_verify() {
+        let world = world();
+        let bytecode = assemble_in(&world, "push {\"f32\": 1.0}\nref 0\nmut 1\n");
+        let error = Bytecode::verify(&world, &bytecode, &[]).unwrap_err();
+        assert_eq!(error.ip, 2);
+        assert!(matches!(error.kind, VerifyErrorKind::MutableBorrowOfRef));
+    }
+
+    #[test]
+    fn borrowing_twice_fails_to_run() {
+        let mut world = world();
+        let bytecode = assemble_in(&world, "push {\"f32\": 1.0}\nmut 0\nref 0\n");
+        let error = Bytecode::run(&mut world, &bytecode).unwrap_err();
+        assert_eq!(error.ip, 2);
+        assert!(matches!(error.kind, VmErrorKind::Borrow(BorrowError::AlreadyMutablyBorrowed(_))));
+    }
+
+    #[test]
+    fn applying_to_a_borrowed_value_fails_to_run() {
+        let mut world = world();
+        let bytecode = assemble_in(&world, "push {\"f32\": 1.0}\nref 0\napply\n");
+        let error = Bytecode::run(&mut world, &bytecode).unwrap_err();
+        assert_eq!(error.ip, 2);
+        assert!(matches!(error.kind, VmErrorKind::Borrow(BorrowError::StillBorrowed(_))));
+    }
+}
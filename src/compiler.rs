@@ -1,271 +1,371 @@
-use crate::nodes::{GraphCompileExt, GraphNode};
-/*use crate::nodes::breakdown_node::BreakdownType;
-use crate::nodes::primitive_node::PrimitiveType;
-use crate::nodes::query_node::QueryDataType;*/
-use crate::nodes::start_node::StartNode;
-use crate::ui::NodeViewer;
-use crate::{Bytecode, QueryWrapper, Value};
-use bevy::ecs::component::ComponentId;
-use bevy::ecs::world::FilteredEntityMut;
-use bevy::prelude::{QueryBuilder, World};
-use egui_snarl::ui::SnarlViewer;
-use egui_snarl::{InPinId, NodeId, OutPin, OutPinId, Snarl};
-use std::any::{Any, TypeId};
+use crate::Bytecode;
+use crate::again::self_node::SelfNode;
+use crate::again::start_node::{Entry, StartNode};
+use crate::again::variable_node::{GraphVariable, VariableNode};
+use crate::again::{Node, Viewer, check_inputs};
+use crate::diagnostics::Location;
+use bevy::prelude::World;
+use egui_snarl::{InPinId, NodeId, OutPinId, Snarl};
 use std::collections::HashMap;
 
-pub(crate) fn compile(world: &mut World, node_viewer: &mut NodeViewer, snarl: &Snarl<GraphNode>) -> Vec<Bytecode> {
-    let mut scope_map: HashMap<OutPinId, usize> = HashMap::new();
-    let mut bytecode: Vec<Bytecode> = vec![];
-    let mut stack_ptr = 0;
-    for (i, node) in snarl.nodes().enumerate() {
-        if node.get::<StartNode>().is_some() {
-            let out_pin = snarl.out_pin(OutPinId { node: NodeId(i), output: 0 });
-            let Some(in_pin) = out_pin.remotes.first() else {
-                continue;
-            };
+/// Where the entity a graph is attached to sits on the stack, in graphs that have a [`SelfNode`].
+/// The graph's variables follow it, and then the entry's payload.
+pub(crate) const SELF_SLOT: usize = 0;
 
-            snarl.resolve_forward_pass_flow_until_finished(&mut bytecode, &mut scope_map, &mut stack_ptr, node_viewer, world, *in_pin);
-            return bytecode;
-        }
+/// The program for one entry point of a graph.
+pub(crate) struct CompiledEntry {
+    pub node: NodeId,
+    pub entry: Entry,
+    pub bytecode: Vec<Bytecode>,
+    /// Whether the program starts with the owning entity at [`SELF_SLOT`], before the entry's payload.
+    pub uses_self: bool,
+    /// The node each instruction was compiled from, so errors found in the bytecode can point at it.
+    pub nodes: HashMap<usize, NodeId>,
+}
+
+impl CompiledEntry {
+    /// The node the instruction at `ip` was compiled from, or the start node if it wasn't any.
+    pub fn node_at(&self, ip: usize) -> NodeId {
+        self.nodes.get(&ip).copied().unwrap_or(self.node)
     }
-    panic!("no start node!");
-    /*let mut start = None;
-    for (i, node) in snarl.nodes().enumerate() {
-        match node {
-            GraphNode::Start => start = Some(i),
-            _ => continue,
+}
+
+/// Compiles every start node of the graph into its own program by following the flow out of it.
+/// Returns `None` if compiling reported any errors into `snarl_viewer.diagnostics`.
+pub(crate) fn compile(world: &mut World, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>) -> Option<Vec<CompiledEntry>> {
+    snarl_viewer.diagnostics.clear();
+    let starts: Vec<NodeId> = snarl.node_ids().filter(|(_, node)| node.downcast::<StartNode>().is_some()).map(|(id, _)| id).collect();
+    if starts.is_empty() {
+        snarl_viewer.diagnostics.error(Location::Graph, "graph has no start node");
+        return None;
+    }
+    let uses_self = snarl.node_ids().any(|(_, node)| node.downcast::<SelfNode>().is_some());
+    // every entry starts with all of the graph's variables, whether it uses them or not
+    snarl_viewer.variables = variables(snarl_viewer, snarl);
+    snarl_viewer.variable_slots = snarl_viewer.variables.iter().enumerate().map(|(index, variable)| (variable.name.clone(), uses_self as usize + index)).collect();
+    let mut entries = vec![];
+    for start in starts {
+        if snarl.out_pin(OutPinId { node: start, output: 0 }).remotes.is_empty() {
+            snarl_viewer.diagnostics.warning(Location::Output(OutPinId { node: start, output: 0 }), "start isn't connected to anything");
+        }
+        let entry = snarl.get_node(start).unwrap().downcast::<StartNode>().unwrap().entry.clone();
+        if entry.has_payload() && entry.payload(&snarl_viewer.registry.read()).is_none() {
+            snarl_viewer.diagnostics.error(Location::Node(start), format!("{entry} refers to a type that isn't registered"));
         }
+        let (bytecode, nodes) = compile_flow(world, snarl_viewer, snarl, start, uses_self as usize + snarl_viewer.variables.len());
+        entries.push(CompiledEntry { node: start, entry, bytecode, uses_self, nodes });
     }
-    let start = start.unwrap();
-    let node = snarl.get_node(NodeId(start)).unwrap();
-    match node {
-        GraphNode::Start => {}
-        _ => panic!(),
+    if snarl_viewer.diagnostics.has_errors() { None } else { Some(entries) }
+}
+
+/// The variables the Variable nodes of the graph name, in the order they first appear.
+/// Nodes naming the same variable have to agree on its type.
+fn variables(snarl_viewer: &mut Viewer, snarl: &Snarl<Box<dyn Node>>) -> Vec<GraphVariable> {
+    let mut variables: Vec<GraphVariable> = vec![];
+    for (node, variable) in snarl.node_ids().filter_map(|(id, node)| Some((id, node.downcast::<VariableNode>()?.variable()))) {
+        if variable.name.is_empty() {
+            snarl_viewer.diagnostics.error(Location::Node(node), "the variable needs a name");
+            continue;
+        }
+        if variable.default_value(&snarl_viewer.registry.read()).is_none() {
+            snarl_viewer.diagnostics.error(Location::Node(node), format!("`{}` isn't a registered type with a default", variable.type_path));
+            continue;
+        }
+        match variables.iter().find(|other| other.name == variable.name) {
+            Some(other) if other.type_path != variable.type_path => snarl_viewer.diagnostics.error(Location::Node(node), format!("`{}` is a `{}` elsewhere in the graph", variable.name, other.type_path)),
+            Some(_) => {}
+            None => variables.push(variable),
+        }
     }
-    let mut next_pin = snarl.out_pin(OutPinId {
-        node: NodeId(start),
-        output: 0,
-    });
+    variables
+}
 
+fn compile_flow(world: &mut World, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>, start: NodeId, mut stack_ptr: usize) -> (Vec<Bytecode>, HashMap<usize, NodeId>) {
     let mut scope_map: HashMap<OutPinId, usize> = HashMap::new();
     let mut bytecode: Vec<Bytecode> = vec![];
-    let mut stack_ptr = 0;
-    resolve_forward_pass_flow_until_finished(
-        &mut bytecode,
-        next_pin,
-        &mut scope_map,
-        &mut stack_ptr,
-        snarl,
-        node_viewer,
-        world,
-    );
-
-    bytecode*/
+    snarl_viewer.compiled.clear();
+    snarl_viewer.compiled_from.clear();
+    compile_chain(Some(InPinId { node: start, input: 0 }), snarl_viewer, snarl, &mut bytecode, &mut scope_map, &mut stack_ptr, world);
+    (bytecode, std::mem::take(&mut snarl_viewer.compiled_from))
 }
-/*
-fn resolve_forward_pass_flow_until_finished(
-    bytecode: &mut Vec<Bytecode>,
-    mut next_pin: OutPin,
-    scope_map: &mut HashMap<OutPinId, usize>,
-    stack_ptr: &mut usize,
-    snarl: &Snarl<GraphNode>,
-    node_viewer: &mut NodeViewer,
-    world: &mut World,
-) {
-    loop {
-        let Some(this) = next_pin.remotes.first() else {
+
+/// Compiles the flow from `pin` node by node until it runs out, like the flow out of a start node or the body of a loop.
+pub(crate) fn compile_chain(mut pin: Option<InPinId>, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>, bytecode: &mut Vec<Bytecode>, scope_map: &mut HashMap<OutPinId, usize>, stack_ptr: &mut usize, world: &mut World) {
+    while let Some(next) = pin {
+        if !snarl_viewer.compiled.insert(next.node) {
+            snarl_viewer.diagnostics.error(Location::Input(next), "flow cycle: this node was already run");
+            break;
+        }
+        let Some(traits) = snarl.get_node(next.node).map(|node| node.get_traits()) else {
+            snarl_viewer.diagnostics.error(Location::Graph, "flow is connected to a node that no longer exists");
             break;
         };
-        let this_node = this.node;
-        match snarl.get_node(this_node).unwrap() {
-            GraphNode::Function(function_node) => {
-                for input in 1..function_node.inputs(node_viewer) {
-                    let data_dependency = snarl
-                        .in_pin(InPinId {
-                            node: this_node,
-                            input,
-                        })
-                        .remotes
-                        .first()
-                        .unwrap()
-                        .clone();
-                    resolve_data_dependency(
-                        bytecode,
-                        scope_map,
-                        stack_ptr,
-                        &snarl,
-                        data_dependency,
-                    );
-                    bytecode.push(Bytecode::Dup(
-                        scope_map.get(&data_dependency).unwrap().clone(),
-                    ));
-                }
-                println!("adding function");
-                bytecode.push(Bytecode::Call(function_node.0.as_ref().unwrap().clone()));
-                *stack_ptr += 1;
+        compile_pure_inputs(next.node, snarl_viewer, snarl, bytecode, scope_map, stack_ptr, world);
+        check_inputs(next.node, snarl_viewer, snarl);
+        let first = bytecode.len();
+        pin = traits.compile_2(snarl.in_pin(next), snarl_viewer, snarl, bytecode, scope_map, stack_ptr, world);
+        // nodes compiled inside this one, like a loop body, keep their own instructions
+        for ip in first..bytecode.len() {
+            snarl_viewer.compiled_from.entry(ip).or_insert(next.node);
+        }
+    }
+}
+
+/// Compiles the pure nodes feeding the inputs of `node` that haven't run in this scope yet, so their values are on the stack when `node` reads them.
+/// They run again in every scope that uses them, like once per iteration of a loop whose items they read.
+pub(crate) fn compile_pure_inputs(node: NodeId, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>, bytecode: &mut Vec<Bytecode>, scope_map: &mut HashMap<OutPinId, usize>, stack_ptr: &mut usize, world: &mut World) {
+    let Some(traits) = snarl.get_node(node).map(|node| node.get_traits()) else {
+        return;
+    };
+    for input in 0..traits.inputs_2(node, snarl_viewer, snarl) {
+        for remote in snarl.in_pin(InPinId { node, input }).remotes {
+            if scope_map.contains_key(&remote) || !snarl_viewer.is_pure(remote.node, snarl) {
+                continue;
             }
-            GraphNode::For(for_node) => {
-                let data_dependency = snarl
-                    .in_pin(InPinId {
-                        node: this_node,
-                        input: 1,
-                    })
-                    .remotes
-                    .first()
-                    .unwrap()
-                    .clone();
-                resolve_data_dependency(bytecode, scope_map, stack_ptr, &snarl, data_dependency);
-                bytecode.push(Bytecode::NextMut);
-                let position = bytecode.len();
-                bytecode.push(Bytecode::Jump(0));
-                let prev_stack = *stack_ptr;
-                resolve_forward_pass_flow_until_finished(
-                    bytecode,
-                    snarl.out_pin(OutPinId {
-                        node: this_node,
-                        output: 0,
-                    }),
-                    scope_map,
-                    stack_ptr,
-                    snarl,
-                    node_viewer,
-                    world,
-                );
-                for _ in prev_stack..(*stack_ptr) {
-                    bytecode.push(Bytecode::Pop);
-                }
-                bytecode.push(Bytecode::Jump(position - 1));
-                let len_temp = bytecode.len();
-                match bytecode.get_mut(position).unwrap() {
-                    Bytecode::Jump(jump) => {
-                        *jump = len_temp;
-                    }
-                    _ => unreachable!(),
-                }
-                next_pin = snarl.out_pin(OutPinId {
-                    node: this.node,
-                    output: 2,
-                });
-                *stack_ptr = prev_stack;
+            if snarl_viewer.compiling_pure.contains(&remote.node) {
+                snarl_viewer.diagnostics.error(Location::Node(remote.node), "data cycle: this node's value feeds back into itself");
                 continue;
             }
-            GraphNode::Query(query_node) => {
-                let mut map: HashMap<TypeId, ComponentId> = HashMap::default();
-                for c in world.components().iter_registered() {
-                    map.insert(c.type_id().unwrap(), c.id());
-                }
-                bytecode.push(Bytecode::Query(QueryWrapper::new(
-                    query_node.querying.clone(),
-                )));
+            snarl_viewer.compiling_pure.push(remote.node);
+            compile_pure_inputs(remote.node, snarl_viewer, snarl, bytecode, scope_map, stack_ptr, world);
+            check_inputs(remote.node, snarl_viewer, snarl);
+            let first = bytecode.len();
+            let pure = snarl.get_node(remote.node).unwrap().get_traits();
+            // pure nodes have no flow to pass on, their compile just places their values
+            pure.compile_2(snarl.in_pin(InPinId { node: remote.node, input: 0 }), snarl_viewer, snarl, bytecode, scope_map, stack_ptr, world);
+            for ip in first..bytecode.len() {
+                snarl_viewer.compiled_from.entry(ip).or_insert(remote.node);
             }
-            _ => panic!("flow must be of flow types"),
+            snarl_viewer.compiling_pure.pop();
         }
-        next_pin = snarl.out_pin(OutPinId {
-            node: this.node,
-            output: 0,
-        });
     }
 }
 
-fn resolve_data_dependency(
-    bytecode: &mut Vec<Bytecode>,
-    scope_map: &mut HashMap<OutPinId, usize>,
-    stack_ptr: &mut usize,
-    snarl: &Snarl<GraphNode>,
-    out_pin_id: OutPinId,
-) {
-    if !scope_map.contains_key(&out_pin_id) {
-        if let Some(primitive) = snarl.get_node(out_pin_id.node).unwrap().primitive() {
-            match &primitive.primitive_type {
-                PrimitiveType::I32(val) => {
-                    bytecode.push(Bytecode::Push(Value::Box(Box::new(val.clone()))))
-                }
-                PrimitiveType::F32(val) => {
-                    bytecode.push(Bytecode::Push(Value::Box(Box::new(val.clone()))))
-                }
-                PrimitiveType::String(val) => {
-                    bytecode.push(Bytecode::Push(Value::Box(Box::new(val.clone()))))
-                }
-            }
-            scope_map.insert(out_pin_id, *stack_ptr);
-            *stack_ptr += 1;
-            return;
+/// Compiles the flow from `pin` in a scope of its own, like one arm of a branch.
+/// What it pushes is popped again at its end, so nothing after it can use those values.
+pub(crate) fn compile_scope(pin: Option<InPinId>, snarl_viewer: &mut Viewer, snarl: &mut Snarl<Box<dyn Node>>, bytecode: &mut Vec<Bytecode>, scope_map: &mut HashMap<OutPinId, usize>, stack_ptr: &mut usize, world: &mut World) {
+    let base = *stack_ptr;
+    compile_chain(pin, snarl_viewer, snarl, bytecode, scope_map, stack_ptr, world);
+    leave_scope(base, bytecode, scope_map, stack_ptr);
+}
+
+/// Pops everything above `base` and forgets the values that lived there.
+pub(crate) fn leave_scope(base: usize, bytecode: &mut Vec<Bytecode>, scope_map: &mut HashMap<OutPinId, usize>, stack_ptr: &mut usize) {
+    bytecode.extend((base..*stack_ptr).map(|_| Bytecode::Pop));
+    scope_map.retain(|_, position| *position < base);
+    *stack_ptr = base;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Value;
+    use crate::again::apply_node::ApplyNode;
+    use crate::again::breakdown_node::TupleBreakdownNode;
+    use crate::again::for_node::ForNode;
+    use crate::again::function_node::FunctionNode;
+    use crate::again::if_some_node::IfSomeNode;
+    use crate::again::primitive_node::{PrimitiveNode, PrimitiveType};
+    use crate::again::query_node::QueryNode;
+    use crate::again::self_node::SelfNode;
+    use crate::script_fn::script_fn;
+    use crate::ui::ScriptFunctions;
+    use bevy::prelude::*;
+    use bevy::reflect::func::args::Ownership;
+    use bevy::reflect::{TypePath, Typed};
+
+    #[derive(Component, Reflect, Default, Debug, PartialEq)]
+    #[reflect(Component, Default)]
+    struct Speed(f32);
+
+    #[derive(Component, Reflect)]
+    #[reflect(Component)]
+    struct Frozen;
+
+    #[script_fn(pure)]
+    fn double(speed: f32) -> f32 {
+        speed * 2.0
+    }
+
+    fn world() -> World {
+        let mut world = World::new();
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<f32>();
+        registry.write().register::<Speed>();
+        registry.write().register::<Frozen>();
+        world.insert_resource(registry);
+        world.init_resource::<ScriptFunctions>();
+        world.register_component::<Speed>();
+        world.register_component::<Frozen>();
+        world
+    }
+
+    /// Start, then a loop over every `Speed` that borrows its field and sets it to 3, in the body or after the loop.
+    /// Returns the graph and the node doing the setting.
+    fn speed_loop(after_loop: bool) -> (Snarl<Box<dyn Node>>, NodeId) {
+        let mut snarl: Snarl<Box<dyn Node>> = Snarl::new();
+        let mut node = |node: Box<dyn Node>| snarl.insert_node(egui::Pos2::ZERO, node);
+        let start = node(Box::<StartNode>::default());
+        let mut query = QueryNode::default();
+        query.terms.push(format!("&mut {}", Speed::type_path()));
+        let query = node(Box::new(query));
+        let for_node = node(Box::<ForNode>::default());
+        let mut breakdown = TupleBreakdownNode::default();
+        breakdown.ownership = Ownership::Mut;
+        let breakdown = node(Box::new(breakdown));
+        let value = node(Box::new(PrimitiveNode { primitive_type: PrimitiveType::F32(3.0), node_id: None }));
+        let apply = node(Box::<ApplyNode>::default());
+        let before_apply = if after_loop { OutPinId { node: for_node, output: 1 } } else { OutPinId { node: value, output: 0 } };
+        for (from, to) in [(OutPinId { node: start, output: 0 }, query), (OutPinId { node: query, output: 0 }, for_node), (OutPinId { node: for_node, output: 0 }, breakdown), (OutPinId { node: breakdown, output: 0 }, value), (before_apply, apply)] {
+            snarl.connect(from, InPinId { node: to, input: 0 });
         }
-        match snarl.get_node(out_pin_id.node).unwrap() {
-            GraphNode::Breakdown(breakdown) => {
-                let out_pin_id_of_breakdown_input = snarl
-                    .in_pin(InPinId {
-                        node: out_pin_id.node,
-                        input: 0,
-                    })
-                    .remotes
-                    .first()
-                    .unwrap()
-                    .clone();
-                resolve_data_dependency(
-                    bytecode,
-                    scope_map,
-                    stack_ptr,
-                    snarl,
-                    out_pin_id_of_breakdown_input,
-                );
-                let position = *scope_map.get(&out_pin_id_of_breakdown_input).unwrap();
-                match breakdown.breakdown_type {
-                    BreakdownType::Owned => {
-                        bytecode.push(Bytecode::DupField(position, out_pin_id.output))
-                    }
-                    BreakdownType::Reference => {
-                        bytecode.push(Bytecode::RefField(position, out_pin_id.output))
-                    }
-                    BreakdownType::MutReference => {
-                        bytecode.push(Bytecode::MutField(position, out_pin_id.output))
-                    }
-                }
-                scope_map.insert(out_pin_id, *stack_ptr);
-                *stack_ptr += 1;
-            }
-            GraphNode::Buildup(buildup) => {
-                let buildup = buildup.buildup.as_ref().unwrap();
-                // we resolve all the data dependencies first so that way we can just get everything
-                // so in the latter for loop we can bring everything to the top
-                for (i, field) in buildup.iter_fields().enumerate() {
-                    let in_pin = snarl.in_pin(InPinId {
-                        node: out_pin_id.node,
-                        input: i,
-                    });
-                    let remote = in_pin.remotes.first().unwrap().clone();
-                    resolve_data_dependency(bytecode, scope_map, stack_ptr, snarl, remote);
-                }
-                bytecode.push(Bytecode::Push(Value::Box(
-                    Box::new(buildup.reflect_clone().unwrap()).into_partial_reflect(),
-                )));
-                for (i, field) in buildup.iter_fields().enumerate() {
-                    let in_pin = snarl.in_pin(InPinId {
-                        node: out_pin_id.node,
-                        input: i,
-                    });
-                    let remote = in_pin.remotes.first().unwrap().clone();
-                    bytecode.push(Bytecode::Dup(scope_map.get(&remote).unwrap().clone()));
-                    bytecode.push(Bytecode::Apply(i));
-                }
-                scope_map.insert(out_pin_id, *stack_ptr);
-                *stack_ptr += 1;
-            }
-            GraphNode::Query(_query) => {
-                scope_map.insert(out_pin_id, *stack_ptr);
-                *stack_ptr += 1;
-            }
-            GraphNode::For(_for) => {
-                scope_map.insert(out_pin_id, *stack_ptr);
-                *stack_ptr += 1;
-            }
-            GraphNode::TupleBreakdown(tuple_breakdown) => {
-                bytecode.push(Bytecode::ListBreakdown(tuple_breakdown.length));
-                scope_map.insert(out_pin_id, *stack_ptr);
-                *stack_ptr += tuple_breakdown.length;
-            }
-            unreachable => unreachable!("{:?}", unreachable.get_type()),
+        snarl.connect(OutPinId { node: query, output: 1 }, InPinId { node: for_node, input: 1 });
+        snarl.connect(OutPinId { node: for_node, output: 2 }, InPinId { node: breakdown, input: 1 });
+        snarl.connect(OutPinId { node: breakdown, output: 1 }, InPinId { node: apply, input: 1 });
+        snarl.connect(OutPinId { node: value, output: 1 }, InPinId { node: apply, input: 2 });
+        (snarl, apply)
+    }
+
+    fn compile_snarl(world: &mut World, snarl: &mut Snarl<Box<dyn Node>>) -> (Option<Vec<CompiledEntry>>, Viewer) {
+        let mut viewer = Viewer::new(world.resource::<AppTypeRegistry>().clone(), world.resource::<ScriptFunctions>().clone(), snarl);
+        (compile(world, &mut viewer, snarl), viewer)
+    }
+
+    #[test]
+    fn loop_body_runs_for_every_item() {
+        let mut world = world();
+        let entities = [world.spawn(Speed(1.0)).id(), world.spawn(Speed(2.0)).id()];
+        let (mut snarl, _) = speed_loop(false);
+        let (entries, viewer) = compile_snarl(&mut world, &mut snarl);
+        let entries = entries.unwrap_or_else(|| panic!("{:?}", viewer.diagnostics.0));
+        Bytecode::verify(&world, &entries[0].bytecode, &[]).unwrap();
+        Bytecode::run(&mut world, &entries[0].bytecode).unwrap();
+        for entity in entities {
+            assert_eq!(world.get::<Speed>(entity), Some(&Speed(3.0)));
+        }
+    }
+
+    #[test]
+    fn loop_body_values_are_out_of_scope_after_it() {
+        let mut world = world();
+        let (mut snarl, apply) = speed_loop(true);
+        let (entries, viewer) = compile_snarl(&mut world, &mut snarl);
+        assert!(entries.is_none());
+        for input in [1, 2] {
+            assert!(viewer.diagnostics.for_input(InPinId { node: apply, input }).any(|diagnostic| diagnostic.message.contains("out of scope")));
+        }
+    }
+
+    #[test]
+    fn query_filters_skip_entities() {
+        let mut world = world();
+        let moving = world.spawn(Speed(1.0)).id();
+        let frozen = world.spawn((Speed(1.0), Frozen)).id();
+        let (mut snarl, _) = speed_loop(false);
+        let query = snarl.node_ids().find(|(_, node)| node.downcast::<QueryNode>().is_some()).map(|(id, _)| id).unwrap();
+        snarl.get_node_mut(query).unwrap().downcast_mut::<QueryNode>().unwrap().filters.push(vec![format!("without {}", Frozen::type_path())]);
+        let (entries, viewer) = compile_snarl(&mut world, &mut snarl);
+        let entries = entries.unwrap_or_else(|| panic!("{:?}", viewer.diagnostics.0));
+        Bytecode::run(&mut world, &entries[0].bytecode).unwrap();
+        assert_eq!(world.get::<Speed>(moving), Some(&Speed(3.0)));
+        assert_eq!(world.get::<Speed>(frozen), Some(&Speed(1.0)));
+    }
+
+    #[test]
+    fn optional_terms_branch_on_whether_the_component_is_there() {
+        let mut world = world();
+        let with_speed = world.spawn((Speed(1.0), Frozen)).id();
+        world.spawn(Frozen);
+        let mut snarl: Snarl<Box<dyn Node>> = Snarl::new();
+        let mut node = |node: Box<dyn Node>| snarl.insert_node(egui::Pos2::ZERO, node);
+        let start = node(Box::<StartNode>::default());
+        let mut query = QueryNode::default();
+        query.terms = vec!["entity".to_string(), format!("Option<&mut {}>", Speed::type_path())];
+        query.filters.push(vec![format!("with {}", Frozen::type_path())]);
+        let query = node(Box::new(query));
+        let for_node = node(Box::<ForNode>::default());
+        let if_some = node(Box::<IfSomeNode>::default());
+        let mut breakdown = TupleBreakdownNode::default();
+        breakdown.ownership = Ownership::Mut;
+        let breakdown = node(Box::new(breakdown));
+        let value = node(Box::new(PrimitiveNode { primitive_type: PrimitiveType::F32(3.0), node_id: None }));
+        let apply = node(Box::<ApplyNode>::default());
+        for (from, to) in [(start, query), (query, for_node), (for_node, if_some), (if_some, breakdown), (breakdown, value), (value, apply)] {
+            snarl.connect(OutPinId { node: from, output: 0 }, InPinId { node: to, input: 0 });
+        }
+        snarl.connect(OutPinId { node: query, output: 1 }, InPinId { node: for_node, input: 1 });
+        snarl.connect(OutPinId { node: for_node, output: 3 }, InPinId { node: if_some, input: 1 });
+        snarl.connect(OutPinId { node: if_some, output: 3 }, InPinId { node: breakdown, input: 1 });
+        snarl.connect(OutPinId { node: breakdown, output: 1 }, InPinId { node: apply, input: 1 });
+        snarl.connect(OutPinId { node: value, output: 1 }, InPinId { node: apply, input: 2 });
+        let (entries, viewer) = compile_snarl(&mut world, &mut snarl);
+        let entries = entries.unwrap_or_else(|| panic!("{:?}", viewer.diagnostics.0));
+        Bytecode::verify(&world, &entries[0].bytecode, &[]).unwrap();
+        Bytecode::run(&mut world, &entries[0].bytecode).unwrap();
+        assert_eq!(world.get::<Speed>(with_speed), Some(&Speed(3.0)));
+    }
+
+    #[test]
+    fn self_node_runs_where_its_outputs_are_read() {
+        let mut world = world();
+        let owner = world.spawn(Speed(1.0)).id();
+        let mut snarl: Snarl<Box<dyn Node>> = Snarl::new();
+        let mut node = |node: Box<dyn Node>| snarl.insert_node(egui::Pos2::ZERO, node);
+        let start = node(Box::<StartNode>::default());
+        let mut self_node = SelfNode::default();
+        self_node.components.push(Speed::type_path().to_string());
+        let self_node = node(Box::new(self_node));
+        let mut breakdown = TupleBreakdownNode::default();
+        breakdown.ownership = Ownership::Mut;
+        let breakdown = node(Box::new(breakdown));
+        let value = node(Box::new(PrimitiveNode { primitive_type: PrimitiveType::F32(3.0), node_id: None }));
+        let apply = node(Box::<ApplyNode>::default());
+        for (from, to) in [(start, breakdown), (breakdown, value), (value, apply)] {
+            snarl.connect(OutPinId { node: from, output: 0 }, InPinId { node: to, input: 0 });
+        }
+        snarl.connect(OutPinId { node: self_node, output: 1 }, InPinId { node: breakdown, input: 1 });
+        snarl.connect(OutPinId { node: breakdown, output: 1 }, InPinId { node: apply, input: 1 });
+        snarl.connect(OutPinId { node: value, output: 1 }, InPinId { node: apply, input: 2 });
+        let (entries, viewer) = compile_snarl(&mut world, &mut snarl);
+        let entries = entries.unwrap_or_else(|| panic!("{:?}", viewer.diagnostics.0));
+        assert!(entries[0].uses_self);
+        Bytecode::verify(&world, &entries[0].bytecode, &[Entity::type_info()]).unwrap();
+        Bytecode::run_with(&mut world, &entries[0].bytecode, vec![Value::Box(Box::new(owner))]).unwrap();
+        assert_eq!(world.get::<Speed>(owner), Some(&Speed(3.0)));
+    }
+
+    #[test]
+    fn pure_functions_run_where_their_value_is_used() {
+        let mut world = world();
+        world.resource::<ScriptFunctions>().write().register_script_fns();
+        let owner = world.spawn(Speed(1.0)).id();
+        let double = world.resource::<ScriptFunctions>().read().listed().into_iter().find(|listing| listing.label == "double").and_then(|listing| listing.function.name().map(|name| name.to_string())).unwrap();
+        let mut snarl: Snarl<Box<dyn Node>> = Snarl::new();
+        let mut node = |node: Box<dyn Node>| snarl.insert_node(egui::Pos2::ZERO, node);
+        let start = node(Box::<StartNode>::default());
+        let mut self_node = SelfNode::default();
+        self_node.components.push(Speed::type_path().to_string());
+        let self_node = node(Box::new(self_node));
+        let mut breakdown = TupleBreakdownNode::default();
+        breakdown.ownership = Ownership::Mut;
+        let breakdown = node(Box::new(breakdown));
+        let value = node(Box::new(PrimitiveNode { primitive_type: PrimitiveType::F32(1.5), node_id: None }));
+        let mut function = FunctionNode::default();
+        function.function = double;
+        let function = node(Box::new(function));
+        let apply = node(Box::<ApplyNode>::default());
+        for (from, to) in [(start, breakdown), (breakdown, value), (value, apply)] {
+            snarl.connect(OutPinId { node: from, output: 0 }, InPinId { node: to, input: 0 });
         }
+        snarl.connect(OutPinId { node: self_node, output: 1 }, InPinId { node: breakdown, input: 1 });
+        snarl.connect(OutPinId { node: breakdown, output: 1 }, InPinId { node: apply, input: 1 });
+        snarl.connect(OutPinId { node: value, output: 1 }, InPinId { node: function, input: 0 });
+        snarl.connect(OutPinId { node: function, output: 0 }, InPinId { node: apply, input: 2 });
+        let (entries, mut viewer) = compile_snarl(&mut world, &mut snarl);
+        assert!(viewer.is_pure(function, &mut snarl));
+        let entries = entries.unwrap_or_else(|| panic!("{:?}", viewer.diagnostics.0));
+        Bytecode::verify(&world, &entries[0].bytecode, &[Entity::type_info()]).unwrap();
+        Bytecode::run_with(&mut world, &entries[0].bytecode, vec![Value::Box(Box::new(owner))]).unwrap();
+        assert_eq!(world.get::<Speed>(owner), Some(&Speed(3.0)));
     }
 }
-*/
@@ -0,0 +1,105 @@
+use egui::Color32;
+use egui_snarl::{InPinId, NodeId, OutPinId};
+use std::fmt::{Display, Formatter};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn color(&self) -> Color32 {
+        match self {
+            Severity::Warning => Color32::from_rgb(0xe0, 0xb0, 0x00),
+            Severity::Error => Color32::from_rgb(0xe0, 0x20, 0x20),
+        }
+    }
+}
+
+/// What part of the graph a [`Diagnostic`] is about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Location {
+    Graph,
+    Node(NodeId),
+    Input(InPinId),
+    Output(OutPinId),
+}
+
+impl Location {
+    pub fn node(&self) -> Option<NodeId> {
+        match self {
+            Location::Graph => None,
+            Location::Node(node) => Some(*node),
+            Location::Input(pin) => Some(pin.node),
+            Location::Output(pin) => Some(pin.node),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub location: Location,
+    pub message: String,
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        match self.location {
+            Location::Graph => write!(f, "{severity}: {}", self.message),
+            Location::Node(node) => write!(f, "{severity} in node {}: {}", node.0, self.message),
+            Location::Input(pin) => write!(f, "{severity} in node {} input {}: {}", pin.node.0, pin.input, self.message),
+            Location::Output(pin) => write!(f, "{severity} in node {} output {}: {}", pin.node.0, pin.output, self.message),
+        }
+    }
+}
+
+/// Everything compiling a graph found wrong with it.
+#[derive(Clone, Debug, Default)]
+pub struct Diagnostics(pub Vec<Diagnostic>);
+
+impl Diagnostics {
+    pub fn error(&mut self, location: Location, message: impl Into<String>) {
+        self.0.push(Diagnostic { severity: Severity::Error, location, message: message.into() });
+    }
+
+    pub fn warning(&mut self, location: Location, message: impl Into<String>) {
+        self.0.push(Diagnostic { severity: Severity::Warning, location, message: message.into() });
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.0.iter().any(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+
+    /// Every diagnostic on `node` or one of its pins.
+    pub fn for_node(&self, node: NodeId) -> impl Iterator<Item = &Diagnostic> {
+        self.0.iter().filter(move |diagnostic| diagnostic.location.node() == Some(node))
+    }
+
+    pub fn for_input(&self, pin: InPinId) -> impl Iterator<Item = &Diagnostic> {
+        self.0.iter().filter(move |diagnostic| diagnostic.location == Location::Input(pin))
+    }
+
+    pub fn for_output(&self, pin: OutPinId) -> impl Iterator<Item = &Diagnostic> {
+        self.0.iter().filter(move |diagnostic| diagnostic.location == Location::Output(pin))
+    }
+
+    /// The worst severity among `diagnostics`.
+    pub fn severity<'a>(diagnostics: impl Iterator<Item = &'a Diagnostic>) -> Option<Severity> {
+        diagnostics.map(|diagnostic| diagnostic.severity).max()
+    }
+
+    /// The messages of `diagnostics` one per line, for a tooltip.
+    pub fn tooltip<'a>(diagnostics: impl Iterator<Item = &'a Diagnostic>) -> String {
+        diagnostics.map(|diagnostic| diagnostic.message.as_str()).collect::<Vec<_>>().join("\n")
+    }
+}
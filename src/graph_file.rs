@@ -0,0 +1,341 @@
+use crate::again::Node;
+use crate::again::apply_node::ApplyNode;
+use crate::again::breakdown_node::{BreakdownNode, TupleBreakdownNode};
+use crate::again::buildup_node::BuildupNode;
+use crate::again::for_node::ForNode;
+use crate::again::function_node::FunctionNode;
+use crate::again::if_else_node::IfElseNode;
+use crate::again::if_some_node::IfSomeNode;
+use crate::again::ownership_node::OwnershipNode;
+use crate::again::primitive_node::{PrimitiveNode, PrimitiveType};
+use crate::again::query_node::QueryNode;
+use crate::again::self_node::SelfNode;
+use crate::again::start_node::{Entry, StartNode};
+use crate::again::variable_node::VariableNode;
+use crate::ui::FunctionRegistry;
+use bevy::reflect::func::args::Ownership;
+use egui::{Pos2, Vec2};
+use egui_snarl::{InPinId, NodeId, OutPinId, Snarl};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+// A `GraphFile` is a `Snarl<Box<dyn Node>>` with the trait objects replaced by a `NodeKind` holding each node's state.
+// Node ids are only meaningful inside the file, `GraphFile::load` inserts the nodes again and maps the wires onto the new ids.
+
+pub const GRAPH_VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct GraphFile {
+    pub version: u32,
+    pub nodes: Vec<SavedNode>,
+    pub wires: Vec<Wire>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct SavedNode {
+    pub id: usize,
+    pub pos: (f32, f32),
+    pub kind: NodeKind,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum NodeKind {
+    Start(Entry),
+    Primitive(PrimitiveType),
+    Ownership(SavedOwnership),
+    Apply,
+    /// The picked component type paths.
+    SelfEntity(Vec<String>),
+    /// The full name of the called function.
+    Function(String),
+    /// The query terms and filters, as the assembler writes them.
+    Query {
+        terms: Vec<String>,
+        #[serde(default)]
+        filters: Vec<Vec<String>>,
+    },
+    For,
+    IfElse,
+    IfSome,
+    Breakdown(SavedOwnership),
+    TupleBreakdown(SavedOwnership),
+    /// The type path of the built struct.
+    Buildup(String),
+    Variable {
+        name: String,
+        type_path: String,
+        ownership: SavedOwnership,
+    },
+}
+
+/// [`Ownership`] isn't serializable.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum SavedOwnership {
+    Owned,
+    Ref,
+    Mut,
+}
+
+impl From<Ownership> for SavedOwnership {
+    fn from(ownership: Ownership) -> Self {
+        match ownership {
+            Ownership::Owned => SavedOwnership::Owned,
+            Ownership::Ref => SavedOwnership::Ref,
+            Ownership::Mut => SavedOwnership::Mut,
+        }
+    }
+}
+
+impl From<SavedOwnership> for Ownership {
+    fn from(ownership: SavedOwnership) -> Self {
+        match ownership {
+            SavedOwnership::Owned => Ownership::Owned,
+            SavedOwnership::Ref => Ownership::Ref,
+            SavedOwnership::Mut => Ownership::Mut,
+        }
+    }
+}
+
+/// A wire from output `from.1` of node `from.0` to input `to.1` of node `to.0`.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Wire {
+    pub from: (usize, usize),
+    pub to: (usize, usize),
+}
+
+#[derive(Debug)]
+pub enum GraphFileError {
+    UnsupportedVersion(u32),
+    UnknownNode(String),
+    MissingNode(usize),
+    Io(String),
+    Format(String),
+}
+
+impl Display for GraphFileError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GraphFileError::UnsupportedVersion(version) => write!(f, "graph version {version} is not supported, expected {GRAPH_VERSION}"),
+            GraphFileError::UnknownNode(title) => write!(f, "`{title}` nodes can't be saved"),
+            GraphFileError::MissingNode(id) => write!(f, "a wire refers to node {id}, which isn't in the file"),
+            GraphFileError::Io(error) => write!(f, "{error}"),
+            GraphFileError::Format(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for GraphFileError {}
+
+impl NodeKind {
+    pub(crate) fn of(node: &dyn Node) -> Result<Self, GraphFileError> {
+        if let Some(start) = node.downcast::<StartNode>() {
+            Ok(NodeKind::Start(start.entry.clone()))
+        } else if let Some(primitive) = node.downcast::<PrimitiveNode>() {
+            Ok(NodeKind::Primitive(primitive.primitive_type.clone()))
+        } else if let Some(ownership) = node.downcast::<OwnershipNode>() {
+            Ok(NodeKind::Ownership(ownership.ownership.into()))
+        } else if node.downcast::<ApplyNode>().is_some() {
+            Ok(NodeKind::Apply)
+        } else if let Some(self_node) = node.downcast::<SelfNode>() {
+            Ok(NodeKind::SelfEntity(self_node.components.clone()))
+        } else if let Some(function) = node.downcast::<FunctionNode>() {
+            Ok(NodeKind::Function(function.function.clone()))
+        } else if let Some(query) = node.downcast::<QueryNode>() {
+            Ok(NodeKind::Query { terms: query.terms.clone(), filters: query.filters.clone() })
+        } else if node.downcast::<ForNode>().is_some() {
+            Ok(NodeKind::For)
+        } else if node.downcast::<IfElseNode>().is_some() {
+            Ok(NodeKind::IfElse)
+        } else if node.downcast::<IfSomeNode>().is_some() {
+            Ok(NodeKind::IfSome)
+        } else if let Some(breakdown) = node.downcast::<BreakdownNode>() {
+            Ok(NodeKind::Breakdown(breakdown.ownership.into()))
+        } else if let Some(breakdown) = node.downcast::<TupleBreakdownNode>() {
+            Ok(NodeKind::TupleBreakdown(breakdown.ownership.into()))
+        } else if let Some(buildup) = node.downcast::<BuildupNode>() {
+            Ok(NodeKind::Buildup(buildup.type_path.clone()))
+        } else if let Some(variable) = node.downcast::<VariableNode>() {
+            Ok(NodeKind::Variable {
+                name: variable.name.clone(),
+                type_path: variable.type_path.clone(),
+                ownership: variable.ownership.into(),
+            })
+        } else {
+            Err(GraphFileError::UnknownNode(node.title_2()))
+        }
+    }
+
+    pub(crate) fn into_node(self) -> Box<dyn Node> {
+        match self {
+            NodeKind::Start(entry) => {
+                let mut start = StartNode::default();
+                start.entry = entry;
+                Box::new(start)
+            }
+            NodeKind::Primitive(primitive_type) => Box::new(PrimitiveNode { primitive_type, node_id: None }),
+            NodeKind::Ownership(ownership) => Box::new(OwnershipNode { node_id: None, ownership: ownership.into() }),
+            NodeKind::Apply => Box::new(ApplyNode::default()),
+            NodeKind::SelfEntity(components) => {
+                let mut self_node = SelfNode::default();
+                self_node.components = components;
+                Box::new(self_node)
+            }
+            NodeKind::Function(function) => {
+                let mut function_node = FunctionNode::default();
+                function_node.function = function;
+                Box::new(function_node)
+            }
+            NodeKind::Query { terms, filters } => {
+                let mut query = QueryNode::default();
+                query.terms = terms;
+                query.filters = filters;
+                Box::new(query)
+            }
+            NodeKind::For => Box::new(ForNode::default()),
+            NodeKind::IfElse => Box::new(IfElseNode::default()),
+            NodeKind::IfSome => Box::new(IfSomeNode::default()),
+            NodeKind::Breakdown(ownership) => {
+                let mut breakdown = BreakdownNode::default();
+                breakdown.ownership = ownership.into();
+                Box::new(breakdown)
+            }
+            NodeKind::TupleBreakdown(ownership) => {
+                let mut breakdown = TupleBreakdownNode::default();
+                breakdown.ownership = ownership.into();
+                Box::new(breakdown)
+            }
+            NodeKind::Buildup(type_path) => {
+                let mut buildup = BuildupNode::default();
+                buildup.type_path = type_path;
+                Box::new(buildup)
+            }
+            NodeKind::Variable { name, type_path, ownership } => {
+                let mut variable = VariableNode::default();
+                variable.name = name;
+                variable.type_path = type_path;
+                variable.ownership = ownership.into();
+                Box::new(variable)
+            }
+        }
+    }
+}
+
+impl GraphFile {
+    pub fn from_snarl(snarl: &Snarl<Box<dyn Node>>) -> Result<Self, GraphFileError> {
+        let nodes: Vec<NodeId> = snarl.node_ids().map(|(id, _)| id).collect();
+        Self::from_nodes(snarl, &nodes)
+    }
+
+    /// Just `nodes` and the wires between them, like a copied selection.
+    pub fn from_nodes(snarl: &Snarl<Box<dyn Node>>, nodes: &[NodeId]) -> Result<Self, GraphFileError> {
+        let saved = snarl.nodes_pos_ids().filter(|(id, ..)| nodes.contains(id)).map(|(id, pos, node)| Ok(SavedNode { id: id.0, pos: (pos.x, pos.y), kind: NodeKind::of(node.as_ref())? })).collect::<Result<_, _>>()?;
+        let wires = snarl.wires().filter(|(from, to)| nodes.contains(&from.node) && nodes.contains(&to.node)).map(|(from, to)| Wire { from: (from.node.0, from.output), to: (to.node.0, to.input) }).collect();
+        Ok(GraphFile { version: GRAPH_VERSION, nodes: saved, wires })
+    }
+
+    /// Builds a new snarl with the saved nodes and wires.
+    pub fn load(&self) -> Result<Snarl<Box<dyn Node>>, GraphFileError> {
+        let mut snarl = Snarl::new();
+        self.insert_into(&mut snarl, Vec2::ZERO)?;
+        Ok(snarl)
+    }
+
+    /// Adds the saved nodes to `snarl` under fresh ids, moved by `offset`, and returns their ids.
+    pub fn insert_into(&self, snarl: &mut Snarl<Box<dyn Node>>, offset: Vec2) -> Result<Vec<NodeId>, GraphFileError> {
+        if self.version != GRAPH_VERSION {
+            return Err(GraphFileError::UnsupportedVersion(self.version));
+        }
+        if let Some(wire) = self.wires.iter().find(|wire| !self.nodes.iter().any(|node| node.id == wire.from.0) || !self.nodes.iter().any(|node| node.id == wire.to.0)) {
+            let missing = if self.nodes.iter().any(|node| node.id == wire.from.0) { wire.to.0 } else { wire.from.0 };
+            return Err(GraphFileError::MissingNode(missing));
+        }
+        let mut ids = HashMap::new();
+        for node in &self.nodes {
+            ids.insert(node.id, snarl.insert_node(Pos2::new(node.pos.0, node.pos.1) + offset, node.kind.clone().into_node()));
+        }
+        for wire in &self.wires {
+            snarl.connect(OutPinId { node: ids[&wire.from.0], output: wire.from.1 }, InPinId { node: ids[&wire.to.0], input: wire.to.1 });
+        }
+        Ok(self.nodes.iter().map(|node| ids[&node.id]).collect())
+    }
+
+    /// The top left corner of the saved nodes.
+    pub fn origin(&self) -> Pos2 {
+        self.nodes.iter().map(|node| Pos2::new(node.pos.0, node.pos.1)).reduce(|a, b| a.min(b)).unwrap_or(Pos2::ZERO)
+    }
+
+    pub fn to_ron(&self) -> Result<String, GraphFileError> {
+        ron::ser::to_string_pretty(self, Default::default()).map_err(|error| GraphFileError::Format(error.to_string()))
+    }
+
+    pub fn from_ron(source: &str) -> Result<Self, GraphFileError> {
+        ron::from_str(source).map_err(|error| GraphFileError::Format(error.to_string()))
+    }
+
+    /// Rewrites a file saved by an older version into the current layout, leaving newer or unknown versions to be rejected on load.
+    /// `functions` tells which function nodes are pure, their layout depends on it.
+    pub fn upgrade(mut self, functions: &FunctionRegistry) -> Self {
+        if self.version == 1 {
+            // pure functions lost their flow pins, so the flow that ran through them now skips them and their data pins move down one
+            let pure: Vec<usize> = self.nodes.iter().filter(|node| matches!(&node.kind, NodeKind::Function(name) if functions.meta(name).is_some_and(|meta| meta.pure))).map(|node| node.id).collect();
+            for node in pure {
+                let into: Vec<(usize, usize)> = self.wires.iter().filter(|wire| wire.to == (node, 0)).map(|wire| wire.from).collect();
+                let out_of: Vec<(usize, usize)> = self.wires.iter().filter(|wire| wire.from == (node, 0)).map(|wire| wire.to).collect();
+                self.wires.retain(|wire| wire.to != (node, 0) && wire.from != (node, 0));
+                self.wires.extend(into.iter().flat_map(|from| out_of.iter().map(|to| Wire { from: *from, to: *to })));
+                for wire in &mut self.wires {
+                    if wire.from.0 == node {
+                        wire.from.1 -= 1;
+                    }
+                    if wire.to.0 == node {
+                        wire.to.1 -= 1;
+                    }
+                }
+            }
+            self.version = 2;
+        }
+        self
+    }
+
+    /// Writes the graph to `path`, creating the folders leading up to it.
+    pub fn save_to(&self, path: &str) -> Result<(), GraphFileError> {
+        let io = |error: std::io::Error| GraphFileError::Io(error.to_string());
+        if let Some(parent) = std::path::Path::new(path).parent() {
+            std::fs::create_dir_all(parent).map_err(io)?;
+        }
+        std::fs::write(path, self.to_ron()?).map_err(io)
+    }
+
+    pub fn open(path: &str) -> Result<Self, GraphFileError> {
+        Self::from_ron(&std::fs::read_to_string(path).map_err(|error| GraphFileError::Io(error.to_string()))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::script_fn::script_fn;
+
+    #[script_fn(pure)]
+    fn halve(value: f32) -> f32 {
+        value / 2.0
+    }
+
+    #[test]
+    fn version_1_pure_functions_drop_their_flow() {
+        let mut functions = FunctionRegistry::default();
+        functions.register_script_fns();
+        let halve = functions.listed().into_iter().find(|listing| listing.label == "halve").and_then(|listing| listing.function.name().map(|name| name.to_string())).unwrap();
+        let node = |id, kind| SavedNode { id, pos: (0.0, 0.0), kind };
+        let wire = |from, to| Wire { from, to };
+        let file = GraphFile {
+            version: 1,
+            nodes: vec![node(0, NodeKind::Start(Entry::Run)), node(1, NodeKind::Primitive(PrimitiveType::F32(3.0))), node(2, NodeKind::Function(halve)), node(3, NodeKind::Apply)],
+            wires: vec![wire((0, 0), (1, 0)), wire((1, 0), (2, 0)), wire((2, 0), (3, 0)), wire((1, 1), (2, 1)), wire((2, 1), (3, 2))],
+        };
+        let upgraded = GraphFile::from_ron(&file.to_ron().unwrap()).unwrap().upgrade(&functions);
+        assert_eq!(upgraded.version, GRAPH_VERSION);
+        assert_eq!(upgraded.wires, vec![wire((0, 0), (1, 0)), wire((1, 1), (2, 0)), wire((2, 0), (3, 2)), wire((1, 0), (3, 0))]);
+    }
+}
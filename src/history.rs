@@ -0,0 +1,363 @@
+use crate::again::Node;
+use crate::graph_file::{GraphFile, GraphFileError, NodeKind};
+use bevy::prelude::Resource;
+use egui::{Pos2, Vec2};
+use egui_snarl::{InPinId, NodeId, OutPinId, Snarl};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+// Edits are recorded where the editor changes the graph, through [`Edits`], and handed to the history as one group per frame.
+// Dragging a node happens inside egui-snarl, so moves are the one edit found by comparing positions before and after the graph is drawn.
+// Snarl reuses the ids of removed nodes, so the history refers to nodes by an id of its own that survives being removed and inserted again.
+
+/// A node as the history knows it, stable across undoing its removal.
+pub type TrackedNode = usize;
+
+/// One reversible change to the graph. Pins are `(node, index)`, where a node is a [`TrackedNode`] once the history has the edit
+/// and the [`NodeId`] it had when the change was made before that.
+#[derive(Clone, Debug)]
+pub enum Edit<N = TrackedNode> {
+    Insert { node: N, pos: Pos2, kind: NodeKind },
+    Remove { node: N, pos: Pos2, kind: NodeKind },
+    Connect((N, usize), (N, usize)),
+    Disconnect((N, usize), (N, usize)),
+    Move { node: N, from: Pos2, to: Pos2 },
+    SetState { node: N, from: NodeKind, to: NodeKind },
+}
+
+impl<N> Edit<N> {
+    fn inverse(self) -> Edit<N> {
+        match self {
+            Edit::Insert { node, pos, kind } => Edit::Remove { node, pos, kind },
+            Edit::Remove { node, pos, kind } => Edit::Insert { node, pos, kind },
+            Edit::Connect(from, to) => Edit::Disconnect(from, to),
+            Edit::Disconnect(from, to) => Edit::Connect(from, to),
+            Edit::Move { node, from, to } => Edit::Move { node, from: to, to: from },
+            Edit::SetState { node, from, to } => Edit::SetState { node, from: to, to: from },
+        }
+    }
+}
+
+/// The edits made to the graph this frame. Each method changes the snarl and records what it did.
+#[derive(Default)]
+pub struct Edits(Vec<Edit<NodeId>>);
+
+impl Edits {
+    pub fn insert_node(&mut self, snarl: &mut Snarl<Box<dyn Node>>, pos: Pos2, node: Box<dyn Node>) -> NodeId {
+        let kind = NodeKind::of(node.as_ref());
+        let id = snarl.insert_node(pos, node);
+        if let Ok(kind) = kind {
+            self.0.push(Edit::Insert { node: id, pos, kind });
+        }
+        id
+    }
+
+    /// Removes `node` along with its wires.
+    pub fn remove_node(&mut self, snarl: &mut Snarl<Box<dyn Node>>, node: NodeId) {
+        let wires: Vec<_> = snarl.wires().filter(|(from, to)| from.node == node || to.node == node).collect();
+        for (from, to) in wires {
+            self.0.push(Edit::Disconnect((from.node, from.output), (to.node, to.input)));
+        }
+        if let Some((pos, Ok(kind))) = snarl.get_node_info(node).map(|info| (info.pos, NodeKind::of(info.value.as_ref()))) {
+            self.0.push(Edit::Remove { node, pos, kind });
+        }
+        snarl.remove_node(node);
+    }
+
+    pub fn connect(&mut self, snarl: &mut Snarl<Box<dyn Node>>, from: OutPinId, to: InPinId) {
+        if snarl.connect(from, to) {
+            self.0.push(Edit::Connect((from.node, from.output), (to.node, to.input)));
+        }
+    }
+
+    pub fn disconnect(&mut self, snarl: &mut Snarl<Box<dyn Node>>, from: OutPinId, to: InPinId) {
+        if snarl.disconnect(from, to) {
+            self.0.push(Edit::Disconnect((from.node, from.output), (to.node, to.input)));
+        }
+    }
+
+    pub fn drop_inputs(&mut self, snarl: &mut Snarl<Box<dyn Node>>, pin: InPinId) {
+        for from in snarl.in_pin(pin).remotes {
+            self.disconnect(snarl, from, pin);
+        }
+    }
+
+    pub fn drop_outputs(&mut self, snarl: &mut Snarl<Box<dyn Node>>, pin: OutPinId) {
+        for to in snarl.out_pin(pin).remotes {
+            self.disconnect(snarl, pin, to);
+        }
+    }
+
+    /// Inserts the nodes and wires of `file` like [`GraphFile::insert_into`].
+    pub fn insert_file(&mut self, snarl: &mut Snarl<Box<dyn Node>>, file: &GraphFile, offset: Vec2) -> Result<Vec<NodeId>, GraphFileError> {
+        let ids = file.insert_into(snarl, offset)?;
+        for id in &ids {
+            if let Some((pos, Ok(kind))) = snarl.get_node_info(*id).map(|info| (info.pos, NodeKind::of(info.value.as_ref()))) {
+                self.0.push(Edit::Insert { node: *id, pos, kind });
+            }
+        }
+        for (from, to) in snarl.wires().filter(|(from, to)| ids.contains(&from.node) && ids.contains(&to.node)) {
+            self.0.push(Edit::Connect((from.node, from.output), (to.node, to.input)));
+        }
+        Ok(ids)
+    }
+
+    /// Changes `node`, a `T`, through `set` and records its state before and after, if that changed.
+    /// Node widgets edit a copy of what they show and call this once the copy differs.
+    pub fn set_state<T: 'static>(&mut self, snarl: &mut Snarl<Box<dyn Node>>, node: NodeId, set: impl FnOnce(&mut T)) {
+        let Some(value) = snarl.get_node_mut(node) else {
+            return;
+        };
+        let from = NodeKind::of(value.as_ref()).ok();
+        let Some(typed) = value.downcast_mut::<T>() else {
+            return;
+        };
+        set(typed);
+        match (from, NodeKind::of(value.as_ref())) {
+            (Some(from), Ok(to)) if from != to => self.0.push(Edit::SetState { node, from, to }),
+            _ => {}
+        }
+    }
+
+    /// Records the nodes that were dragged away from where they were in `before`.
+    /// Nodes inserted or removed this frame are skipped, their ids may stand for another node by now.
+    pub fn moved(&mut self, snarl: &Snarl<Box<dyn Node>>, before: &HashMap<NodeId, Pos2>) {
+        let replaced: HashSet<NodeId> = self.0.iter().filter_map(|edit| if let Edit::Insert { node, .. } | Edit::Remove { node, .. } = edit { Some(*node) } else { None }).collect();
+        for (node, to, _) in snarl.nodes_pos_ids() {
+            match before.get(&node) {
+                Some(from) if *from != to && !replaced.contains(&node) => self.0.push(Edit::Move { node, from: *from, to }),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Undo and redo stacks for the graph in the editor, holding at most `limit` groups of edits.
+#[derive(Resource)]
+pub struct History {
+    undo: VecDeque<Vec<Edit>>,
+    redo: Vec<Vec<Edit>>,
+    limit: usize,
+    changed_last_frame: bool,
+    next_tracked: TrackedNode,
+    tracked: HashMap<NodeId, TrackedNode>,
+    nodes: HashMap<TrackedNode, NodeId>,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self::with_limit(100)
+    }
+}
+
+impl History {
+    pub fn with_limit(limit: usize) -> Self {
+        History {
+            undo: VecDeque::new(),
+            redo: vec![],
+            limit,
+            changed_last_frame: false,
+            next_tracked: 0,
+            tracked: HashMap::new(),
+            nodes: HashMap::new(),
+        }
+    }
+
+    /// Forgets every edit, for when the whole graph is replaced.
+    pub fn clear(&mut self) {
+        *self = Self::with_limit(self.limit);
+    }
+
+    fn track(&mut self, node: NodeId) -> TrackedNode {
+        if let Some(tracked) = self.tracked.get(&node) {
+            return *tracked;
+        }
+        let tracked = self.next_tracked;
+        self.next_tracked += 1;
+        self.tracked.insert(node, tracked);
+        self.nodes.insert(tracked, node);
+        tracked
+    }
+
+    fn untrack(&mut self, node: NodeId) {
+        if let Some(tracked) = self.tracked.remove(&node) {
+            self.nodes.remove(&tracked);
+        }
+    }
+
+    /// The edit by tracked nodes. Edits come in the order they were made, so an id snarl handed out again after a removal gets a new tracked node.
+    fn tracked(&mut self, edit: Edit<NodeId>) -> Edit {
+        let mut pin = |(node, index): (NodeId, usize)| (self.track(node), index);
+        match edit {
+            Edit::Connect(from, to) => Edit::Connect(pin(from), pin(to)),
+            Edit::Disconnect(from, to) => Edit::Disconnect(pin(from), pin(to)),
+            Edit::Insert { node, pos, kind } => {
+                self.untrack(node);
+                Edit::Insert { node: self.track(node), pos, kind }
+            }
+            Edit::Remove { node, pos, kind } => {
+                let tracked = self.track(node);
+                self.untrack(node);
+                Edit::Remove { node: tracked, pos, kind }
+            }
+            Edit::Move { node, from, to } => Edit::Move { node: self.track(node), from, to },
+            Edit::SetState { node, from, to } => Edit::SetState { node: self.track(node), from, to },
+        }
+    }
+
+    /// Takes the edits of one frame as one group.
+    /// Edits in consecutive frames that only move nodes or edit their state, like a drag, are merged into one group.
+    pub fn record(&mut self, edits: Edits) {
+        if edits.0.is_empty() {
+            self.changed_last_frame = false;
+            return;
+        }
+        let edits: Vec<Edit> = edits.0.into_iter().map(|edit| self.tracked(edit)).collect();
+        self.redo.clear();
+        let continues = |previous: &Vec<Edit>| previous.len() == edits.len() && previous.iter().zip(&edits).all(|pair| matches!(pair, (Edit::Move { node: a, .. }, Edit::Move { node: b, .. }) | (Edit::SetState { node: a, .. }, Edit::SetState { node: b, .. }) if a == b));
+        match self.undo.back_mut() {
+            Some(previous) if self.changed_last_frame && continues(previous) => {
+                for (previous, edit) in previous.iter_mut().zip(edits) {
+                    match (previous, edit) {
+                        (Edit::Move { to, .. }, Edit::Move { to: new, .. }) => *to = new,
+                        (Edit::SetState { to, .. }, Edit::SetState { to: new, .. }) => *to = new,
+                        _ => {}
+                    }
+                }
+            }
+            _ => {
+                self.undo.push_back(edits);
+                if self.undo.len() > self.limit {
+                    self.undo.pop_front();
+                }
+            }
+        }
+        self.changed_last_frame = true;
+    }
+
+    pub fn undo(&mut self, snarl: &mut Snarl<Box<dyn Node>>) {
+        let Some(group) = self.undo.pop_back() else {
+            return;
+        };
+        for edit in group.iter().rev() {
+            self.apply(&edit.clone().inverse(), snarl);
+        }
+        self.redo.push(group);
+        self.changed_last_frame = false;
+    }
+
+    pub fn redo(&mut self, snarl: &mut Snarl<Box<dyn Node>>) {
+        let Some(group) = self.redo.pop() else {
+            return;
+        };
+        for edit in group.iter() {
+            self.apply(edit, snarl);
+        }
+        self.undo.push_back(group);
+        self.changed_last_frame = false;
+    }
+
+    fn apply(&mut self, edit: &Edit, snarl: &mut Snarl<Box<dyn Node>>) {
+        let pin = |nodes: &HashMap<TrackedNode, NodeId>, (node, index): (TrackedNode, usize)| nodes.get(&node).map(|node| (*node, index));
+        match edit {
+            Edit::Insert { node, pos, kind } => {
+                let id = snarl.insert_node(*pos, kind.clone().into_node());
+                self.tracked.insert(id, *node);
+                self.nodes.insert(*node, id);
+            }
+            Edit::Remove { node, .. } => {
+                if let Some(id) = self.nodes.get(node).copied() {
+                    snarl.remove_node(id);
+                    self.untrack(id);
+                }
+            }
+            Edit::Connect(from, to) => {
+                if let (Some((from, output)), Some((to, input))) = (pin(&self.nodes, *from), pin(&self.nodes, *to)) {
+                    snarl.connect(OutPinId { node: from, output }, InPinId { node: to, input });
+                }
+            }
+            Edit::Disconnect(from, to) => {
+                if let (Some((from, output)), Some((to, input))) = (pin(&self.nodes, *from), pin(&self.nodes, *to)) {
+                    snarl.disconnect(OutPinId { node: from, output }, InPinId { node: to, input });
+                }
+            }
+            Edit::Move { node, to, .. } => {
+                if let Some(info) = self.nodes.get(node).and_then(|id| snarl.get_node_info_mut(*id)) {
+                    info.pos = *to;
+                }
+            }
+            Edit::SetState { node, to, .. } => {
+                if let Some(value) = self.nodes.get(node).and_then(|id| snarl.get_node_mut(*id)) {
+                    *value = to.clone().into_node();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::again::apply_node::ApplyNode;
+    use crate::again::primitive_node::{PrimitiveNode, PrimitiveType};
+
+    fn frame(history: &mut History, edit: impl FnOnce(&mut Edits)) {
+        let mut edits = Edits::default();
+        edit(&mut edits);
+        history.record(edits);
+    }
+
+    #[test]
+    fn undoing_a_removal_brings_back_the_node_and_its_wires() {
+        let mut snarl: Snarl<Box<dyn Node>> = Snarl::new();
+        let mut history = History::default();
+        let mut value = None;
+        frame(&mut history, |edits| {
+            let apply = edits.insert_node(&mut snarl, Pos2::ZERO, Box::<ApplyNode>::default());
+            value = Some(edits.insert_node(&mut snarl, Pos2::ZERO, Box::new(PrimitiveNode { primitive_type: PrimitiveType::F32(1.0), node_id: None })));
+            edits.connect(&mut snarl, OutPinId { node: value.unwrap(), output: 1 }, InPinId { node: apply, input: 2 });
+        });
+        frame(&mut history, |edits| edits.remove_node(&mut snarl, value.unwrap()));
+        assert_eq!((snarl.nodes().count(), snarl.wires().count()), (1, 0));
+        history.undo(&mut snarl);
+        assert_eq!((snarl.nodes().count(), snarl.wires().count()), (2, 1));
+        history.redo(&mut snarl);
+        assert_eq!((snarl.nodes().count(), snarl.wires().count()), (1, 0));
+        history.undo(&mut snarl);
+        history.undo(&mut snarl);
+        assert_eq!(snarl.nodes().count(), 0);
+    }
+
+    #[test]
+    fn a_drag_over_several_frames_is_undone_at_once() {
+        let mut snarl: Snarl<Box<dyn Node>> = Snarl::new();
+        let mut history = History::default();
+        let mut apply = None;
+        frame(&mut history, |edits| apply = Some(edits.insert_node(&mut snarl, Pos2::ZERO, Box::<ApplyNode>::default())));
+        let apply = apply.unwrap();
+        for x in 1..4 {
+            let before = HashMap::from([(apply, snarl.get_node_info(apply).unwrap().pos)]);
+            snarl.get_node_info_mut(apply).unwrap().pos = Pos2::new(x as f32, 0.0);
+            frame(&mut history, |edits| edits.moved(&snarl, &before));
+        }
+        history.undo(&mut snarl);
+        assert_eq!(snarl.get_node_info(apply).unwrap().pos, Pos2::ZERO);
+        history.undo(&mut snarl);
+        assert_eq!(snarl.nodes().count(), 0);
+    }
+
+    #[test]
+    fn undoing_a_state_change_restores_the_node() {
+        let mut snarl: Snarl<Box<dyn Node>> = Snarl::new();
+        let mut history = History::default();
+        let mut value = None;
+        frame(&mut history, |edits| value = Some(edits.insert_node(&mut snarl, Pos2::ZERO, Box::new(PrimitiveNode { primitive_type: PrimitiveType::F32(1.0), node_id: None }))));
+        let value = value.unwrap();
+        frame(&mut history, |edits| edits.set_state(&mut snarl, value, |primitive: &mut PrimitiveNode| primitive.primitive_type = PrimitiveType::F32(2.0)));
+        let primitive_type = |snarl: &Snarl<Box<dyn Node>>| snarl[value].downcast::<PrimitiveNode>().unwrap().primitive_type.clone();
+        assert_eq!(primitive_type(&snarl), PrimitiveType::F32(2.0));
+        history.undo(&mut snarl);
+        assert_eq!(primitive_type(&snarl), PrimitiveType::F32(1.0));
+        history.redo(&mut snarl);
+        assert_eq!(primitive_type(&snarl), PrimitiveType::F32(2.0));
+    }
+}
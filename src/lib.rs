@@ -0,0 +1,1255 @@
+//! Node graph scripts for bevy: an editor for the graphs, a compiler to bytecode and an interpreter running it from systems.
+//! Add [`NodeScriptingPlugin`] to an app to get started.
+
+// lets `#[script_fn]`'s `::bevy_node_scripting::` paths resolve inside this crate too
+extern crate self as bevy_node_scripting;
+
+pub mod again;
+pub mod arena;
+pub mod assembly;
+pub mod compiler;
+pub mod diagnostics;
+pub mod graph_file;
+pub mod history;
+pub mod plugin;
+pub mod program;
+pub mod runtime;
+pub mod script_fn;
+pub mod script_graph;
+pub mod ui;
+pub mod verifier;
+
+pub use plugin::NodeScriptingPlugin;
+pub use runtime::ScriptEventAppExt;
+pub use script_fn::script_fn;
+
+use crate::arena::{Arena, BorrowError, Frame, Handle};
+use bevy::ecs::component::{ComponentId, Tick};
+use bevy::ecs::query::FilteredAccess;
+use bevy::ecs::system::SystemChangeTick;
+use bevy::ecs::world::FilteredEntityMut;
+use bevy::prelude::*;
+use bevy::reflect::func::args::Ownership;
+use bevy::reflect::func::{ArgList, DynamicFunction, FunctionError, Return};
+use bevy::reflect::{ApplyError, ReflectCloneError, ReflectFromPtr, ReflectMut, ReflectRef, TypeInfo, TypeRegistry};
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::fmt::{Debug, Display, Formatter};
+use std::ops::Range;
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub enum QueryDataType {
+    Entity,
+    Ref(&'static TypeInfo),
+    Mut(&'static TypeInfo),
+    /// An iterator with the component as its one item, or no items if the entity doesn't have it,
+    /// so `iter_ref` and the jump after it branch on whether it's there.
+    OptionalRef(&'static TypeInfo),
+    OptionalMut(&'static TypeInfo),
+}
+
+impl Display for QueryDataType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueryDataType::Entity => f.write_str("entity"),
+            QueryDataType::Ref(info) => write!(f, "&{}", info.type_path()),
+            QueryDataType::Mut(info) => write!(f, "&mut {}", info.type_path()),
+            QueryDataType::OptionalRef(info) => write!(f, "Option<&{}>", info.type_path()),
+            QueryDataType::OptionalMut(info) => write!(f, "Option<&mut {}>", info.type_path()),
+        }
+    }
+}
+
+/// Narrows which entities a query yields, like bevy's query filters.
+#[derive(Clone)]
+pub enum QueryFilterType {
+    With(&'static TypeInfo),
+    Without(&'static TypeInfo),
+    Changed(&'static TypeInfo),
+    Added(&'static TypeInfo),
+    /// Matches if any of the filters does.
+    Or(Vec<QueryFilterType>),
+}
+
+impl QueryFilterType {
+    fn named_components(&self, found: &mut Vec<&'static TypeInfo>) {
+        match self {
+            QueryFilterType::With(info) | QueryFilterType::Without(info) | QueryFilterType::Changed(info) | QueryFilterType::Added(info) => found.push(*info),
+            QueryFilterType::Or(filters) => filters.iter().for_each(|filter| filter.named_components(found)),
+        }
+    }
+}
+
+impl Display for QueryFilterType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QueryFilterType::With(info) => write!(f, "with {}", info.type_path()),
+            QueryFilterType::Without(info) => write!(f, "without {}", info.type_path()),
+            QueryFilterType::Changed(info) => write!(f, "changed {}", info.type_path()),
+            QueryFilterType::Added(info) => write!(f, "added {}", info.type_path()),
+            QueryFilterType::Or(filters) => write!(f, "or({})", filters.iter().map(QueryFilterType::to_string).collect::<Vec<_>>().join(" | ")),
+        }
+    }
+}
+
+#[derive(Reflect)]
+pub struct ValueReflectIterThing {
+    #[reflect(ignore)]
+    internal: Option<Box<dyn Iterator<Item = Result<Value, VmErrorKind>>>>,
+}
+unsafe impl Send for ValueReflectIterThing {}
+unsafe impl Sync for ValueReflectIterThing {}
+
+#[derive(Debug)]
+pub enum Value {
+    Mut(Handle),
+    Ref(Handle),
+    Box(Box<dyn PartialReflect>),
+    List(Vec<Value>),
+}
+
+/// What [`Bytecode::Push`] puts on the stack. It only owns its data, so compiled programs can be shared between threads.
+#[derive(Debug)]
+pub enum Literal {
+    Box(Box<dyn PartialReflect>),
+    List(Vec<Literal>),
+}
+
+impl Literal {
+    fn to_value(&self) -> Result<Value, VmErrorKind> {
+        Ok(match self {
+            Literal::Box(val) => Value::Box(val.reflect_clone().map_err(|error| VmErrorKind::CloneFailed(Box::new(error)))?.into_partial_reflect()),
+            Literal::List(vals) => Value::List(vals.iter().map(Literal::to_value).collect::<Result<_, _>>()?),
+        })
+    }
+}
+
+// What we do is simple
+// We take our query -> a buncha &mut or & handles to the components
+// We put that in a Value::List(Vec<Value>>)
+// We add an instruction to be able to get a value from that list
+// So we would have a node that can deconstruct a tuple basically
+// Maybe we could use the existing breakdown for it?
+// Let's start with just not doing that though and have a TupleBreakdown node
+// OH we can use the existing RefField or MutField instructions, match on
+// if the value is a List and in that case just get the thing from the vec
+// and then return it.
+
+#[derive(Debug)]
+pub enum Bytecode {
+    Pop,
+    Push(Literal),
+    Clone(usize),
+    Dup(usize),
+    Ref(usize),
+    Mut(usize),
+    DupField(usize, usize),
+    RefField(usize, usize),
+    MutField(usize, usize),
+    ListBreakdown(usize),
+    /// Calls the function through the signature at this index, which picks the overload.
+    Call(DynamicFunction<'static>, usize),
+    Query(QueryWrapper),
+    /// Pops an `Entity` and pushes a list with the requested components of just that entity.
+    Get(QueryWrapper),
+    IterRef,
+    NextMut,
+    Apply,
+    Jump(usize),
+    /// Pops a `bool` and jumps if it is `false`.
+    JumpIfNot(usize),
+}
+
+/// What [`QueryWrapper::declared`] finds.
+type Declared = (Vec<(ComponentId, bool)>, Vec<ComponentId>);
+
+pub struct QueryWrapper {
+    queries: Vec<QueryDataType>,
+    filters: Vec<QueryFilterType>,
+}
+impl QueryWrapper {
+    pub fn new(queries: Vec<QueryDataType>) -> Self {
+        QueryWrapper { queries, filters: vec![] }
+    }
+
+    pub fn with_filters(mut self, filters: Vec<QueryFilterType>) -> Self {
+        self.filters = filters;
+        self
+    }
+
+    /// Each component the query reads or writes and whether it writes it, and the components an entity needs to match.
+    fn declared(&self, map: &HashMap<TypeId, ComponentId>) -> Option<Declared> {
+        let mut components = vec![];
+        let mut required = vec![];
+        for query in &self.queries {
+            let (info, mutable, optional) = match query {
+                QueryDataType::Entity => continue,
+                QueryDataType::Ref(info) => (info, false, false),
+                QueryDataType::Mut(info) => (info, true, false),
+                QueryDataType::OptionalRef(info) => (info, false, true),
+                QueryDataType::OptionalMut(info) => (info, true, true),
+            };
+            let component_id = *map.get(&info.type_id())?;
+            components.push((component_id, mutable));
+            if !optional {
+                required.push(component_id);
+            }
+        }
+        for filter in &self.filters {
+            match ResolvedFilter::new(filter, map).ok()? {
+                ResolvedFilter::With(component_id) => required.push(component_id),
+                ResolvedFilter::Changed(component_id) | ResolvedFilter::Added(component_id) => {
+                    components.push((component_id, false));
+                    required.push(component_id);
+                }
+                ResolvedFilter::Or(filters) => filters.iter().for_each(|filter| filter.ticked_components(&mut components)),
+                ResolvedFilter::Without(_) => {}
+            }
+        }
+        Some((components, required))
+    }
+
+    /// Every component the query names, in its terms and its filters.
+    fn named_components(&self, found: &mut Vec<&'static TypeInfo>) {
+        for query in &self.queries {
+            match query {
+                QueryDataType::Entity => {}
+                QueryDataType::Ref(info) | QueryDataType::Mut(info) | QueryDataType::OptionalRef(info) | QueryDataType::OptionalMut(info) => found.push(*info),
+            }
+        }
+        self.filters.iter().for_each(|filter| filter.named_components(found));
+    }
+
+    /// The components the query reads and writes and the archetypes it can match.
+    pub fn access(&self, map: &HashMap<TypeId, ComponentId>) -> Result<FilteredAccess<ComponentId>, VmErrorKind> {
+        let mut access = FilteredAccess::matches_everything();
+        for query in &self.queries {
+            let (info, mutable, optional) = match query {
+                QueryDataType::Entity => continue,
+                QueryDataType::Ref(info) => (info, false, false),
+                QueryDataType::Mut(info) => (info, true, false),
+                QueryDataType::OptionalRef(info) => (info, false, true),
+                QueryDataType::OptionalMut(info) => (info, true, true),
+            };
+            let component_id = *map.get(&info.type_id()).ok_or_else(|| VmErrorKind::UnregisteredComponent(info.type_path().to_string()))?;
+            // optional terms access the component without requiring it
+            match (mutable, optional) {
+                (false, false) => access.add_component_read(component_id),
+                (true, false) => access.add_component_write(component_id),
+                (false, true) => access.access_mut().add_component_read(component_id),
+                (true, true) => access.access_mut().add_component_write(component_id),
+            }
+        }
+        for filter in &self.filters {
+            ResolvedFilter::new(filter, map)?.access(&mut access);
+        }
+        Ok(access)
+    }
+}
+impl Debug for QueryWrapper {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.write_str("QueryWrapper(")?;
+        for (i, query) in self.queries.iter().enumerate() {
+            if i > 0 {
+                f.write_str(", ")?;
+            }
+            write!(f, "{query}")?;
+        }
+        if !self.filters.is_empty() {
+            write!(f, " where {}", self.filters.iter().map(QueryFilterType::to_string).collect::<Vec<_>>().join(", "))?;
+        }
+        f.write_str(")")
+    }
+}
+
+/// Why a [`Bytecode`] program stopped before running to completion.
+#[derive(Debug)]
+pub enum VmErrorKind {
+    StackUnderflow,
+    StackIndexOutOfRange(usize),
+    JumpOutOfRange(usize),
+    FieldOutOfRange(usize),
+    NotIndexable(String),
+    ExpectedValue,
+    ExpectedList,
+    ListLengthMismatch { expected: usize, found: usize },
+    MutableBorrowOfRef,
+    Borrow(BorrowError),
+    NotAnIterator,
+    CloneFailed(Box<ReflectCloneError>),
+    ApplyFailed(ApplyError),
+    CallFailed(FunctionError),
+    NoSuchOverload(usize),
+    MissingTypeRegistry,
+    UnregisteredComponent(String),
+    MissingReflectFromPtr(String),
+    ComponentUnavailable(String),
+    UnsupportedQueryTerm(&'static str),
+    ExpectedEntity,
+    ExpectedBool,
+    NoSuchEntity(Entity),
+    /// Another program of the same graph is running further up the stack and holds its variables.
+    VariablesInUse,
+}
+
+impl Display for VmErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VmErrorKind::StackUnderflow => f.write_str("stack underflow"),
+            VmErrorKind::StackIndexOutOfRange(index) => write!(f, "stack index {index} is out of range"),
+            VmErrorKind::JumpOutOfRange(target) => write!(f, "jump target {target} is out of range"),
+            VmErrorKind::FieldOutOfRange(field) => write!(f, "field {field} is out of range"),
+            VmErrorKind::NotIndexable(type_path) => write!(f, "`{type_path}` has no indexable fields"),
+            VmErrorKind::ExpectedValue => f.write_str("expected a single value but found a list"),
+            VmErrorKind::ExpectedList => f.write_str("expected a list"),
+            VmErrorKind::ListLengthMismatch { expected, found } => write!(f, "expected a list of {expected} values but found {found}"),
+            VmErrorKind::MutableBorrowOfRef => f.write_str("cannot mutably borrow through a shared reference"),
+            VmErrorKind::Borrow(err) => write!(f, "{err}"),
+            VmErrorKind::NotAnIterator => f.write_str("value is not an iterator"),
+            VmErrorKind::CloneFailed(err) => write!(f, "clone failed: {err}"),
+            VmErrorKind::ApplyFailed(err) => write!(f, "apply failed: {err}"),
+            VmErrorKind::CallFailed(err) => write!(f, "call failed: {err}"),
+            VmErrorKind::NoSuchOverload(overload) => write!(f, "function has no overload {overload}"),
+            VmErrorKind::MissingTypeRegistry => f.write_str("world has no `AppTypeRegistry`"),
+            VmErrorKind::UnregisteredComponent(type_path) => write!(f, "`{type_path}` is not a registered component"),
+            VmErrorKind::MissingReflectFromPtr(type_path) => write!(f, "`{type_path}` has no `ReflectFromPtr` type data"),
+            VmErrorKind::ComponentUnavailable(type_path) => write!(f, "query could not access `{type_path}`"),
+            VmErrorKind::UnsupportedQueryTerm(term) => write!(f, "query term `{term}` is not supported"),
+            VmErrorKind::ExpectedEntity => f.write_str("expected an entity"),
+            VmErrorKind::ExpectedBool => f.write_str("expected a bool"),
+            VmErrorKind::NoSuchEntity(entity) => write!(f, "entity {entity} does not exist"),
+            VmErrorKind::VariablesInUse => f.write_str("the graph's variables are in use by a program running further up the stack"),
+        }
+    }
+}
+
+/// A runtime error raised by [`Bytecode::run`], along with the state of the machine when it happened.
+#[derive(Debug)]
+pub struct VmError {
+    pub ip: usize,
+    pub opcode: String,
+    pub stack: String,
+    pub kind: VmErrorKind,
+}
+
+impl Display for VmError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at {} ({}), stack: {}", self.kind, self.ip, self.opcode, self.stack)
+    }
+}
+
+impl std::error::Error for VmError {}
+
+impl From<BorrowError> for VmErrorKind {
+    fn from(err: BorrowError) -> Self {
+        VmErrorKind::Borrow(err)
+    }
+}
+
+// Raw pointers default to a `'static` trait object bound, so borrowed values have their bound erased here.
+fn erase_ref(value: &dyn PartialReflect) -> *const dyn PartialReflect {
+    unsafe { std::mem::transmute(value as *const (dyn PartialReflect + '_)) }
+}
+
+fn erase_mut(value: &mut dyn PartialReflect) -> *mut dyn PartialReflect {
+    unsafe { std::mem::transmute(value as *mut (dyn PartialReflect + '_)) }
+}
+
+fn field_ref(value: &dyn PartialReflect, field: usize) -> Result<&dyn PartialReflect, VmErrorKind> {
+    match value.reflect_ref() {
+        ReflectRef::Struct(s) => s.field_at(field),
+        ReflectRef::TupleStruct(s) => s.field(field),
+        ReflectRef::Tuple(s) => s.field(field),
+        ReflectRef::List(s) => s.get(field),
+        ReflectRef::Array(s) => s.get(field),
+        _ => return Err(VmErrorKind::NotIndexable(value.reflect_type_path().to_string())),
+    }
+    .ok_or(VmErrorKind::FieldOutOfRange(field))
+}
+
+fn field_mut(value: &mut dyn PartialReflect, field: usize) -> Result<&mut dyn PartialReflect, VmErrorKind> {
+    let type_path = value.reflect_type_path().to_string();
+    match value.reflect_mut() {
+        ReflectMut::Struct(s) => s.field_at_mut(field),
+        ReflectMut::TupleStruct(s) => s.field_mut(field),
+        ReflectMut::Tuple(s) => s.field_mut(field),
+        ReflectMut::List(s) => s.get_mut(field),
+        ReflectMut::Array(s) => s.get_mut(field),
+        _ => return Err(VmErrorKind::NotIndexable(type_path)),
+    }
+    .ok_or(VmErrorKind::FieldOutOfRange(field))
+}
+
+impl Value {
+    /// Copies the value; references are reborrowed, owned data is cloned.
+    fn try_clone(&self) -> Result<Value, VmErrorKind> {
+        Ok(match self {
+            Value::Mut(handle) => Value::Mut(handle.reborrow(true)?),
+            Value::Ref(handle) => Value::Ref(handle.reborrow(false)?),
+            Value::Box(val) => Value::Box(val.reflect_clone().map_err(|error| VmErrorKind::CloneFailed(Box::new(error)))?.into_partial_reflect()),
+            Value::List(vals) => Value::List(vals.iter().map(Value::try_clone).collect::<Result<_, _>>()?),
+        })
+    }
+
+    /// Clones the data behind the value, following references.
+    fn try_clone_owned(&self) -> Result<Value, VmErrorKind> {
+        match self {
+            Value::List(vals) => Ok(Value::List(vals.iter().map(Value::try_clone_owned).collect::<Result<_, _>>()?)),
+            value => Ok(Value::Box(value.as_partial_reflect()?.reflect_clone().map_err(|error| VmErrorKind::CloneFailed(Box::new(error)))?.into_partial_reflect())),
+        }
+    }
+
+    fn as_partial_reflect(&self) -> Result<&dyn PartialReflect, VmErrorKind> {
+        match self {
+            Value::Mut(handle) | Value::Ref(handle) => Ok(handle.read()?),
+            Value::Box(val) => Ok(val.as_ref()),
+            Value::List(_) => Err(VmErrorKind::ExpectedValue),
+        }
+    }
+
+    fn as_partial_reflect_mut(&mut self) -> Result<&mut dyn PartialReflect, VmErrorKind> {
+        match self {
+            Value::Mut(handle) => Ok(handle.get_mut()?),
+            Value::Ref(_) => Err(VmErrorKind::MutableBorrowOfRef),
+            Value::Box(val) => Ok(val.as_mut()),
+            Value::List(_) => Err(VmErrorKind::ExpectedValue),
+        }
+    }
+
+    fn borrow_shared(&self, arena: &Arena) -> Result<Value, VmErrorKind> {
+        Ok(match self {
+            Value::Mut(handle) | Value::Ref(handle) => Value::Ref(handle.reborrow(false)?),
+            Value::Box(val) => Value::Ref(arena.borrow_root(erase_ref(val.as_ref()) as *mut dyn PartialReflect, false)?),
+            Value::List(vals) => Value::List(vals.iter().map(|val| val.borrow_shared(arena)).collect::<Result<_, _>>()?),
+        })
+    }
+
+    fn borrow_mut(&mut self, arena: &Arena) -> Result<Value, VmErrorKind> {
+        Ok(match self {
+            Value::Mut(handle) => Value::Mut(handle.reborrow(true)?),
+            Value::Ref(_) => return Err(VmErrorKind::MutableBorrowOfRef),
+            Value::Box(val) => Value::Mut(arena.borrow_root(erase_mut(val.as_mut()), true)?),
+            Value::List(vals) => Value::List(vals.iter_mut().map(|val| val.borrow_mut(arena)).collect::<Result<_, _>>()?),
+        })
+    }
+
+    fn borrow_field(&mut self, arena: &Arena, field: usize, exclusive: bool) -> Result<Value, VmErrorKind> {
+        let handle = match self {
+            Value::List(vals) => {
+                let val = vals.get_mut(field).ok_or(VmErrorKind::FieldOutOfRange(field))?;
+                return if exclusive { val.borrow_mut(arena) } else { val.borrow_shared(arena) };
+            }
+            Value::Box(val) => {
+                let root = erase_ref(val.as_ref());
+                let ptr = if exclusive { erase_mut(field_mut(val.as_mut(), field)?) } else { erase_ref(field_ref(val.as_ref(), field)?) as *mut dyn PartialReflect };
+                arena.borrow_field(root, field, ptr, exclusive)?
+            }
+            Value::Mut(handle) | Value::Ref(handle) => {
+                // `project` checks the access, the handle itself may be frozen by a disjoint field's borrow
+                let ptr = if exclusive { erase_mut(field_mut(unsafe { &mut *handle.as_ptr() }, field)?) } else { erase_ref(field_ref(handle.get(), field)?) as *mut dyn PartialReflect };
+                handle.project(field, ptr, exclusive)?
+            }
+        };
+        Ok(if exclusive { Value::Mut(handle) } else { Value::Ref(handle) })
+    }
+
+    fn into_shared(self) -> Value {
+        match self {
+            Value::Mut(handle) => Value::Ref(handle.into_shared()),
+            Value::List(vals) => Value::List(vals.into_iter().map(Value::into_shared).collect()),
+            value => value,
+        }
+    }
+}
+
+struct ResolvedTerm {
+    component_id: ComponentId,
+    reflect_from_ptr: ReflectFromPtr,
+    type_path: String,
+    mutable: bool,
+    optional: bool,
+}
+
+impl ResolvedTerm {
+    /// The component on `entity`, if it has it.
+    fn component(&self, entity: &mut FilteredEntityMut) -> Option<*mut dyn PartialReflect> {
+        if self.mutable {
+            let mut component = entity.get_mut_by_id(self.component_id)?;
+            let reflect = unsafe { self.reflect_from_ptr.as_reflect_mut(component.as_mut()) };
+            Some(erase_mut(reflect.as_partial_reflect_mut()))
+        } else {
+            let component = entity.get_by_id(self.component_id)?;
+            let reflect = unsafe { self.reflect_from_ptr.as_reflect(component) };
+            Some(erase_ref(reflect.as_partial_reflect()) as *mut dyn PartialReflect)
+        }
+    }
+}
+
+fn borrow_component(arena: &Arena, component: *mut dyn PartialReflect, mutable: bool) -> Result<Value, VmErrorKind> {
+    Ok(if mutable { Value::Mut(arena.borrow_root(component, true)?) } else { Value::Ref(arena.borrow_root(component, false)?) })
+}
+
+/// The iterator an optional query term yields, one item if the component was found.
+fn optional_component(arena: Arena, component: Option<*mut dyn PartialReflect>, mutable: bool) -> Value {
+    let found = component.into_iter().map(move |component| Ok(Value::List(vec![borrow_component(&arena, component, mutable)?])));
+    Value::Box(Box::new(ValueReflectIterThing { internal: Some(Box::new(found)) }))
+}
+
+fn resolve_component(info: &'static TypeInfo, map: &HashMap<TypeId, ComponentId>, type_registry: &TypeRegistry) -> Result<(ComponentId, ReflectFromPtr), VmErrorKind> {
+    let (type_path, type_id) = (info.type_path(), info.type_id());
+    let component_id = *map.get(&type_id).ok_or_else(|| VmErrorKind::UnregisteredComponent(type_path.to_string()))?;
+    let reflect_from_ptr = type_registry.get_type_data::<ReflectFromPtr>(type_id).ok_or_else(|| VmErrorKind::MissingReflectFromPtr(type_path.to_string()))?;
+    Ok((component_id, reflect_from_ptr.clone()))
+}
+
+/// A [`QueryFilterType`] with its components looked up.
+enum ResolvedFilter {
+    With(ComponentId),
+    Without(ComponentId),
+    Changed(ComponentId),
+    Added(ComponentId),
+    Or(Vec<ResolvedFilter>),
+}
+
+impl ResolvedFilter {
+    fn new(filter: &QueryFilterType, map: &HashMap<TypeId, ComponentId>) -> Result<Self, VmErrorKind> {
+        let component = |info: &'static TypeInfo| map.get(&info.type_id()).copied().ok_or_else(|| VmErrorKind::UnregisteredComponent(info.type_path().to_string()));
+        Ok(match filter {
+            QueryFilterType::With(info) => ResolvedFilter::With(component(info)?),
+            QueryFilterType::Without(info) => ResolvedFilter::Without(component(info)?),
+            QueryFilterType::Changed(info) => ResolvedFilter::Changed(component(info)?),
+            QueryFilterType::Added(info) => ResolvedFilter::Added(component(info)?),
+            QueryFilterType::Or(filters) => ResolvedFilter::Or(filters.iter().map(|filter| ResolvedFilter::new(filter, map)).collect::<Result<_, _>>()?),
+        })
+    }
+
+    /// Narrows the archetypes `builder` matches, and asks for read access to the components whose change ticks get checked.
+    fn narrow(&self, builder: &mut QueryBuilder<FilteredEntityMut>) {
+        match self {
+            ResolvedFilter::With(id) => {
+                builder.with_id(*id);
+            }
+            ResolvedFilter::Without(id) => {
+                builder.without_id(*id);
+            }
+            ResolvedFilter::Changed(id) | ResolvedFilter::Added(id) => {
+                builder.with_id(*id);
+                self.ticked(builder);
+            }
+            ResolvedFilter::Or(filters) => {
+                for filter in filters {
+                    filter.ticked(builder);
+                }
+                // a nested `Or` can't be spelled with the builder, those groups are only checked per entity
+                if filters.iter().all(|filter| !matches!(filter, ResolvedFilter::Or(_))) {
+                    builder.or(|builder| {
+                        for filter in filters {
+                            match filter {
+                                ResolvedFilter::Without(id) => builder.without_id(*id),
+                                ResolvedFilter::With(id) | ResolvedFilter::Changed(id) | ResolvedFilter::Added(id) => builder.with_id(*id),
+                                ResolvedFilter::Or(_) => unreachable!(),
+                            };
+                        }
+                    });
+                }
+            }
+        }
+    }
+
+    /// Read access to every component whose change ticks this filter checks.
+    fn ticked(&self, builder: &mut QueryBuilder<FilteredEntityMut>) {
+        match self {
+            ResolvedFilter::Changed(id) | ResolvedFilter::Added(id) => {
+                builder.optional(|builder| {
+                    builder.ref_id(*id);
+                });
+            }
+            ResolvedFilter::Or(filters) => filters.iter().for_each(|filter| filter.ticked(builder)),
+            ResolvedFilter::With(_) | ResolvedFilter::Without(_) => {}
+        }
+    }
+
+    /// The components whose change ticks this filter checks, as read access.
+    fn ticked_components(&self, components: &mut Vec<(ComponentId, bool)>) {
+        match self {
+            ResolvedFilter::Changed(id) | ResolvedFilter::Added(id) => components.push((*id, false)),
+            ResolvedFilter::Or(filters) => filters.iter().for_each(|filter| filter.ticked_components(components)),
+            ResolvedFilter::With(_) | ResolvedFilter::Without(_) => {}
+        }
+    }
+
+    /// Adds what the filter reads and which archetypes it rules out to `access`.
+    fn access(&self, access: &mut FilteredAccess<ComponentId>) {
+        match self {
+            ResolvedFilter::With(id) => access.and_with(*id),
+            ResolvedFilter::Without(id) => access.and_without(*id),
+            ResolvedFilter::Changed(id) | ResolvedFilter::Added(id) => access.add_component_read(*id),
+            ResolvedFilter::Or(filters) => {
+                let mut any = FilteredAccess::matches_nothing();
+                for filter in filters {
+                    let mut branch = FilteredAccess::matches_everything();
+                    filter.access(&mut branch);
+                    any.append_or(&branch);
+                    any.extend_access(&branch);
+                }
+                access.extend(&any);
+            }
+        }
+    }
+
+    /// Whether `entity` passes, counting changes between `last_run` and `this_run`.
+    fn matches(&self, entity: &FilteredEntityMut, last_run: Tick, this_run: Tick) -> bool {
+        match self {
+            ResolvedFilter::With(id) => entity.contains_id(*id),
+            ResolvedFilter::Without(id) => !entity.contains_id(*id),
+            ResolvedFilter::Changed(id) => entity.get_change_ticks_by_id(*id).is_some_and(|ticks| ticks.is_changed(last_run, this_run)),
+            ResolvedFilter::Added(id) => entity.get_change_ticks_by_id(*id).is_some_and(|ticks| ticks.is_added(last_run, this_run)),
+            ResolvedFilter::Or(filters) => filters.iter().any(|filter| filter.matches(entity, last_run, this_run)),
+        }
+    }
+}
+
+/// The terms and filters of a query or `Get` with their components looked up.
+#[derive(Clone)]
+struct ResolvedQuery {
+    terms: Arc<Vec<Option<ResolvedTerm>>>,
+    filters: Arc<Vec<ResolvedFilter>>,
+}
+
+impl ResolvedQuery {
+    fn new(query: &QueryWrapper, map: &HashMap<TypeId, ComponentId>, type_registry: &TypeRegistry) -> Result<Self, VmErrorKind> {
+        let terms = resolve_terms(&query.queries, map, type_registry)?;
+        let filters = query.filters.iter().map(|filter| ResolvedFilter::new(filter, map)).collect::<Result<Vec<_>, _>>()?;
+        Ok(ResolvedQuery { terms: Arc::new(terms), filters: Arc::new(filters) })
+    }
+}
+
+/// A query's state and resolved terms, kept between runs so archetypes are only matched as they're added.
+struct CachedQuery {
+    state: Box<QueryState<FilteredEntityMut<'static>>>,
+    resolved: ResolvedQuery,
+}
+
+impl CachedQuery {
+    fn new(world: &mut World, resolved: ResolvedQuery) -> Self {
+        let mut query_builder = QueryBuilder::<FilteredEntityMut>::new(world);
+        for term in resolved.terms.iter().flatten() {
+            let (component_id, mutable) = (term.component_id, term.mutable);
+            if term.optional {
+                query_builder.optional(|builder| {
+                    if mutable {
+                        builder.mut_id(component_id);
+                    } else {
+                        builder.ref_id(component_id);
+                    }
+                });
+            } else if mutable {
+                query_builder.mut_id(component_id);
+            } else {
+                query_builder.ref_id(component_id);
+            }
+        }
+        for filter in resolved.filters.iter() {
+            filter.narrow(&mut query_builder);
+        }
+        CachedQuery { state: Box::new(query_builder.build()), resolved }
+    }
+}
+
+/// Looks up the components of `queries`, `None` standing in for the entity itself.
+fn resolve_terms(queries: &[QueryDataType], map: &HashMap<TypeId, ComponentId>, type_registry: &TypeRegistry) -> Result<Vec<Option<ResolvedTerm>>, VmErrorKind> {
+    let mut terms = vec![];
+    for query in queries {
+        let (info, mutable, optional) = match query {
+            QueryDataType::Entity => {
+                terms.push(None);
+                continue;
+            }
+            QueryDataType::Ref(info) => (info, false, false),
+            QueryDataType::Mut(info) => (info, true, false),
+            QueryDataType::OptionalRef(info) => (info, false, true),
+            QueryDataType::OptionalMut(info) => (info, true, true),
+        };
+        let (component_id, reflect_from_ptr) = resolve_component(info, map, type_registry)?;
+        terms.push(Some(ResolvedTerm { component_id, reflect_from_ptr, type_path: info.type_path().to_string(), mutable, optional }));
+    }
+    Ok(terms)
+}
+
+/// The list a query or `Get` yields for `entity`.
+fn query_item(entity: &mut FilteredEntityMut, terms: &[Option<ResolvedTerm>], arena: &Arena) -> Result<Value, VmErrorKind> {
+    let mut values = Vec::with_capacity(terms.len());
+    for term in terms {
+        let Some(term) = term else {
+            values.push(Value::Box(Box::new(entity.id())));
+            continue;
+        };
+        let component = term.component(entity);
+        let value = match (component, term.optional) {
+            (component, true) => optional_component(arena.clone(), component, term.mutable),
+            (Some(component), false) => borrow_component(arena, component, term.mutable)?,
+            (None, false) => return Err(VmErrorKind::ComponentUnavailable(term.type_path.clone())),
+        };
+        values.push(value);
+    }
+    Ok(Value::List(values))
+}
+
+/// Everything a script system running a program reaches, declared as a single query: optional access to
+/// every component its queries and `Get`s name, matching the entities at least one of them could.
+pub(crate) struct DeclaredAccess {
+    /// Each component, and whether it's written.
+    accessed: Vec<(ComponentId, bool)>,
+    /// The components each query or `Get` requires, `None` if one of them could match any entity.
+    matches: Option<Vec<Vec<ComponentId>>>,
+}
+
+impl DeclaredAccess {
+    /// `None` if `bytecode` names a component that isn't registered.
+    pub(crate) fn of(bytecode: &[Bytecode], map: &HashMap<TypeId, ComponentId>) -> Option<Self> {
+        let mut accessed: Vec<(ComponentId, bool)> = vec![];
+        let mut matches = Some(vec![]);
+        for op in bytecode {
+            let (Bytecode::Query(query) | Bytecode::Get(query)) = op else {
+                continue;
+            };
+            let (components, required) = query.declared(map)?;
+            for (component_id, mutable) in components {
+                match accessed.iter_mut().find(|(id, _)| *id == component_id) {
+                    Some((_, written)) => *written |= mutable,
+                    None => accessed.push((component_id, mutable)),
+                }
+            }
+            match (&mut matches, required.is_empty()) {
+                (Some(_), true) => matches = None,
+                (Some(matches), false) => matches.push(required),
+                (None, _) => {}
+            }
+        }
+        Some(DeclaredAccess { accessed, matches })
+    }
+
+    pub(crate) fn build(&self, builder: &mut QueryBuilder<FilteredEntityMut>) {
+        builder.optional(|builder| {
+            for (component_id, mutable) in &self.accessed {
+                if *mutable {
+                    builder.mut_id(*component_id);
+                } else {
+                    builder.ref_id(*component_id);
+                }
+            }
+        });
+        if let Some(matches) = &self.matches {
+            builder.or(|builder| {
+                for required in matches {
+                    builder.and(|builder| {
+                        for component_id in required {
+                            builder.with_id(*component_id);
+                        }
+                    });
+                }
+            });
+        }
+    }
+}
+
+/// How a running program reaches the world.
+enum WorldAccess<'a> {
+    /// All of it, from an exclusive system or command.
+    Exclusive(&'a mut World),
+    /// Only what a script system or observer declared, see [`DeclaredAccess`].
+    Declared { entities: &'a Query<'a, 'a, FilteredEntityMut<'static>>, type_registry: AppTypeRegistry, last_run: Tick, this_run: Tick },
+}
+
+impl WorldAccess<'_> {
+    fn type_registry(&self) -> Result<AppTypeRegistry, VmErrorKind> {
+        match self {
+            WorldAccess::Exclusive(world) => world.get_resource::<AppTypeRegistry>().cloned().ok_or(VmErrorKind::MissingTypeRegistry),
+            WorldAccess::Declared { type_registry, .. } => Ok(type_registry.clone()),
+        }
+    }
+}
+
+/// What a program keeps between runs: the component ids it resolved and the query states it built.
+#[derive(Default)]
+pub struct QueryCache {
+    components: HashMap<TypeId, ComponentId>,
+    /// How many components the world had when `components` was filled in, components are never unregistered.
+    registered: usize,
+    /// By the index of their `Bytecode::Query`.
+    queries: HashMap<usize, CachedQuery>,
+    /// The terms of queries that have no state here, like `Get`s and the queries of script systems, by their index.
+    resolved: HashMap<usize, ResolvedQuery>,
+}
+
+impl QueryCache {
+    /// Looks up the components registered since the last time.
+    pub(crate) fn refresh(&mut self, world: &World) {
+        if self.registered == world.components().len() {
+            return;
+        }
+        self.components = component_map(world);
+        self.registered = world.components().len();
+    }
+
+    pub(crate) fn components(&self) -> &HashMap<TypeId, ComponentId> {
+        &self.components
+    }
+}
+
+pub(crate) fn component_map(world: &World) -> HashMap<TypeId, ComponentId> {
+    world.components().iter_registered().filter_map(|c| Some((c.type_id()?, c.id()))).collect()
+}
+
+#[derive(Default)]
+struct Vm {
+    stack: Vec<Value>,
+    arena: Arena,
+    /// Cached queries nothing is iterating yet.
+    queries: HashMap<usize, CachedQuery>,
+    resolved: HashMap<usize, ResolvedQuery>,
+    // Query iterators on the stack borrow these states, the boxes keep them in place.
+    running: Vec<(usize, CachedQuery)>,
+}
+
+impl Vm {
+    fn pop(&mut self) -> Result<Value, VmErrorKind> {
+        self.stack.pop().ok_or(VmErrorKind::StackUnderflow)
+    }
+
+    fn get(&self, index: usize) -> Result<&Value, VmErrorKind> {
+        self.stack.get(index).ok_or(VmErrorKind::StackIndexOutOfRange(index))
+    }
+
+    fn get_mut(&mut self, index: usize) -> Result<&mut Value, VmErrorKind> {
+        self.stack.get_mut(index).ok_or(VmErrorKind::StackIndexOutOfRange(index))
+    }
+
+    /// Owned data can only go away once nothing borrows it anymore.
+    fn ensure_unborrowed(&self, value: &Value) -> Result<(), VmErrorKind> {
+        match value {
+            Value::Box(val) if self.arena.is_borrowed(val.as_ref()) => Err(BorrowError::StillBorrowed(val.reflect_type_path().to_string()).into()),
+            Value::List(vals) => vals.iter().try_for_each(|val| self.ensure_unborrowed(val)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Checks no live `&mut` points into the data `value` owns, so it can be read. Handles check this themselves.
+    fn ensure_readable(&self, value: &Value) -> Result<(), VmErrorKind> {
+        match value {
+            Value::Box(val) if self.arena.is_mutably_borrowed(val.as_ref()) => Err(BorrowError::AlreadyMutablyBorrowed(val.reflect_type_path().to_string()).into()),
+            Value::List(vals) => vals.iter().try_for_each(|val| self.ensure_readable(val)),
+            _ => Ok(()),
+        }
+    }
+
+    /// The terms of the query or `Get` at `ip`, looked up on its first run.
+    fn resolve(&mut self, world: &WorldAccess, map: &HashMap<TypeId, ComponentId>, ip: usize, query: &QueryWrapper) -> Result<ResolvedQuery, VmErrorKind> {
+        if let Some(resolved) = self.resolved.get(&ip) {
+            return Ok(resolved.clone());
+        }
+        let resolved = ResolvedQuery::new(query, map, &world.type_registry()?.read())?;
+        self.resolved.insert(ip, resolved.clone());
+        Ok(resolved)
+    }
+
+    /// Checks the top `count` values can be taken off the stack. They stay on it if not,
+    /// the error formats the stack and the handles into them have to stay valid until then.
+    fn ensure_poppable(&self, count: usize) -> Result<(), VmErrorKind> {
+        let start = self.stack.len().checked_sub(count).ok_or(VmErrorKind::StackUnderflow)?;
+        self.stack[start..].iter().try_for_each(|value| self.ensure_unborrowed(value))
+    }
+
+    fn step(&mut self, world: &mut WorldAccess, map: &HashMap<TypeId, ComponentId>, bytecode: &Bytecode, ip: &mut usize, len: usize) -> Result<(), VmErrorKind> {
+        match bytecode {
+            Bytecode::Pop => {
+                self.ensure_poppable(1)?;
+                self.pop()?;
+            }
+            Bytecode::Push(literal) => {
+                let value = literal.to_value()?;
+                self.stack.push(value);
+            }
+            Bytecode::Clone(index) => {
+                self.ensure_readable(self.get(*index)?)?;
+                let value = self.get(*index)?.try_clone_owned()?;
+                self.stack.push(value);
+            }
+            Bytecode::Dup(index) => {
+                self.ensure_readable(self.get(*index)?)?;
+                let value = self.get(*index)?.try_clone()?;
+                self.stack.push(value);
+            }
+            Bytecode::Ref(index) => {
+                let arena = self.arena.clone();
+                let value = self.get(*index)?.borrow_shared(&arena)?;
+                self.stack.push(value);
+            }
+            Bytecode::Mut(index) => {
+                let arena = self.arena.clone();
+                let value = self.get_mut(*index)?.borrow_mut(&arena)?;
+                self.stack.push(value);
+            }
+            Bytecode::DupField(index, field) => {
+                self.ensure_readable(self.get(*index)?)?;
+                let value = match self.get(*index)? {
+                    Value::List(vals) => vals.get(*field).ok_or(VmErrorKind::FieldOutOfRange(*field))?.try_clone()?,
+                    value => Value::Box(field_ref(value.as_partial_reflect()?, *field)?.reflect_clone().map_err(|error| VmErrorKind::CloneFailed(Box::new(error)))?.into_partial_reflect()),
+                };
+                self.stack.push(value);
+            }
+            Bytecode::RefField(index, field) => {
+                let arena = self.arena.clone();
+                let value = self.get_mut(*index)?.borrow_field(&arena, *field, false)?;
+                self.stack.push(value);
+            }
+            Bytecode::MutField(index, field) => {
+                let arena = self.arena.clone();
+                let value = self.get_mut(*index)?.borrow_field(&arena, *field, true)?;
+                self.stack.push(value);
+            }
+            Bytecode::ListBreakdown(length) => {
+                let Value::List(vals) = self.pop()? else {
+                    return Err(VmErrorKind::ExpectedList);
+                };
+                if vals.len() != *length {
+                    return Err(VmErrorKind::ListLengthMismatch { expected: *length, found: vals.len() });
+                }
+                self.stack.extend(vals);
+            }
+            Bytecode::Call(function, overload) => {
+                let signature = function.info().signatures().get(*overload).ok_or(VmErrorKind::NoSuchOverload(*overload))?;
+                let arg_count = signature.arg_count();
+                self.ensure_poppable(arg_count)?;
+                let values = self.stack.split_off(self.stack.len() - arg_count);
+                let mut frame = Frame::default();
+                // handles passed to the call stay registered until it returns
+                let mut held = vec![];
+                let mut args = ArgList::new();
+                for (value, arg) in values.into_iter().zip(signature.args()) {
+                    match value {
+                        Value::Mut(mut handle) => {
+                            let val = erase_mut(handle.get_mut()?);
+                            held.push(handle);
+                            args.push_mut(unsafe { &mut *val });
+                        }
+                        Value::Ref(handle) => {
+                            let val = erase_ref(handle.read()?);
+                            held.push(handle);
+                            args.push_ref(unsafe { &*val });
+                        }
+                        Value::Box(val) => match arg.ownership() {
+                            Ownership::Owned => args.push_boxed(val),
+                            Ownership::Ref => {
+                                let val = frame.lend(val);
+                                args.push_ref(unsafe { &*val });
+                            }
+                            Ownership::Mut => {
+                                let val = frame.lend(val);
+                                args.push_mut(unsafe { &mut *val });
+                            }
+                        },
+                        Value::List(_) => return Err(VmErrorKind::ExpectedValue),
+                    }
+                }
+                let ret = function.call(args).map_err(VmErrorKind::CallFailed)?;
+                drop(held);
+                let value = match ret {
+                    Return::Owned(ret) => Value::Box(ret),
+                    Return::Ref(ret) => Value::Ref(self.arena.borrow_returned(erase_ref(ret) as *mut dyn PartialReflect, false, frame)?),
+                    Return::Mut(ret) => Value::Mut(self.arena.borrow_returned(erase_mut(ret), true, frame)?),
+                };
+                self.stack.push(value);
+            }
+            Bytecode::Query(query) => {
+                let arena = self.arena.clone();
+                // the verifier rejects programs where this query could run alongside a conflicting one, see `VerifyErrorKind::QueryConflict`
+                let iter: Box<dyn Iterator<Item = Result<Value, VmErrorKind>>> = match world {
+                    WorldAccess::Exclusive(world) => {
+                        // a query that's still iterating keeps its state, running the instruction again builds another
+                        let query = match self.queries.remove(ip) {
+                            Some(query) => query,
+                            None => {
+                                let type_registry = world.get_resource::<AppTypeRegistry>().ok_or(VmErrorKind::MissingTypeRegistry)?.clone();
+                                let resolved = ResolvedQuery::new(query, map, &type_registry.read())?;
+                                CachedQuery::new(world, resolved)
+                            }
+                        };
+                        self.running.push((*ip, query));
+                        let query = &mut self.running.last_mut().unwrap().1;
+                        let query_state = unsafe { &mut *(query.state.as_mut() as *mut QueryState<FilteredEntityMut<'static>>) };
+                        let ResolvedQuery { terms, filters } = query.resolved.clone();
+                        // changes count from when the world last cleared its trackers, the end of the previous frame
+                        let (last_run, this_run) = (world.last_change_tick(), world.read_change_tick());
+                        let world: &'static mut World = unsafe { &mut *(*world as *mut World) };
+                        Box::new(query_state.iter_mut(world).filter(move |entity| filters.iter().all(|filter| filter.matches(entity, last_run, this_run))).map(move |mut entity| query_item(&mut entity, &terms, &arena)))
+                    }
+                    WorldAccess::Declared { .. } => {
+                        let ResolvedQuery { terms, filters } = self.resolve(world, map, *ip, query)?;
+                        let WorldAccess::Declared { entities, last_run, this_run, .. } = world else { unreachable!() };
+                        let (last_run, this_run) = (*last_run, *this_run);
+                        let entities: &'static Query<'static, 'static, FilteredEntityMut<'static>> = unsafe { std::mem::transmute(*entities) };
+                        // the declared query covers every query of the program, which entities this one matches is checked one by one
+                        let required = terms.clone();
+                        let matches = move |entity: &FilteredEntityMut| required.iter().flatten().all(|term| term.optional || entity.contains_id(term.component_id)) && filters.iter().all(|filter| filter.matches(entity, last_run, this_run));
+                        Box::new(unsafe { entities.iter_unsafe() }.filter(move |entity| matches(entity)).map(move |mut entity| query_item(&mut entity, &terms, &arena)))
+                    }
+                };
+                self.stack.push(Value::Box(Box::new(ValueReflectIterThing { internal: Some(iter) })));
+            }
+            Bytecode::Get(query) => {
+                if !query.filters.is_empty() {
+                    return Err(VmErrorKind::UnsupportedQueryTerm("filter"));
+                }
+                self.ensure_poppable(1)?;
+                let entity = *self.pop()?.as_partial_reflect()?.try_downcast_ref::<Entity>().ok_or(VmErrorKind::ExpectedEntity)?;
+                let terms = self.resolve(world, map, *ip, query)?.terms;
+                // the terms are checked to be distinct components by the compiler, so the borrows can't overlap
+                let mut entity_mut = match world {
+                    WorldAccess::Exclusive(world) => FilteredEntityMut::from(EntityMut::from(world.get_entity_mut(entity).map_err(|_| VmErrorKind::NoSuchEntity(entity))?)),
+                    WorldAccess::Declared { entities, .. } => unsafe { entities.get_unchecked(entity) }.map_err(|_| VmErrorKind::NoSuchEntity(entity))?,
+                };
+                let value = query_item(&mut entity_mut, &terms, &self.arena)?;
+                self.stack.push(value);
+            }
+            Bytecode::IterRef | Bytecode::NextMut => {
+                let next = {
+                    let iterator = self.stack.last_mut().ok_or(VmErrorKind::StackUnderflow)?.as_partial_reflect_mut()?;
+                    let iterator = iterator.try_as_reflect_mut().and_then(|val| val.downcast_mut::<ValueReflectIterThing>()).and_then(|val| val.internal.as_mut()).ok_or(VmErrorKind::NotAnIterator)?;
+                    iterator.next()
+                };
+                if let Some(value) = next {
+                    let value = if let Bytecode::IterRef = bytecode { value?.into_shared() } else { value? };
+                    self.stack.push(value);
+                    // skip the jump out of the loop that follows
+                    *ip += 2;
+                    return Ok(());
+                }
+            }
+            Bytecode::Apply => {
+                // an owned receiver that's still borrowed would be written through while a handle reads it
+                self.ensure_poppable(2)?;
+                let applier = self.pop()?;
+                let mut receiver = self.pop()?;
+                receiver.as_partial_reflect_mut()?.try_apply(applier.as_partial_reflect()?).map_err(VmErrorKind::ApplyFailed)?;
+            }
+            Bytecode::Jump(jump_position) => {
+                if *jump_position > len {
+                    return Err(VmErrorKind::JumpOutOfRange(*jump_position));
+                }
+                *ip = *jump_position;
+                return Ok(());
+            }
+            Bytecode::JumpIfNot(jump_position) => {
+                if *jump_position > len {
+                    return Err(VmErrorKind::JumpOutOfRange(*jump_position));
+                }
+                self.ensure_poppable(1)?;
+                if !*self.pop()?.as_partial_reflect()?.try_downcast_ref::<bool>().ok_or(VmErrorKind::ExpectedBool)? {
+                    *ip = *jump_position;
+                    return Ok(());
+                }
+            }
+        }
+        *ip += 1;
+        Ok(())
+    }
+}
+
+impl Bytecode {
+    /// Statically checks `bytecode` against the world's type registry and components, see [`verifier::verify`].
+    /// `inputs` are the types of the owned values the program is run with.
+    /// Registers the reflected components `bytecode` queries, so [`Bytecode::verify`] knows their access before any of them is spawned.
+    pub fn register_components(world: &mut World, bytecode: &[Bytecode]) {
+        let mut named = vec![];
+        for op in bytecode {
+            if let Bytecode::Query(query) | Bytecode::Get(query) = op {
+                query.named_components(&mut named);
+            }
+        }
+        let type_registry = world.resource::<AppTypeRegistry>().clone();
+        for info in named {
+            let reflect_component = type_registry.read().get_type_data::<ReflectComponent>(info.type_id()).cloned();
+            if let Some(reflect_component) = reflect_component {
+                reflect_component.register_component(world);
+            }
+        }
+    }
+
+    /// Fails on a query naming a component that isn't registered, see [`Bytecode::register_components`].
+    pub fn verify(world: &World, bytecode: &[Bytecode], inputs: &[&'static TypeInfo]) -> Result<(), verifier::VerifyError> {
+        let type_registry = world.get_resource::<AppTypeRegistry>().ok_or(verifier::VerifyError { ip: 0, opcode: String::new(), kind: verifier::VerifyErrorKind::MissingTypeRegistry })?.read();
+        let inputs = inputs.iter().map(|info| verifier::Kind::Value(Ownership::Owned, Some(*info))).collect();
+        let map = component_map(world);
+        let mut accesses = HashMap::new();
+        for (ip, op) in bytecode.iter().enumerate() {
+            let (Bytecode::Query(query) | Bytecode::Get(query)) = op else {
+                continue;
+            };
+            let access = query.access(&map).map_err(|error| verifier::VerifyError { ip, opcode: format!("{:?}", op), kind: verifier::VerifyErrorKind::UnresolvedQuery(error.to_string()) })?;
+            accesses.insert(ip, access);
+        }
+        verifier::verify(bytecode, inputs, &type_registry, &accesses)
+    }
+
+    pub fn run(world: &mut World, bytecode: &[Bytecode]) -> Result<(), VmError> {
+        Self::run_with(world, bytecode, vec![])
+    }
+
+    /// Runs `bytecode` with `inputs` already on the stack, like the event that triggered it.
+    pub fn run_with(world: &mut World, bytecode: &[Bytecode], inputs: Vec<Value>) -> Result<(), VmError> {
+        Self::run_cached(world, bytecode, inputs, &mut QueryCache::default())
+    }
+
+    /// Like [`Bytecode::run_with`], reusing what earlier runs of the same `bytecode` left in `cache`.
+    pub fn run_cached(world: &mut World, bytecode: &[Bytecode], inputs: Vec<Value>, cache: &mut QueryCache) -> Result<(), VmError> {
+        Self::run_keeping(world, bytecode, inputs, 0..0, cache).0
+    }
+
+    /// Like [`Bytecode::run_cached`], handing back the inputs in `keep` as the program left them, like a graph's variables.
+    /// They come back even if the program fails, as far as it got.
+    pub(crate) fn run_keeping(world: &mut World, bytecode: &[Bytecode], inputs: Vec<Value>, keep: Range<usize>, cache: &mut QueryCache) -> (Result<(), VmError>, Vec<Value>) {
+        cache.refresh(world);
+        let mut vm = Vm {
+            stack: inputs,
+            queries: std::mem::take(&mut cache.queries),
+            resolved: std::mem::take(&mut cache.resolved),
+            ..Vm::default()
+        };
+        let result = vm.execute(&mut WorldAccess::Exclusive(world), &cache.components, bytecode, keep);
+        cache.queries = vm.queries;
+        cache.resolved = vm.resolved;
+        for (ip, query) in vm.running {
+            cache.queries.entry(ip).or_insert(query);
+        }
+        result
+    }
+
+    /// Runs `bytecode` from a script system that declared the [`DeclaredAccess`] of it as `entities`, handing back the inputs in `keep` like [`Bytecode::run_keeping`].
+    /// The components in `cache` have to include every one the program names, and changes count since the system last ran.
+    pub(crate) fn run_declared(entities: &Query<'_, '_, FilteredEntityMut<'static>>, type_registry: AppTypeRegistry, ticks: &SystemChangeTick, bytecode: &[Bytecode], inputs: Vec<Value>, keep: Range<usize>, cache: &mut QueryCache) -> (Result<(), VmError>, Vec<Value>) {
+        let mut vm = Vm { stack: inputs, resolved: std::mem::take(&mut cache.resolved), ..Vm::default() };
+        let (last_run, this_run) = (ticks.last_run(), ticks.this_run());
+        let result = vm.execute(&mut WorldAccess::Declared { entities, type_registry, last_run, this_run }, &cache.components, bytecode, keep);
+        cache.resolved = vm.resolved;
+        result
+    }
+}
+
+impl Vm {
+    /// Runs `bytecode` and hands back the values in `keep`, or as many of them as are still on the stack.
+    fn execute(&mut self, world: &mut WorldAccess, map: &HashMap<TypeId, ComponentId>, bytecode: &[Bytecode], keep: Range<usize>) -> (Result<(), VmError>, Vec<Value>) {
+        let mut ip = 0;
+        let result = loop {
+            let Some(op) = bytecode.get(ip) else {
+                break Ok(());
+            };
+            if let Err(kind) = self.step(world, map, op, &mut ip, bytecode.len()) {
+                break Err(VmError { ip, opcode: format!("{:?}", op), stack: format!("{:?}", self.stack), kind });
+            }
+        };
+        // Query iterators on the stack borrow the query states, so they have to go first, and whatever borrows the kept values with them.
+        self.stack.truncate(keep.end);
+        let kept = self.stack.drain(keep.start.min(self.stack.len())..).collect();
+        self.stack.clear();
+        (result, kept)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Component, Reflect, Debug, PartialEq)]
+    #[reflect(Component)]
+    struct Speed(f32, f32);
+
+    fn world() -> World {
+        let mut world = World::new();
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<f32>();
+        registry.write().register::<Speed>();
+        world.insert_resource(registry);
+        world
+    }
+
+    fn push(value: impl PartialReflect) -> Bytecode {
+        Bytecode::Push(Literal::Box(Box::new(value)))
+    }
+
+    /// Runs `bytecode` and hands back everything it left on the stack.
+    fn run(bytecode: &[Bytecode]) -> Result<Vec<Value>, VmError> {
+        let (result, stack) = Bytecode::run_keeping(&mut world(), bytecode, vec![], 0..usize::MAX, &mut QueryCache::default());
+        result.map(|()| stack)
+    }
+
+    fn f32s(stack: &[Value]) -> Vec<f32> {
+        stack.iter().map(|value| *value.as_partial_reflect().unwrap().try_downcast_ref::<f32>().unwrap()).collect()
+    }
+
+    #[test]
+    fn errors_report_where_the_program_stopped() {
+        let error = run(&[push(1.0f32), Bytecode::Pop, Bytecode::Pop]).unwrap_err();
+        assert_eq!((error.ip, error.opcode.as_str(), error.stack.as_str()), (2, "Pop", "[]"));
+        assert!(matches!(error.kind, VmErrorKind::StackUnderflow));
+        let error = run(&[push(1.0f32), Bytecode::Clone(3)]).unwrap_err();
+        assert!(matches!(error.kind, VmErrorKind::StackIndexOutOfRange(3)));
+        assert!(matches!(run(&[Bytecode::Jump(5)]).unwrap_err().kind, VmErrorKind::JumpOutOfRange(5)));
+    }
+
+    #[test]
+    fn jump_if_not_branches_on_the_popped_bool() {
+        let program = |condition: bool| [push(condition), Bytecode::JumpIfNot(4), push(1.0f32), Bytecode::Jump(5), push(2.0f32)];
+        assert_eq!(f32s(&run(&program(true)).unwrap()), [1.0]);
+        assert_eq!(f32s(&run(&program(false)).unwrap()), [2.0]);
+        assert!(matches!(run(&[push(1.0f32), Bytecode::JumpIfNot(0)]).unwrap_err().kind, VmErrorKind::ExpectedBool));
+    }
+
+    #[test]
+    fn clones_and_dups_copy_values() {
+        assert_eq!(f32s(&run(&[push(1.0f32), Bytecode::Clone(0), Bytecode::Dup(1)]).unwrap()), [1.0, 1.0, 1.0]);
+        let stack = run(&[push(Speed(1.0, 2.0)), Bytecode::DupField(0, 1), Bytecode::Clone(1)]).unwrap();
+        assert_eq!(f32s(&stack[1..]), [2.0, 2.0]);
+    }
+
+    #[test]
+    fn fields_are_set_through_mutable_borrows() {
+        let stack = run(&[push(Speed(1.0, 2.0)), Bytecode::MutField(0, 1), push(3.0f32), Bytecode::Apply]).unwrap();
+        assert_eq!(stack[0].as_partial_reflect().unwrap().try_downcast_ref::<Speed>(), Some(&Speed(1.0, 3.0)));
+        assert!(run(&[push(Speed(1.0, 2.0)), Bytecode::RefField(0, 1), Bytecode::MutField(0, 0)]).is_ok());
+        let error = run(&[push(Speed(1.0, 2.0)), Bytecode::RefField(0, 1), Bytecode::Mut(0)]).unwrap_err();
+        assert!(matches!(error.kind, VmErrorKind::Borrow(BorrowError::AlreadyBorrowed(_))));
+        let error = run(&[push(Speed(1.0, 2.0)), Bytecode::Ref(0), Bytecode::MutField(1, 0)]).unwrap_err();
+        assert!(matches!(error.kind, VmErrorKind::Borrow(BorrowError::NotExclusive(_))));
+    }
+
+    #[test]
+    fn one_mutable_borrow_cant_be_passed_twice() {
+        let both = |_: &Speed, _: &mut Speed| {};
+        let error = run(&[push(Speed(1.0, 2.0)), Bytecode::Mut(0), Bytecode::Ref(1), Bytecode::Dup(1), Bytecode::Call(both.into_function(), 0)]).unwrap_err();
+        assert!(matches!(error.kind, VmErrorKind::Borrow(BorrowError::AlreadyBorrowed(_))));
+        let reversed = |_: &mut Speed, _: &Speed| {};
+        let error = run(&[push(Speed(1.0, 2.0)), Bytecode::Mut(0), Bytecode::Ref(1), Bytecode::Call(reversed.into_function(), 0)]).unwrap_err();
+        assert!(matches!(error.kind, VmErrorKind::Borrow(BorrowError::AlreadyBorrowed(_))));
+        let error = run(&[push(Speed(1.0, 2.0)), Bytecode::Mut(0), Bytecode::Clone(0)]).unwrap_err();
+        assert!(matches!(error.kind, VmErrorKind::Borrow(BorrowError::AlreadyMutablyBorrowed(_))));
+    }
+
+    #[test]
+    fn lists_break_down_into_their_values() {
+        let list = || Bytecode::Push(Literal::List(vec![Literal::Box(Box::new(1.0f32)), Literal::Box(Box::new(2.0f32))]));
+        assert_eq!(f32s(&run(&[list(), Bytecode::ListBreakdown(2)]).unwrap()), [1.0, 2.0]);
+        let error = run(&[list(), Bytecode::ListBreakdown(3)]).unwrap_err();
+        assert!(matches!(error.kind, VmErrorKind::ListLengthMismatch { expected: 3, found: 2 }));
+        assert!(matches!(run(&[push(1.0f32), Bytecode::ListBreakdown(1)]).unwrap_err().kind, VmErrorKind::ExpectedList));
+    }
+
+    #[test]
+    fn calls_go_through_the_picked_overload() {
+        let function = (|value: f32| value * 2.0).into_function().with_overload(|a: f32, b: f32| a + b);
+        assert_eq!(f32s(&run(&[push(3.0f32), Bytecode::Call(function.clone(), 0)]).unwrap()), [6.0]);
+        assert_eq!(f32s(&run(&[push(1.0f32), push(2.0f32), Bytecode::Call(function.clone(), 1)]).unwrap()), [3.0]);
+        assert!(matches!(run(&[Bytecode::Call(function, 2)]).unwrap_err().kind, VmErrorKind::NoSuchOverload(2)));
+    }
+}
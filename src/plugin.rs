@@ -0,0 +1,118 @@
+use crate::history::History;
+use crate::runtime::EntryPointsPlugin;
+use crate::script_graph::ScriptGraphPlugin;
+use crate::ui::{FunctionRegistry, GraphBytecode, GraphClipboard, GraphDiagnostics, GraphPath, ScriptFunctions, SnarlResource, import_functions, setup, ui_system};
+use bevy::ecs::schedule::{InternedScheduleLabel, ScheduleLabel};
+use bevy::prelude::*;
+use bevy_egui::{EguiPlugin, EguiPrimaryContextPass};
+use std::sync::{Arc, RwLock};
+
+type RegisterFunctions = Box<dyn Fn(&mut FunctionRegistry) + Send + Sync>;
+
+/// Compiles and runs node graph scripts. Add `DefaultPlugins` first, or just `MinimalPlugins` when [`headless`](Self::headless).
+///
+/// ```ignore
+/// App::new().add_plugins(MinimalPlugins).register_function(jump).add_plugins(NodeScriptingPlugin::headless()).run();
+/// ```
+pub struct NodeScriptingPlugin {
+    functions: Vec<RegisterFunctions>,
+    startup_schedule: InternedScheduleLabel,
+    update_schedule: InternedScheduleLabel,
+    fixed_update_schedule: InternedScheduleLabel,
+    editor: bool,
+    history_limit: usize,
+}
+
+impl Default for NodeScriptingPlugin {
+    fn default() -> Self {
+        NodeScriptingPlugin {
+            functions: vec![],
+            startup_schedule: Startup.intern(),
+            update_schedule: Update.intern(),
+            fixed_update_schedule: FixedUpdate.intern(),
+            editor: true,
+            history_limit: 100,
+        }
+    }
+}
+
+impl NodeScriptingPlugin {
+    /// Without the editor window, so graphs run with only `MinimalPlugins`. Compile them with [`compile_graph`](crate::ui::compile_graph).
+    pub fn headless() -> Self {
+        NodeScriptingPlugin { editor: false, ..Default::default() }
+    }
+
+    pub fn with_editor(mut self, editor: bool) -> Self {
+        self.editor = editor;
+        self
+    }
+
+    /// How many edits the editor can undo.
+    pub fn with_history_limit(mut self, limit: usize) -> Self {
+        self.history_limit = limit;
+        self
+    }
+
+    /// Adds functions to the [`FunctionRegistry`] graphs can call, besides those imported from bevy's `AppFunctionRegistry`.
+    pub fn with_functions(mut self, register: impl Fn(&mut FunctionRegistry) + Send + Sync + 'static) -> Self {
+        self.functions.push(Box::new(register));
+        self
+    }
+
+    /// The schedule `On Startup` entries run in.
+    pub fn with_startup_schedule(mut self, schedule: impl ScheduleLabel) -> Self {
+        self.startup_schedule = schedule.intern();
+        self
+    }
+
+    /// The schedule `On Update` and `On Event` entries run in.
+    /// Anything but `First`, which adds the systems of newly installed graphs to it.
+    pub fn with_update_schedule(mut self, schedule: impl ScheduleLabel) -> Self {
+        self.update_schedule = schedule.intern();
+        self
+    }
+
+    /// The schedule `On FixedUpdate` entries run in.
+    /// Anything but `First`, which adds the systems of newly installed graphs to it.
+    pub fn with_fixed_update_schedule(mut self, schedule: impl ScheduleLabel) -> Self {
+        self.fixed_update_schedule = schedule.intern();
+        self
+    }
+}
+
+impl Plugin for NodeScriptingPlugin {
+    fn build(&self, app: &mut App) {
+        let mut functions = FunctionRegistry::default();
+        functions.register_script_fns();
+        for register in &self.functions {
+            register(&mut functions);
+        }
+        app.insert_resource(ScriptFunctions(Arc::new(RwLock::new(functions))))
+            .init_resource::<SnarlResource>()
+            .init_resource::<GraphDiagnostics>()
+            .register_type::<Transform>()
+            .register_type::<Vec3>()
+            .register_type_data::<Vec3, ReflectDefault>()
+            .register_type_data::<f32, ReflectDefault>()
+            .register_type_data::<i32, ReflectDefault>()
+            .register_type_data::<String, ReflectDefault>()
+            .add_plugins(EntryPointsPlugin {
+                startup_schedule: self.startup_schedule,
+                update_schedule: self.update_schedule,
+                fixed_update_schedule: self.fixed_update_schedule,
+            })
+            .add_systems(First, import_functions);
+        if app.is_plugin_added::<AssetPlugin>() {
+            app.add_plugins(ScriptGraphPlugin);
+        }
+        if self.editor {
+            app.add_plugins(EguiPlugin::default()).init_resource::<GraphPath>().init_resource::<GraphClipboard>().init_resource::<GraphBytecode>().insert_resource(History::with_limit(self.history_limit)).add_systems(EguiPrimaryContextPass, ui_system).add_systems(Startup, setup);
+        }
+    }
+
+    fn finish(&self, app: &mut App) {
+        // every plugin has registered its functions by now, later ones are picked up in `First`
+        let world = app.world();
+        world.resource::<ScriptFunctions>().write().import(&world.resource::<AppFunctionRegistry>().read(), &world.resource::<AppTypeRegistry>().read());
+    }
+}
@@ -0,0 +1,373 @@
+use crate::again::start_node::Entry;
+use crate::again::variable_node::GraphVariable;
+use crate::assembly::{AssemblyErrorKind, parse_query_filter, parse_query_term, parse_value};
+use crate::ui::{FunctionRegistry, ScriptFunctions};
+use crate::verifier::VerifyError;
+use crate::{Bytecode, Literal, QueryDataType, QueryFilterType, QueryWrapper};
+use bevy::prelude::{AppTypeRegistry, Entity, World};
+use bevy::reflect::PartialReflect;
+use bevy::reflect::func::args::Ownership;
+use bevy::reflect::func::{DynamicFunction, SignatureInfo};
+use bevy::reflect::serde::ReflectSerializer;
+use bevy::reflect::{TypeRegistry, Typed};
+use serde::{Deserialize, Serialize};
+use std::fmt::{Display, Formatter};
+
+// A `Program` is `Vec<Bytecode>` with everything that only makes sense inside one process replaced by names:
+// functions by their registered name and signature, constants by their reflect-serialized RON,
+// and query terms and filters by component type path. `Program::load` resolves the names again against the registries
+// and verifies the result, since a file can say anything.
+
+pub const PROGRAM_VERSION: u32 = 2;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Program {
+    pub version: u32,
+    /// When the program runs, which decides the value it starts with.
+    #[serde(default)]
+    pub entry: Entry,
+    /// Whether the program starts with the entity it's attached to, see [`SELF_SLOT`](crate::compiler::SELF_SLOT).
+    #[serde(default)]
+    pub uses_self: bool,
+    /// The graph's variables, which the program starts with after the entity.
+    #[serde(default)]
+    pub variables: Vec<GraphVariable>,
+    pub instructions: Vec<Instruction>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Instruction {
+    Pop,
+    Push(Constant),
+    Clone(usize),
+    Dup(usize),
+    Ref(usize),
+    Mut(usize),
+    DupField(usize, usize),
+    RefField(usize, usize),
+    MutField(usize, usize),
+    ListBreakdown(usize),
+    Call(FunctionRef),
+    /// Query terms, then filters.
+    Query(Vec<String>, Vec<String>),
+    /// Query terms, then filters.
+    Get(Vec<String>, Vec<String>),
+    IterRef,
+    NextMut,
+    Apply,
+    Jump(usize),
+    JumpIfNot(usize),
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum Constant {
+    /// A value serialized through `ReflectSerializer` as RON.
+    Reflect(String),
+    List(Vec<Constant>),
+}
+
+/// A function by name, with the signature it had when the program was compiled, like `["&glam::Vec3", "f32"]`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct FunctionRef {
+    pub name: String,
+    pub args: Vec<String>,
+    pub ret: String,
+}
+
+#[derive(Debug)]
+pub enum ProgramError {
+    UnsupportedVersion(u32),
+    AnonymousFunction,
+    UnknownFunction(String),
+    SignatureMismatch(String),
+    Unserializable(String),
+    Resolve(AssemblyErrorKind),
+    Verify(VerifyError),
+    Format(String),
+    Io(String),
+}
+
+impl Display for ProgramError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProgramError::UnsupportedVersion(version) => write!(f, "program version {version} is not supported, expected {PROGRAM_VERSION}"),
+            ProgramError::AnonymousFunction => f.write_str("anonymous functions can't be saved"),
+            ProgramError::UnknownFunction(name) => write!(f, "no function named `{name}` is registered"),
+            ProgramError::SignatureMismatch(name) => write!(f, "`{name}` no longer has the signature it was compiled against"),
+            ProgramError::Unserializable(type_path) => write!(f, "`{type_path}` can't be serialized"),
+            ProgramError::Resolve(kind) => write!(f, "{kind}"),
+            ProgramError::Verify(error) => write!(f, "{error}"),
+            ProgramError::Format(error) => write!(f, "{error}"),
+            ProgramError::Io(error) => write!(f, "{error}"),
+        }
+    }
+}
+
+impl std::error::Error for ProgramError {}
+
+impl From<AssemblyErrorKind> for ProgramError {
+    fn from(kind: AssemblyErrorKind) -> Self {
+        ProgramError::Resolve(kind)
+    }
+}
+
+fn signature_of(info: &SignatureInfo) -> (Vec<String>, String) {
+    let rep = |ownership: Ownership, type_path: &str| match ownership {
+        Ownership::Owned => type_path.to_string(),
+        Ownership::Ref => format!("&{type_path}"),
+        Ownership::Mut => format!("&mut {type_path}"),
+    };
+    let args = info.args().iter().map(|arg| rep(arg.ownership(), arg.type_path())).collect();
+    let ret = rep(info.return_info().ownership(), info.return_info().type_path());
+    (args, ret)
+}
+
+impl FunctionRef {
+    fn new(function: &DynamicFunction<'static>, overload: usize) -> Result<Self, ProgramError> {
+        let name = function.name().ok_or(ProgramError::AnonymousFunction)?.to_string();
+        let (args, ret) = signature_of(&function.info().signatures()[overload]);
+        Ok(FunctionRef { name, args, ret })
+    }
+
+    /// The function and the index of the overload with the recorded signature, which may have moved since.
+    fn resolve(&self, functions: &FunctionRegistry) -> Result<(DynamicFunction<'static>, usize), ProgramError> {
+        let function = functions.get(&self.name).ok_or_else(|| ProgramError::UnknownFunction(self.name.clone()))?;
+        let overload = function.info().signatures().iter().position(|signature| signature_of(signature) == (self.args.clone(), self.ret.clone())).ok_or_else(|| ProgramError::SignatureMismatch(self.name.clone()))?;
+        Ok((function.clone(), overload))
+    }
+}
+
+impl Constant {
+    fn new(literal: &Literal, type_registry: &TypeRegistry) -> Result<Self, ProgramError> {
+        match literal {
+            Literal::Box(val) => {
+                let val: &dyn PartialReflect = val.as_ref();
+                ron::to_string(&ReflectSerializer::new(val, type_registry)).map(Constant::Reflect).map_err(|_| ProgramError::Unserializable(val.reflect_type_path().to_string()))
+            }
+            Literal::List(vals) => Ok(Constant::List(vals.iter().map(|val| Constant::new(val, type_registry)).collect::<Result<_, _>>()?)),
+        }
+    }
+
+    fn resolve(&self, type_registry: &TypeRegistry) -> Result<Literal, ProgramError> {
+        match self {
+            Constant::Reflect(ron) => Ok(parse_value(ron, type_registry)?),
+            Constant::List(constants) => Ok(Literal::List(constants.iter().map(|constant| constant.resolve(type_registry)).collect::<Result<_, _>>()?)),
+        }
+    }
+}
+
+impl Program {
+    pub fn from_bytecode(entry: Entry, uses_self: bool, variables: Vec<GraphVariable>, bytecode: &[Bytecode], type_registry: &TypeRegistry) -> Result<Self, ProgramError> {
+        let instructions = bytecode
+            .iter()
+            .map(|op| {
+                Ok(match op {
+                    Bytecode::Pop => Instruction::Pop,
+                    Bytecode::Push(value) => Instruction::Push(Constant::new(value, type_registry)?),
+                    Bytecode::Clone(index) => Instruction::Clone(*index),
+                    Bytecode::Dup(index) => Instruction::Dup(*index),
+                    Bytecode::Ref(index) => Instruction::Ref(*index),
+                    Bytecode::Mut(index) => Instruction::Mut(*index),
+                    Bytecode::DupField(index, field) => Instruction::DupField(*index, *field),
+                    Bytecode::RefField(index, field) => Instruction::RefField(*index, *field),
+                    Bytecode::MutField(index, field) => Instruction::MutField(*index, *field),
+                    Bytecode::ListBreakdown(length) => Instruction::ListBreakdown(*length),
+                    Bytecode::Call(function, overload) => Instruction::Call(FunctionRef::new(function, *overload)?),
+                    Bytecode::Query(QueryWrapper { queries, filters }) => Instruction::Query(queries.iter().map(QueryDataType::to_string).collect(), filters.iter().map(QueryFilterType::to_string).collect()),
+                    Bytecode::Get(QueryWrapper { queries, filters }) => Instruction::Get(queries.iter().map(QueryDataType::to_string).collect(), filters.iter().map(QueryFilterType::to_string).collect()),
+                    Bytecode::IterRef => Instruction::IterRef,
+                    Bytecode::NextMut => Instruction::NextMut,
+                    Bytecode::Apply => Instruction::Apply,
+                    Bytecode::Jump(target) => Instruction::Jump(*target),
+                    Bytecode::JumpIfNot(target) => Instruction::JumpIfNot(*target),
+                })
+            })
+            .collect::<Result<_, ProgramError>>()?;
+        Ok(Program { version: PROGRAM_VERSION, entry, uses_self, variables, instructions })
+    }
+
+    /// Turns the program back into bytecode against the world's registries and verifies it with the values its entry starts with.
+    /// Fails if a function or type it names isn't registered anymore, or the bytecode doesn't hold up.
+    pub fn load(&self, world: &mut World) -> Result<Vec<Bytecode>, ProgramError> {
+        if self.version != PROGRAM_VERSION {
+            return Err(ProgramError::UnsupportedVersion(self.version));
+        }
+        let bytecode = self.resolve(&world.resource::<ScriptFunctions>().read(), &world.resource::<AppTypeRegistry>().read())?;
+        let payload = match &self.entry {
+            Entry::Event(type_path) => Some(world.resource::<AppTypeRegistry>().read().get_with_type_path(type_path).ok_or_else(|| AssemblyErrorKind::UnknownType(type_path.clone()))?.type_info()),
+            entry => entry.payload(&world.resource::<AppTypeRegistry>().read()),
+        };
+        let variables = self.variables.iter().map(|variable| variable.type_info(&world.resource::<AppTypeRegistry>().read()).ok_or_else(|| AssemblyErrorKind::UnknownType(variable.type_path.clone()))).collect::<Result<Vec<_>, _>>()?;
+        let inputs: Vec<_> = self.uses_self.then(Entity::type_info).into_iter().chain(variables).chain(payload).collect();
+        Bytecode::register_components(world, &bytecode);
+        Bytecode::verify(world, &bytecode, &inputs).map_err(ProgramError::Verify)?;
+        Ok(bytecode)
+    }
+
+    fn resolve(&self, functions: &FunctionRegistry, type_registry: &TypeRegistry) -> Result<Vec<Bytecode>, ProgramError> {
+        self.instructions
+            .iter()
+            .map(|instruction| {
+                Ok(match instruction {
+                    Instruction::Pop => Bytecode::Pop,
+                    Instruction::Push(constant) => Bytecode::Push(constant.resolve(type_registry)?),
+                    Instruction::Clone(index) => Bytecode::Clone(*index),
+                    Instruction::Dup(index) => Bytecode::Dup(*index),
+                    Instruction::Ref(index) => Bytecode::Ref(*index),
+                    Instruction::Mut(index) => Bytecode::Mut(*index),
+                    Instruction::DupField(index, field) => Bytecode::DupField(*index, *field),
+                    Instruction::RefField(index, field) => Bytecode::RefField(*index, *field),
+                    Instruction::MutField(index, field) => Bytecode::MutField(*index, *field),
+                    Instruction::ListBreakdown(length) => Bytecode::ListBreakdown(*length),
+                    Instruction::Call(function) => {
+                        let (function, overload) = function.resolve(functions)?;
+                        Bytecode::Call(function, overload)
+                    }
+                    Instruction::Query(terms, filters) => Bytecode::Query(Self::query(terms, filters, type_registry)?),
+                    Instruction::Get(terms, filters) => Bytecode::Get(Self::query(terms, filters, type_registry)?),
+                    Instruction::IterRef => Bytecode::IterRef,
+                    Instruction::NextMut => Bytecode::NextMut,
+                    Instruction::Apply => Bytecode::Apply,
+                    Instruction::Jump(target) => Bytecode::Jump(*target),
+                    Instruction::JumpIfNot(target) => Bytecode::JumpIfNot(*target),
+                })
+            })
+            .collect()
+    }
+
+    fn query(terms: &[String], filters: &[String], type_registry: &TypeRegistry) -> Result<QueryWrapper, ProgramError> {
+        let terms = terms.iter().map(|term| parse_query_term(term, type_registry)).collect::<Result<_, _>>()?;
+        let filters = filters.iter().map(|filter| parse_query_filter(filter, type_registry)).collect::<Result<_, _>>()?;
+        Ok(QueryWrapper::new(terms).with_filters(filters))
+    }
+
+    pub fn to_ron(&self) -> Result<String, ProgramError> {
+        ron::ser::to_string_pretty(self, Default::default()).map_err(|error| ProgramError::Format(error.to_string()))
+    }
+
+    pub fn from_ron(source: &str) -> Result<Self, ProgramError> {
+        ron::from_str(source).map_err(|error| ProgramError::Format(error.to_string()))
+    }
+}
+
+/// The programs of one graph, one for each of its entries, the way `.program.ron` files hold them.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+pub struct ProgramFile {
+    pub programs: Vec<Program>,
+}
+
+impl ProgramFile {
+    pub fn to_ron(&self) -> Result<String, ProgramError> {
+        ron::ser::to_string_pretty(self, Default::default()).map_err(|error| ProgramError::Format(error.to_string()))
+    }
+
+    pub fn from_ron(source: &str) -> Result<Self, ProgramError> {
+        ron::from_str(source).map_err(|error| ProgramError::Format(error.to_string()))
+    }
+
+    /// Writes the programs to `path`, creating the folders leading up to it.
+    pub fn save_to(&self, path: &str) -> Result<(), ProgramError> {
+        let io = |error: std::io::Error| ProgramError::Io(error.to_string());
+        if let Some(parent) = std::path::Path::new(path).parent() {
+            std::fs::create_dir_all(parent).map_err(io)?;
+        }
+        std::fs::write(path, self.to_ron()?).map_err(io)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembly::{assemble, disassemble};
+    use crate::runtime::{self, EntryPoints, GraphSource};
+    use crate::verifier::VerifyErrorKind;
+    use bevy::prelude::*;
+    use bevy::reflect::TypePath;
+
+    #[derive(Component, Reflect, Debug, PartialEq)]
+    #[reflect(Component)]
+    struct Speed(f32);
+
+    fn world() -> World {
+        let mut world = World::new();
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<f32>();
+        registry.write().register::<Speed>();
+        world.insert_resource(registry);
+        world.init_resource::<ScriptFunctions>();
+        world.init_resource::<EntryPoints>();
+        world.register_component::<Speed>();
+        world
+    }
+
+    fn program(world: &World, entry: Entry, source: &str) -> Program {
+        let type_registry = world.resource::<AppTypeRegistry>().read();
+        let bytecode = assemble(source, &world.resource::<ScriptFunctions>().read(), &type_registry).unwrap();
+        Program::from_bytecode(entry, false, vec![], &bytecode, &type_registry).unwrap()
+    }
+
+    fn speed_setter() -> String {
+        let speed = Speed::type_path();
+        format!("query &mut {speed}\nL0:\n    next_mut\n    jump L1\n    list_breakdown 1\n    push {{\"{speed}\":(3.0)}}\n    apply\n    jump L0\nL1:\n    pop\n")
+    }
+
+    #[test]
+    fn saved_program_loads_back() {
+        let mut world = world();
+        let program = program(&world, Entry::Update, &speed_setter());
+        let loaded = Program::from_ron(&program.to_ron().unwrap()).unwrap();
+        assert_eq!(loaded, program);
+        let bytecode = loaded.load(&mut world).unwrap();
+        assert_eq!(disassemble(&bytecode, &world.resource::<AppTypeRegistry>().read()).trim(), speed_setter().trim());
+    }
+
+    #[test]
+    fn loading_verifies() {
+        let mut world = world();
+        let program = program(&world, Entry::Run, "push {\"f32\": 1.0}\nlist_breakdown 1\n");
+        assert!(matches!(program.load(&mut world), Err(ProgramError::Verify(VerifyError { ip: 1, kind: VerifyErrorKind::ExpectedList(_), .. }))));
+    }
+
+    #[test]
+    fn loading_checks_the_version() {
+        let mut world = world();
+        let mut program = program(&world, Entry::Run, "");
+        program.version = PROGRAM_VERSION - 1;
+        assert!(matches!(program.load(&mut world), Err(ProgramError::UnsupportedVersion(_))));
+    }
+
+    #[test]
+    fn loading_needs_the_functions_the_program_calls() {
+        let mut saved = world();
+        saved.resource::<ScriptFunctions>().write().register_freestanding((|value: f32| value * 2.0).into_function().with_name("double"));
+        let program = program(&saved, Entry::Run, "push {\"f32\": 1.0}\ncall double\npop\n");
+        assert!(matches!(program.load(&mut world()), Err(ProgramError::UnknownFunction(name)) if name == "double"));
+        let mut changed = world();
+        changed.resource::<ScriptFunctions>().write().register_freestanding((|value: Speed| value.0 * 2.0).into_function().with_name("double"));
+        assert!(matches!(program.load(&mut changed), Err(ProgramError::SignatureMismatch(name)) if name == "double"));
+        assert!(program.load(&mut saved).is_ok());
+    }
+
+    #[test]
+    fn installed_program_runs() {
+        let mut world = world();
+        let entity = world.spawn(Speed(1.0)).id();
+        let file = ProgramFile { programs: vec![program(&world, Entry::Run, &speed_setter())] };
+        let compiled = runtime::load_programs(&mut world, &ProgramFile::from_ron(&file.to_ron().unwrap()).unwrap()).unwrap();
+        assert!(runtime::install(&mut world, GraphSource::Editor, &compiled).is_empty());
+        assert_eq!(world.get::<Speed>(entity), Some(&Speed(3.0)));
+    }
+
+    #[test]
+    fn get_keeps_its_filters() {
+        let mut world = world();
+        let type_registry = world.resource::<AppTypeRegistry>().read();
+        let query = |filters| QueryWrapper::new(vec![parse_query_term("entity", &type_registry).unwrap()]).with_filters(filters);
+        let filters = vec![parse_query_filter(&format!("with {}", Speed::type_path()), &type_registry).unwrap()];
+        let program = Program::from_bytecode(Entry::Run, true, vec![], &[Bytecode::Get(query(filters)), Bytecode::Pop], &type_registry).unwrap();
+        drop(type_registry);
+        assert!(matches!(&program.instructions[0], Instruction::Get(_, filters) if filters.len() == 1));
+        let bytecode = program.load(&mut world).unwrap();
+        assert!(matches!(&bytecode[0], Bytecode::Get(QueryWrapper { filters, .. }) if filters.len() == 1));
+    }
+}
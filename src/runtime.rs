@@ -0,0 +1,662 @@
+use crate::again::start_node::Entry;
+use crate::again::variable_node::GraphVariable;
+use crate::again::{Node, Viewer};
+use crate::assembly::disassemble;
+use crate::compiler::{self, CompiledEntry};
+use crate::diagnostics::{Diagnostics, Location};
+use crate::program::{self, ProgramError, ProgramFile};
+use crate::ui::ScriptFunctions;
+use crate::verifier::VerifyErrorKind;
+use crate::{Bytecode, DeclaredAccess, QueryCache, Value, VmError, VmErrorKind};
+use bevy::ecs::event::{EventCursor, Events};
+use bevy::ecs::schedule::{InternedScheduleLabel, ScheduleConfigs};
+use bevy::ecs::system::{FilteredResourcesParamBuilder, ParamBuilder, QueryParamBuilder, ScheduleSystem, SystemChangeTick, SystemParamBuilder};
+use bevy::ecs::world::{FilteredEntityMut, FilteredResources, FilteredResourcesBuilder};
+use bevy::prelude::*;
+use bevy::reflect::{FromType, TypeRegistry, Typed};
+use egui_snarl::{NodeId, Snarl};
+use std::any::Any;
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError, TryLockError};
+
+/// Lets a graph start from an event of this type. Register it with [`ScriptEventAppExt::register_script_event`].
+#[derive(Clone)]
+pub struct ReflectScriptEvent {
+    new_cursor: fn() -> Box<dyn Any + Send + Sync>,
+    access: fn(&mut FilteredResourcesBuilder),
+    read: fn(&FilteredResources, &mut dyn Any) -> Vec<Box<dyn PartialReflect>>,
+}
+
+impl ReflectScriptEvent {
+    pub fn new_cursor(&self) -> Box<dyn Any + Send + Sync> {
+        (self.new_cursor)()
+    }
+
+    /// Declares read access to the events, which [`read`](Self::read) needs.
+    pub fn access(&self, builder: &mut FilteredResourcesBuilder) {
+        (self.access)(builder)
+    }
+
+    /// Clones every event the cursor hasn't seen yet.
+    pub fn read(&self, resources: &FilteredResources, cursor: &mut dyn Any) -> Vec<Box<dyn PartialReflect>> {
+        (self.read)(resources, cursor)
+    }
+}
+
+impl<T: Event + Reflect> FromType<T> for ReflectScriptEvent {
+    fn from_type() -> Self {
+        ReflectScriptEvent {
+            new_cursor: || Box::new(EventCursor::<T>::default()),
+            access: |builder| {
+                builder.add_read::<Events<T>>();
+            },
+            read: |resources, cursor| {
+                let (Ok(events), Some(cursor)) = (resources.get::<Events<T>>(), cursor.downcast_mut::<EventCursor<T>>()) else {
+                    return vec![];
+                };
+                cursor.read(&events).filter_map(|event| event.reflect_clone().ok()).map(|event| event.into_partial_reflect()).collect()
+            },
+        }
+    }
+}
+
+pub trait ScriptEventAppExt {
+    fn register_script_event<T: Event + Reflect + TypePath + bevy::reflect::GetTypeRegistration>(&mut self) -> &mut Self;
+}
+
+impl ScriptEventAppExt for App {
+    fn register_script_event<T: Event + Reflect + TypePath + bevy::reflect::GetTypeRegistration>(&mut self) -> &mut Self {
+        self.add_event::<T>().register_type::<T>().register_type_data::<T, ReflectScriptEvent>()
+    }
+}
+
+/// The values of an installed graph's [`GraphVariable`]s, shared by all of its programs.
+#[derive(Clone, Default)]
+struct Variables {
+    declared: Arc<Vec<GraphVariable>>,
+    defaults: Arc<Vec<ReflectDefault>>,
+    values: Arc<Mutex<Vec<Box<dyn PartialReflect>>>>,
+}
+
+impl Variables {
+    /// Starts the `declared` variables from their defaults, except the ones `previous` has with the same name and type, which keep their values.
+    /// Fails with the first variable whose type has no registered default.
+    fn new(declared: &[GraphVariable], previous: Option<&Variables>, type_registry: &TypeRegistry) -> Result<Self, String> {
+        let mut previous = previous.map(|previous| (previous.declared.clone(), std::mem::take(&mut *previous.values.lock().unwrap_or_else(PoisonError::into_inner))));
+        let mut defaults = vec![];
+        let mut values = vec![];
+        for variable in declared {
+            let default = type_registry.get_with_type_path(&variable.type_path).and_then(|registration| registration.data::<ReflectDefault>()).cloned();
+            let Some(default) = default else {
+                return Err(format!("variable `{}` is a `{}`, which isn't a registered type with a default", variable.name, variable.type_path));
+            };
+            let kept = previous.as_mut().and_then(|(declared, values)| {
+                let index = declared.iter().position(|other| other == variable)?;
+                // a program that failed can hand back fewer values than it was given
+                (values.len() == declared.len()).then(|| std::mem::replace(&mut values[index], default.default().into_partial_reflect()))
+            });
+            values.push(kept.unwrap_or_else(|| default.default().into_partial_reflect()));
+            defaults.push(default);
+        }
+        Ok(Variables {
+            declared: Arc::new(declared.to_vec()),
+            defaults: Arc::new(defaults),
+            values: Arc::new(Mutex::new(values)),
+        })
+    }
+
+    fn defaults(&self) -> Vec<Box<dyn PartialReflect>> {
+        self.defaults.iter().map(|default| default.default().into_partial_reflect()).collect()
+    }
+
+    /// Calls `run` with the values and stores what it hands back. Programs of the graph take turns, unless `wait` is false
+    /// and one is already running further up the stack: it holds the values, so `run` isn't called and this is `None`.
+    fn with<R>(&self, wait: bool, run: impl FnOnce(Vec<Value>) -> (R, Vec<Value>)) -> Option<R> {
+        let values = match self.values.try_lock() {
+            Ok(values) => Some(values),
+            Err(TryLockError::Poisoned(poisoned)) => Some(poisoned.into_inner()),
+            Err(TryLockError::WouldBlock) if wait => Some(self.values.lock().unwrap_or_else(PoisonError::into_inner)),
+            Err(TryLockError::WouldBlock) => None,
+        };
+        let mut values = values?;
+        let (result, kept) = run(std::mem::take(&mut *values).into_iter().map(Value::Box).collect());
+        let kept: Vec<Box<dyn PartialReflect>> = kept
+            .into_iter()
+            .filter_map(|value| match value {
+                Value::Box(value) => Some(value),
+                _ => None,
+            })
+            .collect();
+        *values = if kept.len() == self.defaults.len() {
+            kept
+        } else {
+            error!("a script ended without its variables, they start over from their defaults");
+            self.defaults()
+        };
+        Some(result)
+    }
+}
+
+/// One entry point's program, run for the entity the graph is attached to, if any.
+#[derive(Clone)]
+struct Program {
+    bytecode: Arc<Vec<Bytecode>>,
+    /// Shared by every source running the same compiled entry point.
+    cache: Arc<Mutex<QueryCache>>,
+    this: Option<Entity>,
+    variables: Variables,
+}
+
+impl Program {
+    /// The owning entity, the graph's variables and then `payload`, see [`compiler::SELF_SLOT`]. Also where the variables are.
+    fn inputs(&self, variables: Vec<Value>, payload: Option<Box<dyn PartialReflect>>) -> (Vec<Value>, Range<usize>) {
+        let start = self.this.is_some() as usize;
+        let keep = start..start + variables.len();
+        (self.this.map(|this| Value::Box(Box::new(this))).into_iter().chain(variables).chain(payload.map(Value::Box)).collect(), keep)
+    }
+
+    fn run(&self, world: &mut World, payload: Option<Box<dyn PartialReflect>>) -> Result<(), VmError> {
+        let result = self.variables.with(false, |variables| {
+            let (inputs, keep) = self.inputs(variables, payload);
+            match self.cache.try_lock() {
+                Ok(mut cache) => Bytecode::run_keeping(world, &self.bytecode, inputs, keep, &mut cache),
+                // already running further up the stack
+                Err(_) => Bytecode::run_keeping(world, &self.bytecode, inputs, keep, &mut QueryCache::default()),
+            }
+        });
+        result.unwrap_or_else(|| Err(VmError { ip: 0, opcode: String::new(), stack: String::new(), kind: VmErrorKind::VariablesInUse }))
+    }
+
+    /// Runs from a system that declared the program's [`DeclaredAccess`] as `entities`, logging what went wrong.
+    fn run_declared(&self, entities: &Query<FilteredEntityMut<'static>>, type_registry: &AppTypeRegistry, ticks: &SystemChangeTick, payload: Option<Box<dyn PartialReflect>>, cache: &mut QueryCache) {
+        // systems and observers never run inside another program, so they can wait for the variables
+        let result = self.variables.with(true, |variables| {
+            let (inputs, keep) = self.inputs(variables, payload);
+            Bytecode::run_declared(entities, type_registry.clone(), ticks, &self.bytecode, inputs, keep, cache)
+        });
+        if let Some(Err(error)) = result {
+            error!("{error}");
+        }
+    }
+
+    /// Every component the program reaches and a cache that has them, `None` if one of them isn't registered.
+    fn declared(&self, world: &World) -> Option<(DeclaredAccess, QueryCache)> {
+        let mut cache = QueryCache::default();
+        cache.refresh(world);
+        Some((DeclaredAccess::of(&self.bytecode, cache.components())?, cache))
+    }
+
+    /// A system running the program every time its schedule runs, or once per event it hasn't seen when `events` is given.
+    /// It declares the components and events it reaches, so the scheduler can run it alongside other systems.
+    fn system(&self, world: &mut World, events: Option<&EventSource>) -> Option<ScheduleConfigs<ScheduleSystem>> {
+        let (declared, mut cache) = self.declared(world)?;
+        let entities = QueryParamBuilder::new(move |builder: &mut QueryBuilder<FilteredEntityMut<'static>>| declared.build(builder));
+        let program = self.clone();
+        let Some(EventSource { reflect_event, cursor }) = events.cloned() else {
+            let system = (entities, ParamBuilder::resource(), ParamBuilder::of::<SystemChangeTick>()).build_state(world).build_system(move |entities: Query<FilteredEntityMut<'static>>, type_registry: Res<AppTypeRegistry>, ticks: SystemChangeTick| {
+                program.run_declared(&entities, &type_registry, &ticks, None, &mut cache);
+            });
+            return Some(system.into_configs());
+        };
+        let access = reflect_event.clone();
+        let resources = FilteredResourcesParamBuilder::new(move |builder: &mut FilteredResourcesBuilder| access.access(builder));
+        let system = (entities, resources, ParamBuilder::resource(), ParamBuilder::of::<SystemChangeTick>()).build_state(world).build_system(move |entities: Query<FilteredEntityMut<'static>>, resources: FilteredResources, type_registry: Res<AppTypeRegistry>, ticks: SystemChangeTick| {
+            let events = reflect_event.read(&resources, cursor.lock().unwrap().as_mut());
+            for event in events {
+                program.run_declared(&entities, &type_registry, &ticks, Some(event), &mut cache);
+            }
+        });
+        Some(system.into_configs())
+    }
+
+    /// An observer running the program with the target of every `E` trigger, declaring what it reaches like [`Program::system`].
+    fn observer<E: Event>(&self, world: &mut World) -> Option<Observer> {
+        let (declared, mut cache) = self.declared(world)?;
+        let entities = QueryParamBuilder::new(move |builder: &mut QueryBuilder<FilteredEntityMut<'static>>| declared.build(builder));
+        let program = self.clone();
+        // Bound outside the builder call so the trigger keeps its own lifetime instead of the one `build_system_with_input` would infer.
+        let run = move |trigger: Trigger<E>, entities: Query<FilteredEntityMut<'static>>, type_registry: Res<AppTypeRegistry>, ticks: SystemChangeTick| {
+            program.run_declared(&entities, &type_registry, &ticks, Some(Box::new(trigger.target())), &mut cache);
+        };
+        let system = (entities, ParamBuilder::resource(), ParamBuilder::of::<SystemChangeTick>()).build_state(world).build_system_with_input(run);
+        Some(Observer::new(system))
+    }
+}
+
+/// Where an event entry point reads its events from.
+#[derive(Clone)]
+struct EventSource {
+    reflect_event: ReflectScriptEvent,
+    cursor: Arc<Mutex<Box<dyn Any + Send + Sync>>>,
+}
+
+/// A program that runs as a system in the update or fixed update schedule, as long as its graph is `installed`.
+#[derive(Clone)]
+struct ScheduledProgram {
+    program: Program,
+    events: Option<EventSource>,
+    installed: Arc<AtomicBool>,
+}
+
+/// Where an installed graph came from. Installing a graph replaces the one from the same source.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum GraphSource {
+    Editor,
+    /// An entity with an [`EntityScript`](crate::script_graph::EntityScript), running its own instance of the graph.
+    Entity(Entity),
+}
+
+impl GraphSource {
+    fn this(&self) -> Option<Entity> {
+        match self {
+            GraphSource::Editor => None,
+            GraphSource::Entity(entity) => Some(*entity),
+        }
+    }
+}
+
+#[derive(Clone)]
+struct CompiledProgram {
+    node: NodeId,
+    entry: Entry,
+    bytecode: Arc<Vec<Bytecode>>,
+    cache: Arc<Mutex<QueryCache>>,
+    uses_self: bool,
+}
+
+/// A graph compiled and verified once, that can be installed for any number of sources.
+#[derive(Clone)]
+pub struct CompiledGraph {
+    entries: Vec<CompiledProgram>,
+    variables: Vec<GraphVariable>,
+}
+
+impl CompiledGraph {
+    /// The bytecode of every entry, the way [`disassemble`] writes it, each under a comment saying when it runs.
+    pub(crate) fn disassemble(&self, type_registry: &TypeRegistry) -> String {
+        self.entries.iter().map(|program| format!("; {}\n{}", program.entry, disassemble(&program.bytecode, type_registry))).collect::<Vec<_>>().join("\n")
+    }
+}
+
+/// The programs of one installed graph, waiting for their schedule or event.
+#[derive(Default)]
+struct InstalledGraph {
+    startup: Vec<Program>,
+    observers: Vec<Entity>,
+    /// Cleared when the graph is uninstalled, which stops its systems.
+    installed: Arc<AtomicBool>,
+    /// Kept for the next graph installed for the same source.
+    variables: Variables,
+}
+
+#[derive(Resource, Default)]
+pub struct EntryPoints {
+    graphs: HashMap<GraphSource, InstalledGraph>,
+    started: bool,
+    /// Systems of installed graphs waiting to be added to the update schedule, which can't change while it runs.
+    pending_update: Vec<ScheduledProgram>,
+    /// Like `pending_update`, for the fixed update schedule.
+    pending_fixed_update: Vec<ScheduledProgram>,
+}
+
+/// Compiles `snarl` and verifies every entry point.
+pub(crate) fn compile_graph(world: &mut World, snarl: &mut Snarl<Box<dyn Node>>) -> (Option<CompiledGraph>, Diagnostics) {
+    let mut viewer = Viewer::new(world.resource::<AppTypeRegistry>().clone(), world.resource::<ScriptFunctions>().clone(), snarl);
+    let Some(compiled) = compiler::compile(world, &mut viewer, snarl) else {
+        return (None, viewer.diagnostics);
+    };
+    let variables: Vec<_> = viewer.variables.iter().filter_map(|variable| variable.type_info(&world.resource::<AppTypeRegistry>().read())).collect();
+    let mut entries = vec![];
+    for compiled in compiled {
+        let payload = compiled.entry.payload(&world.resource::<AppTypeRegistry>().read());
+        let inputs: Vec<_> = compiled.uses_self.then(Entity::type_info).into_iter().chain(variables.iter().copied()).chain(payload).collect();
+        Bytecode::register_components(world, &compiled.bytecode);
+        if let Err(error) = Bytecode::verify(world, &compiled.bytecode, &inputs) {
+            // a conflict is between two queries, both of them get marked
+            if let VerifyErrorKind::QueryConflict { other } = error.kind {
+                viewer.diagnostics.error(Location::Node(compiled.node_at(other)), "another query runs while this one is still iterating and conflicts with it, add `without` filters to keep them apart");
+            }
+            viewer.diagnostics.error(Location::Node(compiled.node_at(error.ip)), error.to_string());
+        }
+        let CompiledEntry { node, entry, bytecode, uses_self, .. } = compiled;
+        entries.push(CompiledProgram { node, entry, bytecode: Arc::new(bytecode), cache: Arc::default(), uses_self });
+    }
+    if viewer.diagnostics.has_errors() {
+        return (None, viewer.diagnostics);
+    }
+    (Some(CompiledGraph { entries, variables: viewer.variables }), viewer.diagnostics)
+}
+
+/// Compiles `snarl` and installs it for `source`.
+pub(crate) fn load_graph(world: &mut World, source: GraphSource, snarl: &mut Snarl<Box<dyn Node>>) -> Diagnostics {
+    let (compiled, mut diagnostics) = compile_graph(world, snarl);
+    if let Some(compiled) = compiled {
+        for (node, error) in install(world, source, &compiled) {
+            diagnostics.error(Location::Node(node), error);
+        }
+    }
+    diagnostics
+}
+
+/// The programs of a compiled graph the way [`ProgramFile`]s save them.
+pub(crate) fn export_programs(world: &World, compiled: &CompiledGraph) -> Result<ProgramFile, ProgramError> {
+    let type_registry = world.resource::<AppTypeRegistry>().read();
+    let programs = compiled.entries.iter().map(|program| program::Program::from_bytecode(program.entry.clone(), program.uses_self, compiled.variables.clone(), &program.bytecode, &type_registry)).collect::<Result<_, _>>()?;
+    Ok(ProgramFile { programs })
+}
+
+/// Turns saved programs back into a graph ready to [`install`], once [`program::Program::load`] verified each of them.
+pub(crate) fn load_programs(world: &mut World, file: &ProgramFile) -> Result<CompiledGraph, ProgramError> {
+    let mut entries = vec![];
+    for (index, program) in file.programs.iter().enumerate() {
+        // there's no graph behind the programs, so the nodes only stand in
+        entries.push(CompiledProgram {
+            node: NodeId(index),
+            entry: program.entry.clone(),
+            bytecode: Arc::new(program.load(world)?),
+            cache: Arc::default(),
+            uses_self: program.uses_self,
+        });
+    }
+    // every program of a graph is saved with the graph's variables
+    let variables = file.programs.first().map(|program| program.variables.clone()).unwrap_or_default();
+    Ok(CompiledGraph { entries, variables })
+}
+
+/// Removes the programs installed for `source`.
+pub(crate) fn uninstall(world: &mut World, source: GraphSource) {
+    let mut entry_points = world.resource_mut::<EntryPoints>();
+    let Some(graph) = entry_points.graphs.remove(&source) else {
+        return;
+    };
+    // bevy can't take systems back out of a schedule, they stay and stop running
+    graph.installed.store(false, Ordering::Relaxed);
+    for observer in graph.observers {
+        if let Ok(observer) = world.get_entity_mut(observer) {
+            observer.despawn();
+        }
+    }
+}
+
+/// Replaces the programs installed for `source` with the entries of `compiled`, running the ones that start right away.
+/// Variables that kept their name and type keep the values they had in the replaced graph.
+/// Returns the entries that couldn't be installed.
+pub(crate) fn install(world: &mut World, source: GraphSource, compiled: &CompiledGraph) -> Vec<(NodeId, String)> {
+    let previous = world.resource::<EntryPoints>().graphs.get(&source).map(|graph| graph.variables.clone());
+    uninstall(world, source);
+    let this = source.this();
+    if this.is_none() && compiled.entries.iter().any(|compiled| compiled.uses_self) {
+        return compiled.entries.iter().filter(|compiled| compiled.uses_self).map(|compiled| (compiled.node, "Self only has a value in graphs attached to an entity".to_string())).collect();
+    }
+    let variables = match Variables::new(&compiled.variables, previous.as_ref(), &world.resource::<AppTypeRegistry>().read()) {
+        Ok(variables) => variables,
+        Err(error) => return compiled.entries.iter().map(|compiled| (compiled.node, error.clone())).collect(),
+    };
+    let started = world.resource::<EntryPoints>().started;
+    let mut graph = InstalledGraph {
+        variables: variables.clone(),
+        installed: Arc::new(AtomicBool::new(true)),
+        ..Default::default()
+    };
+    let mut update = vec![];
+    let mut fixed_update = vec![];
+    let mut errors = vec![];
+    let mut run_now = vec![];
+    for CompiledProgram { node, entry, bytecode, cache, uses_self } in compiled.entries.iter() {
+        let program = Program {
+            bytecode: bytecode.clone(),
+            cache: cache.clone(),
+            this: if *uses_self { this } else { None },
+            variables: variables.clone(),
+        };
+        match entry {
+            Entry::Run => run_now.push((*node, program)),
+            Entry::Startup if started => run_now.push((*node, program)),
+            Entry::Startup => graph.startup.push(program),
+            Entry::Update | Entry::FixedUpdate | Entry::Event(_) if program.declared(world).is_none() => errors.push((*node, "the graph uses a component that isn't registered".to_string())),
+            Entry::Update => update.push(ScheduledProgram { program, events: None, installed: graph.installed.clone() }),
+            Entry::FixedUpdate => fixed_update.push(ScheduledProgram { program, events: None, installed: graph.installed.clone() }),
+            Entry::Event(type_path) => {
+                let reflect_event = world.resource::<AppTypeRegistry>().read().get_with_type_path(type_path).and_then(|registration| registration.data::<ReflectScriptEvent>()).cloned();
+                let Some(reflect_event) = reflect_event else {
+                    errors.push((*node, format!("{type_path} isn't registered as a script event")));
+                    continue;
+                };
+                let cursor = Arc::new(Mutex::new(reflect_event.new_cursor()));
+                update.push(ScheduledProgram {
+                    program,
+                    events: Some(EventSource { reflect_event, cursor }),
+                    installed: graph.installed.clone(),
+                });
+            }
+            Entry::ComponentAdded(type_path) | Entry::ComponentRemoved(type_path) => {
+                let reflect_component = world.resource::<AppTypeRegistry>().read().get_with_type_path(type_path).and_then(|registration| registration.data::<ReflectComponent>()).cloned();
+                let Some(reflect_component) = reflect_component else {
+                    errors.push((*node, format!("{type_path} isn't a registered component")));
+                    continue;
+                };
+                let component = reflect_component.register_component(world);
+                let observer = if matches!(entry, Entry::ComponentAdded(_)) { program.observer::<OnAdd>(world) } else { program.observer::<OnRemove>(world) };
+                let Some(mut observer) = observer else {
+                    errors.push((*node, "the graph uses a component that isn't registered".to_string()));
+                    continue;
+                };
+                observer = observer.with_component(component);
+                // a graph attached to an entity only hears about its own components
+                if let Some(this) = this {
+                    observer.watch_entity(this);
+                }
+                graph.observers.push(world.spawn(observer).id());
+            }
+        }
+    }
+    let mut entry_points = world.resource_mut::<EntryPoints>();
+    entry_points.graphs.insert(source, graph);
+    entry_points.pending_update.extend(update);
+    entry_points.pending_fixed_update.extend(fixed_update);
+    for (node, program) in run_now {
+        if let Err(error) = program.run(world, None) {
+            errors.push((node, error.to_string()));
+        }
+    }
+    errors
+}
+
+fn run_programs(world: &mut World, programs: &[Program]) {
+    for program in programs {
+        if let Err(error) = program.run(world, None) {
+            error!("{error}");
+        }
+    }
+}
+
+fn startup_system(world: &mut World) {
+    let mut entry_points = world.resource_mut::<EntryPoints>();
+    entry_points.started = true;
+    let programs: Vec<Program> = entry_points.graphs.values_mut().flat_map(|graph| std::mem::take(&mut graph.startup)).collect();
+    run_programs(world, &programs);
+}
+
+/// Adds the systems of graphs installed since the last run to the schedules the plugin was given. It runs in `First`,
+/// so neither of them is running.
+fn add_pending_systems(update: InternedScheduleLabel, fixed_update: InternedScheduleLabel) -> impl FnMut(&mut World) {
+    move |world: &mut World| {
+        let mut entry_points = world.resource_mut::<EntryPoints>();
+        let pending = [(update, std::mem::take(&mut entry_points.pending_update)), (fixed_update, std::mem::take(&mut entry_points.pending_fixed_update))];
+        for (label, programs) in pending {
+            for ScheduledProgram { program, events, installed } in programs {
+                if !installed.load(Ordering::Relaxed) {
+                    continue;
+                }
+                // checked when installing, only a component going away in between gets here
+                let Some(system) = program.system(world, events.as_ref()) else {
+                    error!("a script uses a component that isn't registered");
+                    continue;
+                };
+                world.get_resource_or_init::<Schedules>().add_systems(label, system.run_if(move || installed.load(Ordering::Relaxed)));
+            }
+        }
+    }
+}
+
+/// Runs the entry points of compiled graphs on their schedules and events.
+pub struct EntryPointsPlugin {
+    pub startup_schedule: InternedScheduleLabel,
+    pub update_schedule: InternedScheduleLabel,
+    pub fixed_update_schedule: InternedScheduleLabel,
+}
+
+impl Plugin for EntryPointsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EntryPoints>().add_systems(self.startup_schedule, startup_system).add_systems(First, add_pending_systems(self.update_schedule, self.fixed_update_schedule));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::again::apply_node::ApplyNode;
+    use crate::again::breakdown_node::TupleBreakdownNode;
+    use crate::again::primitive_node::{PrimitiveNode, PrimitiveType};
+    use crate::again::self_node::SelfNode;
+    use crate::again::start_node::StartNode;
+    use crate::again::variable_node::VariableNode;
+    use bevy::ecs::schedule::ScheduleLabel;
+    use bevy::reflect::TypePath;
+    use bevy::reflect::func::args::Ownership;
+    use egui_snarl::{InPinId, OutPinId};
+
+    #[derive(Component, Reflect, Default, Debug, PartialEq)]
+    #[reflect(Component, Default)]
+    struct Speed(f32);
+
+    fn world() -> World {
+        let mut world = World::new();
+        let registry = AppTypeRegistry::default();
+        registry.write().register::<f32>();
+        registry.write().register::<i32>();
+        registry.write().register::<Speed>();
+        registry.write().register_type_data::<f32, ReflectDefault>();
+        registry.write().register_type_data::<i32, ReflectDefault>();
+        world.insert_resource(registry);
+        world.init_resource::<ScriptFunctions>();
+        world.init_resource::<EntryPoints>();
+        world.register_component::<Speed>();
+        world
+    }
+
+    fn variable(name: &str, type_path: &str, ownership: Ownership) -> Box<VariableNode> {
+        let mut variable = VariableNode::default();
+        variable.name = name.to_string();
+        variable.type_path = type_path.to_string();
+        variable.ownership = ownership;
+        Box::new(variable)
+    }
+
+    /// A graph that runs once and sets the `f32` variable `speed` to 3.
+    fn set_speed() -> Snarl<Box<dyn Node>> {
+        let mut snarl: Snarl<Box<dyn Node>> = Snarl::new();
+        let start = snarl.insert_node(egui::Pos2::ZERO, Box::<StartNode>::default());
+        let value = snarl.insert_node(egui::Pos2::ZERO, Box::new(PrimitiveNode { primitive_type: PrimitiveType::F32(3.0), node_id: None }));
+        let apply = snarl.insert_node(egui::Pos2::ZERO, Box::<ApplyNode>::default());
+        let speed = snarl.insert_node(egui::Pos2::ZERO, variable("speed", f32::type_path(), Ownership::Mut));
+        snarl.connect(OutPinId { node: start, output: 0 }, InPinId { node: value, input: 0 });
+        snarl.connect(OutPinId { node: value, output: 0 }, InPinId { node: apply, input: 0 });
+        snarl.connect(OutPinId { node: speed, output: 0 }, InPinId { node: apply, input: 1 });
+        snarl.connect(OutPinId { node: value, output: 1 }, InPinId { node: apply, input: 2 });
+        snarl
+    }
+
+    /// A graph that runs once and copies the `f32` variable `speed` into the entity's `Speed`.
+    fn copy_speed() -> Snarl<Box<dyn Node>> {
+        copy_speed_on(Entry::Run)
+    }
+
+    /// Like [`copy_speed`], starting from `entry`.
+    fn copy_speed_on(entry: Entry) -> Snarl<Box<dyn Node>> {
+        let mut snarl: Snarl<Box<dyn Node>> = Snarl::new();
+        let mut start = StartNode::default();
+        start.entry = entry;
+        let start = snarl.insert_node(egui::Pos2::ZERO, Box::new(start));
+        let mut self_node = SelfNode::default();
+        self_node.components.push(Speed::type_path().to_string());
+        let self_node = snarl.insert_node(egui::Pos2::ZERO, Box::new(self_node));
+        let mut breakdown = TupleBreakdownNode::default();
+        breakdown.ownership = Ownership::Mut;
+        let breakdown = snarl.insert_node(egui::Pos2::ZERO, Box::new(breakdown));
+        let apply = snarl.insert_node(egui::Pos2::ZERO, Box::<ApplyNode>::default());
+        let speed = snarl.insert_node(egui::Pos2::ZERO, variable("speed", f32::type_path(), Ownership::Owned));
+        snarl.connect(OutPinId { node: start, output: 0 }, InPinId { node: breakdown, input: 0 });
+        snarl.connect(OutPinId { node: breakdown, output: 0 }, InPinId { node: apply, input: 0 });
+        snarl.connect(OutPinId { node: self_node, output: 1 }, InPinId { node: breakdown, input: 1 });
+        snarl.connect(OutPinId { node: breakdown, output: 1 }, InPinId { node: apply, input: 1 });
+        snarl.connect(OutPinId { node: speed, output: 0 }, InPinId { node: apply, input: 2 });
+        snarl
+    }
+
+    fn install_snarl(world: &mut World, source: GraphSource, mut snarl: Snarl<Box<dyn Node>>) {
+        let (compiled, diagnostics) = compile_graph(world, &mut snarl);
+        let compiled = compiled.unwrap_or_else(|| panic!("{:?}", diagnostics.0));
+        assert_eq!(install(world, source, &compiled), vec![]);
+    }
+
+    #[test]
+    fn variables_keep_their_values_when_the_graph_is_installed_again() {
+        let mut world = world();
+        let owner = world.spawn(Speed(1.0)).id();
+        let source = GraphSource::Entity(owner);
+        install_snarl(&mut world, source, set_speed());
+        install_snarl(&mut world, source, copy_speed());
+        assert_eq!(world.get::<Speed>(owner), Some(&Speed(3.0)));
+    }
+
+    #[test]
+    fn variables_that_changed_type_start_from_their_default() {
+        let mut world = world();
+        let owner = world.spawn(Speed(1.0)).id();
+        let source = GraphSource::Entity(owner);
+        install_snarl(&mut world, source, set_speed());
+        let mut retyped: Snarl<Box<dyn Node>> = Snarl::new();
+        retyped.insert_node(egui::Pos2::ZERO, Box::<StartNode>::default());
+        retyped.insert_node(egui::Pos2::ZERO, variable("speed", i32::type_path(), Ownership::Owned));
+        install_snarl(&mut world, source, retyped);
+        install_snarl(&mut world, source, copy_speed());
+        assert_eq!(world.get::<Speed>(owner), Some(&Speed(0.0)));
+    }
+
+    #[test]
+    fn disassembled_graphs_assemble_again() {
+        let mut world = world();
+        let (compiled, diagnostics) = compile_graph(&mut world, &mut set_speed());
+        let text = compiled.unwrap_or_else(|| panic!("{:?}", diagnostics.0)).disassemble(&world.resource::<AppTypeRegistry>().read());
+        assert!(text.starts_with("; Start\n"));
+        assert!(crate::assembly::assemble(&text, &world.resource::<ScriptFunctions>().read(), &world.resource::<AppTypeRegistry>().read()).is_ok());
+    }
+
+    #[test]
+    fn nested_runs_fail_instead_of_dropping_what_they_write() {
+        let world = world();
+        let declared = [GraphVariable { name: "speed".to_string(), type_path: f32::type_path().to_string() }];
+        let variables = Variables::new(&declared, None, &world.resource::<AppTypeRegistry>().read()).unwrap();
+        let nested = variables.with(false, |values| (variables.with(false, |values| ((), values)), values));
+        assert!(matches!(nested, Some(None)));
+    }
+
+    #[test]
+    fn update_entries_run_in_the_update_schedule_until_uninstalled() {
+        let mut world = world();
+        let owner = world.spawn(Speed(1.0)).id();
+        let source = GraphSource::Entity(owner);
+        install_snarl(&mut world, source, set_speed());
+        install_snarl(&mut world, source, copy_speed_on(Entry::Update));
+        add_pending_systems(Update.intern(), FixedUpdate.intern())(&mut world);
+        world.run_schedule(Update);
+        assert_eq!(world.get::<Speed>(owner), Some(&Speed(3.0)));
+        uninstall(&mut world, source);
+        world.get_mut::<Speed>(owner).unwrap().0 = 1.0;
+        world.run_schedule(Update);
+        assert_eq!(world.get::<Speed>(owner), Some(&Speed(1.0)));
+    }
+}
@@ -0,0 +1,36 @@
+use bevy::reflect::TypeInfo;
+use bevy::reflect::func::DynamicFunction;
+
+// `#[script_fn]` submits one `ScriptFn` per annotated function through `inventory`, so functions can be
+// exposed from any module without a central list. `FunctionRegistry::register_script_fns` collects them.
+
+pub use bevy_node_scripting_macros::script_fn;
+// the macro reaches `inventory` through here, so users don't need to depend on it themselves
+#[doc(hidden)]
+pub use inventory;
+
+/// What `#[script_fn]` knows about a function beyond its signature.
+#[derive(Clone, Debug)]
+pub struct FunctionMeta {
+    /// Shown on the node and in the palette.
+    pub name: &'static str,
+    /// Where the palette files the function, `/` separated. Empty for the top level.
+    pub category: &'static str,
+    /// One per argument, in order.
+    pub args: &'static [&'static str],
+    /// What the return value is called, empty to leave it unnamed.
+    pub returns: &'static str,
+    pub doc: &'static str,
+    /// Whether calling the function has no side effects.
+    pub pure: bool,
+}
+
+/// A function exposed with `#[script_fn]`.
+pub struct ScriptFn {
+    pub function: fn() -> DynamicFunction<'static>,
+    /// The type the function is listed under, if it's associated with one.
+    pub owner: Option<fn() -> &'static TypeInfo>,
+    pub meta: FunctionMeta,
+}
+
+inventory::collect!(ScriptFn);
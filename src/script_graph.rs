@@ -0,0 +1,195 @@
+use crate::diagnostics::Severity;
+use crate::graph_file::{GraphFile, GraphFileError};
+use crate::program::{ProgramError, ProgramFile};
+use crate::runtime::{self, CompiledGraph, GraphSource};
+use crate::ui::ScriptFunctions;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, LoadContext, UntypedAssetId};
+use bevy::ecs::event::{EventCursor, Events};
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+/// A graph file loaded through the `AssetServer`. Attach it to entities with [`EntityScript`];
+/// whenever the file changes it's compiled again and swapped into those entities on the next frame, unless it no longer compiles.
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct ScriptGraph(pub GraphFile);
+
+#[derive(Default)]
+pub struct ScriptGraphLoader;
+
+impl AssetLoader for ScriptGraphLoader {
+    type Asset = ScriptGraph;
+    type Settings = ();
+    type Error = GraphFileError;
+
+    async fn load(&self, reader: &mut dyn Reader, _settings: &(), _load_context: &mut LoadContext<'_>) -> Result<ScriptGraph, GraphFileError> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes).await.map_err(|error| GraphFileError::Io(error.to_string()))?;
+        let source = std::str::from_utf8(&bytes).map_err(|error| GraphFileError::Format(error.to_string()))?;
+        Ok(ScriptGraph(GraphFile::from_ron(source)?))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["graph.ron"]
+    }
+}
+
+/// Programs exported from the editor, loaded through the `AssetServer`. Attach them to entities with [`EntityProgram`];
+/// they run like the graph they were exported from, without it or the editor.
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct ScriptProgram(pub ProgramFile);
+
+#[derive(Default)]
+pub struct ScriptProgramLoader;
+
+impl AssetLoader for ScriptProgramLoader {
+    type Asset = ScriptProgram;
+    type Settings = ();
+    type Error = ProgramError;
+
+    async fn load(&self, reader: &mut dyn Reader, _settings: &(), _load_context: &mut LoadContext<'_>) -> Result<ScriptProgram, ProgramError> {
+        let mut bytes = vec![];
+        reader.read_to_end(&mut bytes).await.map_err(|error| ProgramError::Io(error.to_string()))?;
+        let source = std::str::from_utf8(&bytes).map_err(|error| ProgramError::Format(error.to_string()))?;
+        Ok(ScriptProgram(ProgramFile::from_ron(source)?))
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["program.ron"]
+    }
+}
+
+/// Runs its own instance of a [`ScriptGraph`] for this entity, which is what the graph's Self node refers to.
+#[derive(Component, Clone, Debug)]
+pub struct EntityScript(pub Handle<ScriptGraph>);
+
+/// Runs its own instance of a [`ScriptProgram`] for this entity, like [`EntityScript`] does for graphs.
+#[derive(Component, Clone, Debug)]
+pub struct EntityProgram(pub Handle<ScriptProgram>);
+
+/// Every loaded [`ScriptGraph`] and [`ScriptProgram`] compiled, ready to be installed for the entities using it.
+#[derive(Resource, Default)]
+pub struct CompiledScripts(pub HashMap<UntypedAssetId, CompiledGraph>);
+
+/// An asset entities run through the component attaching it.
+trait Script: Asset + Sized {
+    type Attached: Component;
+    fn id(attached: &Self::Attached) -> AssetId<Self>;
+    /// Turns the asset into a graph ready to install, or logs why it couldn't.
+    fn compile(world: &mut World, id: AssetId<Self>) -> Option<CompiledGraph>;
+}
+
+impl Script for ScriptGraph {
+    type Attached = EntityScript;
+
+    fn id(attached: &EntityScript) -> AssetId<Self> {
+        attached.0.id()
+    }
+
+    fn compile(world: &mut World, id: AssetId<Self>) -> Option<CompiledGraph> {
+        let file = world.resource::<Assets<ScriptGraph>>().get(id)?.0.clone();
+        let mut snarl = match file.upgrade(&world.resource::<ScriptFunctions>().read()).load() {
+            Ok(snarl) => snarl,
+            Err(error) => {
+                error!("{error}");
+                return None;
+            }
+        };
+        let (compiled, diagnostics) = runtime::compile_graph(world, &mut snarl);
+        for diagnostic in diagnostics.0 {
+            match diagnostic.severity {
+                Severity::Warning => warn!("{diagnostic}"),
+                Severity::Error => error!("{diagnostic}"),
+            }
+        }
+        compiled
+    }
+}
+
+impl Script for ScriptProgram {
+    type Attached = EntityProgram;
+
+    fn id(attached: &EntityProgram) -> AssetId<Self> {
+        attached.0.id()
+    }
+
+    fn compile(world: &mut World, id: AssetId<Self>) -> Option<CompiledGraph> {
+        let file = world.resource::<Assets<ScriptProgram>>().get(id)?.0.clone();
+        match runtime::load_programs(world, &file) {
+            Ok(compiled) => Some(compiled),
+            Err(error) => {
+                error!("{error}");
+                None
+            }
+        }
+    }
+}
+
+fn install_script(world: &mut World, entity: Entity, id: UntypedAssetId) {
+    let Some(compiled) = world.resource::<CompiledScripts>().0.get(&id).cloned() else {
+        // installed once the graph has loaded
+        return;
+    };
+    for (node, error) in runtime::install(world, GraphSource::Entity(entity), &compiled) {
+        error!("script of {entity}, node {}: {error}", node.0);
+    }
+}
+
+/// The entities running the script `id`.
+fn users<S: Script>(world: &mut World, id: AssetId<S>) -> Vec<Entity> {
+    world.query::<(Entity, &S::Attached)>().iter(world).filter(|(_, attached)| S::id(attached) == id).map(|(entity, _)| entity).collect()
+}
+
+fn reload_scripts<S: Script>(world: &mut World, mut cursor: Local<EventCursor<AssetEvent<S>>>) {
+    let events: Vec<AssetEvent<S>> = cursor.read(world.resource::<Events<AssetEvent<S>>>()).copied().collect();
+    for event in events {
+        match event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => {
+                let Some(compiled) = S::compile(world, id) else {
+                    // the diagnostics are logged, and entities keep running the last version that compiled
+                    if world.resource::<CompiledScripts>().0.contains_key(&id.untyped()) {
+                        warn!("{id} didn't compile, its entities keep running the previous version");
+                    }
+                    continue;
+                };
+                world.resource_mut::<CompiledScripts>().0.insert(id.untyped(), compiled);
+                // swap the new program into every entity running this script, which keeps the variables that didn't change
+                for entity in users(world, id) {
+                    install_script(world, entity, id.untyped());
+                }
+            }
+            AssetEvent::Removed { id } => {
+                world.resource_mut::<CompiledScripts>().0.remove(&id.untyped());
+                for entity in users(world, id) {
+                    runtime::uninstall(world, GraphSource::Entity(entity));
+                }
+            }
+            AssetEvent::Added { .. } | AssetEvent::Unused { .. } => {}
+        }
+    }
+}
+
+fn attach_scripts<S: Script>(mut commands: Commands, scripts: Query<(Entity, &S::Attached), Changed<S::Attached>>, mut removed: RemovedComponents<S::Attached>) {
+    for entity in removed.read() {
+        commands.queue(move |world: &mut World| runtime::uninstall(world, GraphSource::Entity(entity)));
+    }
+    for (entity, attached) in &scripts {
+        let id = S::id(attached).untyped();
+        commands.queue(move |world: &mut World| install_script(world, entity, id));
+    }
+}
+
+/// Loads `.graph.ron` files as [`ScriptGraph`]s and `.program.ron` files as [`ScriptProgram`]s.
+/// Needs the `AssetPlugin`, and the `hot_reload` feature to notice files changing.
+pub struct ScriptGraphPlugin;
+
+impl Plugin for ScriptGraphPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<ScriptGraph>()
+            .init_asset_loader::<ScriptGraphLoader>()
+            .init_asset::<ScriptProgram>()
+            .init_asset_loader::<ScriptProgramLoader>()
+            .init_resource::<CompiledScripts>()
+            .add_systems(PreUpdate, (reload_scripts::<ScriptGraph>, reload_scripts::<ScriptProgram>, attach_scripts::<ScriptGraph>, attach_scripts::<ScriptProgram>).chain());
+    }
+}
@@ -1,115 +1,67 @@
-/*use crate::nodes::breakdown_node::BreakdownNode;
-use crate::nodes::buildup_node::BuildupNode;
-use crate::nodes::for_node::ForNode;
-use crate::nodes::function_node::FunctionNode;
-use crate::nodes::if_else_node::IfElseNode;
-use crate::nodes::primitive_node::PrimitiveNode;
-use crate::nodes::query_node::QueryNode;
-use crate::nodes::tuple_breakdown_node::TupleBreakdownNode;*/
 use crate::again::{Node, Viewer};
-use crate::nodes::GraphNode;
-use crate::{Bytecode, compiler};
-use bevy::DefaultPlugins;
-use bevy::math::Vec3;
-use bevy::prelude::{AppTypeRegistry, Camera2d, Commands, IntoFunction, Local, Mut, ReflectDefault, Res, ResMut, Resource, Startup, Struct, Transform, Update, World};
-use bevy::reflect::func::args::Ownership;
-use bevy::reflect::func::{DynamicFunction, ReturnInfo};
-use bevy::reflect::{DynamicTypePath, PartialReflect, Reflect, StructInfo, Type};
-use bevy_egui::{EguiContexts, EguiPlugin};
-use egui::{Color32, Id, Ui};
-use egui_snarl::ui::{NodeLayout, PinInfo, PinPlacement, SnarlPin, SnarlStyle, SnarlViewer, SnarlWidget, WireStyle};
-use egui_snarl::{InPin, InPinId, NodeId, OutPin, Snarl};
+use crate::diagnostics::{Diagnostics, Location};
+use crate::graph_file::GraphFile;
+use crate::history::{Edits, History};
+use crate::runtime;
+use crate::runtime::GraphSource;
+use crate::script_fn::{FunctionMeta, ScriptFn, script_fn};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::{AppFunctionRegistry, AppTypeRegistry, Camera2d, Commands, IntoFunction, Local, Mut, Res, ResMut, Resource, Result, World};
+use bevy::reflect::func::DynamicFunction;
+use bevy::reflect::{TypeInfo, TypeRegistry, Typed};
+use bevy_egui::EguiContexts;
+use egui::{Event, Id, Key, Modifiers, Pos2, Vec2};
+use egui_snarl::ui::{NodeLayout, PinPlacement, SnarlStyle, SnarlWidget, get_selected_nodes};
+use egui_snarl::{NodeId, Snarl};
 use std::any::{Any, TypeId};
-use std::collections::{BTreeMap, BTreeSet, HashMap};
-use std::hash::{DefaultHasher, Hash, Hasher};
-use std::ops::{Add, AddAssign};
-
-pub fn uwu() {
-    bevy::prelude::App::new()
-        .add_plugins(DefaultPlugins)
-        .insert_resource(SnarlResource::default())
-        .register_type::<Transform>()
-        .register_type::<Vec3>()
-        .register_type_data::<Vec3, ReflectDefault>()
-        .register_type_data::<f32, ReflectDefault>()
-        .register_type_data::<i32, ReflectDefault>()
-        .register_type_data::<String, ReflectDefault>()
-        .add_plugins(EguiPlugin)
-        .add_systems(Update, ui_system)
-        .add_systems(Startup, setup)
-        .run();
-}
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
+/// The functions graphs can call. Functions belonging to a type are kept apart, grouped under that type.
+#[derive(Default)]
 pub struct FunctionRegistry {
     pub associated_functions: HashMap<TypeId, BTreeMap<String, DynamicFunction<'static>>>,
-    pub associated_types: Vec<Box<dyn Reflect>>,
+    /// Every type with associated functions, in the order they were first registered.
+    pub associated_types: Vec<&'static TypeInfo>,
     pub freestanding_functions: BTreeMap<String, DynamicFunction<'static>>,
+    /// What `#[script_fn]` said about a function, by its full name.
+    pub metadata: HashMap<String, FunctionMeta>,
 }
 
+/// Prints a greeting.
+#[script_fn(name = "Hello World", category = "Debug")]
 pub fn hello_world() {
     println!("hello world!");
 }
 
-impl Default for FunctionRegistry {
-    fn default() -> Self {
-        let mut this = Self {
-            associated_functions: Default::default(),
-            associated_types: Default::default(),
-            freestanding_functions: Default::default(),
-        };
-        fn print(string: &str) {
-            println!("{}", string);
-        }
-        fn print_2(string: String) {
-            println!("{}", string);
-        }
-        fn print_3(string: &String) {
-            println!("{}", string);
-        }
-        let print = print_2.into_function().with_name("print").with_overload(print).with_overload(print_3);
-        this.register_freestanding(hello_world);
-        this.freestanding_functions.insert("print".to_string(), print);
-        this.register_associated(Vec3::default(), Vec3::default);
-        this.register_associated(Vec3::default(), Vec3::to_string);
-        this.register_associated(Vec3::default(), <Vec3 as AddAssign<Vec3>>::add_assign);
-        this
-    }
+/// A function as the editor lists it.
+pub struct Listing<'a> {
+    /// The display name, `Type::function` for associated functions.
+    pub label: String,
+    /// `/` separated, associated functions without one are filed under their type.
+    pub category: String,
+    pub function: &'a DynamicFunction<'static>,
+    pub meta: Option<&'a FunctionMeta>,
 }
 
-pub trait TraitExtTuple {
-    fn get_string_rep(&self) -> String;
-}
-impl TraitExtTuple for (Box<dyn PartialReflect>, Ownership) {
-    fn get_string_rep(&self) -> String {
-        format!(
-            "{}{}",
-            match self.1 {
-                Ownership::Owned => "",
-                Ownership::Ref => "&",
-                Ownership::Mut => "&mut ",
-            },
-            self.0.reflect_type_ident().unwrap()
-        )
+/// The type a function is a method of: the struct or enum its first argument takes, as long as every overload agrees.
+/// Primitives and other opaque types don't count, a function taking a `String` first isn't a `String` method.
+fn receiver_of(function: &DynamicFunction<'static>, type_registry: &TypeRegistry) -> Option<&'static TypeInfo> {
+    let mut receivers = function.info().signatures().iter().map(|signature| signature.args().first().map(|arg| arg.type_id()));
+    let receiver = receivers.next()??;
+    if !receivers.all(|other| other == Some(receiver)) {
+        return None;
     }
+    let type_info = type_registry.get(receiver)?.type_info();
+    (!matches!(type_info, TypeInfo::Opaque(_))).then_some(type_info)
 }
 
 impl FunctionRegistry {
-    pub fn register_associated<T: Reflect, Marker, F>(&mut self, r#type: T, function: F)
+    pub fn register_associated<T: Typed, Marker, F>(&mut self, function: F)
     where
         F: IntoFunction<'static, Marker> + 'static,
     {
-        let t = r#type.reflect_clone().unwrap();
-        if !self.associated_functions.contains_key(&r#type.reflect_type_info().type_id()) {
-            self.associated_functions.insert(r#type.reflect_type_info().type_id(), BTreeMap::new());
-        }
-        let f = function.into_function();
-        self.associated_functions.get_mut(&r#type.reflect_type_info().type_id()).unwrap().insert(f.name().unwrap().to_string().rsplit_once("::").unwrap().1.to_string(), f);
-        for t in &self.associated_types {
-            if t.reflect_type_ident().unwrap() == r#type.reflect_type_ident().unwrap() {
-                return;
-            }
-        }
-        self.associated_types.push(Box::new(r#type));
+        self.insert_associated(T::type_info(), function.into_function());
     }
     pub fn register_freestanding<Marker, F>(&mut self, function: F)
     where
@@ -118,40 +70,113 @@ impl FunctionRegistry {
         let f = function.into_function();
         self.freestanding_functions.insert(f.name().unwrap().to_string(), f);
     }
+    fn insert_associated(&mut self, type_info: &'static TypeInfo, function: DynamicFunction<'static>) {
+        let name = function.name().unwrap().to_string();
+        let key = name.rsplit("::").next().unwrap_or(&name).to_string();
+        self.associated_functions.entry(type_info.type_id()).or_default().insert(key, function);
+        if !self.associated_types.iter().any(|t| t.type_id() == type_info.type_id()) {
+            self.associated_types.push(type_info);
+        }
+    }
+    /// Registers every function annotated with `#[script_fn]`.
+    pub fn register_script_fns(&mut self) {
+        for script_fn in inventory::iter::<ScriptFn> {
+            let function = (script_fn.function)();
+            let Some(name) = function.name().map(|name| name.to_string()) else {
+                continue;
+            };
+            match script_fn.owner {
+                Some(owner) => self.insert_associated(owner(), function),
+                None => {
+                    self.freestanding_functions.insert(name.clone(), function);
+                }
+            }
+            self.metadata.insert(name, script_fn.meta.clone());
+        }
+    }
+    /// Adds every function in bevy's `AppFunctionRegistry` that isn't here yet. Methods, functions whose first argument
+    /// is a registered struct or enum, are grouped under that type.
+    pub fn import(&mut self, functions: &bevy::reflect::func::FunctionRegistry, type_registry: &TypeRegistry) {
+        for function in functions.iter() {
+            let Some(name) = function.name() else {
+                continue;
+            };
+            if self.get(name).is_some() {
+                continue;
+            }
+            match receiver_of(function, type_registry) {
+                Some(type_info) => self.insert_associated(type_info, function.clone()),
+                None => {
+                    self.freestanding_functions.insert(name.to_string(), function.clone());
+                }
+            }
+        }
+    }
+    /// Gives the function with this full name a display name, category and argument names, like `#[script_fn]` does.
+    pub fn describe(&mut self, name: impl Into<String>, meta: FunctionMeta) {
+        self.metadata.insert(name.into(), meta);
+    }
+    pub fn meta(&self, name: &str) -> Option<&FunctionMeta> {
+        self.metadata.get(name)
+    }
+    fn listing<'a>(&'a self, key: &str, function: &'a DynamicFunction<'static>, owner: Option<&'static TypeInfo>) -> Listing<'a> {
+        let meta = function.name().and_then(|name| self.metadata.get(name.as_ref()));
+        let name = meta.map(|meta| meta.name).unwrap_or_else(|| key.rsplit("::").next().unwrap_or(key));
+        let owner = owner.map(|owner| owner.type_path_table().short_path());
+        Listing {
+            label: owner.map(|owner| format!("{owner}::{name}")).unwrap_or_else(|| name.to_string()),
+            category: meta.map(|meta| meta.category).filter(|category| !category.is_empty()).or(owner).unwrap_or_default().to_string(),
+            function,
+            meta,
+        }
+    }
+    /// Every named function the way the editor lists it.
+    pub fn listed(&self) -> Vec<Listing<'_>> {
+        let mut listed: Vec<Listing<'_>> = self.freestanding_functions.iter().map(|(key, f)| self.listing(key, f, None)).collect();
+        for t in &self.associated_types {
+            let Some(functions) = self.associated_functions.get(&t.type_id()) else {
+                continue;
+            };
+            listed.extend(functions.iter().map(|(key, f)| self.listing(key, f, Some(*t))));
+        }
+        listed.retain(|listing| listing.function.name().is_some());
+        listed
+    }
+    /// Finds a function by the key it was registered under, or by its full name.
+    pub fn get(&self, name: &str) -> Option<&DynamicFunction<'static>> {
+        if let Some(f) = self.freestanding_functions.get(name) {
+            return Some(f);
+        }
+        self.freestanding_functions.values().chain(self.associated_functions.values().flat_map(BTreeMap::values)).find(|f| f.name().is_some_and(|n| n == name))
+    }
 }
 
-fn setup(mut commands: Commands) {
-    commands.spawn(Camera2d);
-}
+/// The [`FunctionRegistry`] graphs call into, shared with the editor the way `AppTypeRegistry` shares the type registry.
+#[derive(Resource, Clone, Default)]
+pub struct ScriptFunctions(pub Arc<RwLock<FunctionRegistry>>);
 
-pub const FLOW_COLOR: Color32 = Color32::from_rgb(0x00, 0xb0, 0x00);
-//const NUMBER_COLOR: Color32 = Color32::from_rgb(0xb0, 0x00, 0x00);
-const DATA_COLOR: Color32 = Color32::from_rgb(0xb0, 0x00, 0xb0);
-const UNTYPED_COLOR: Color32 = Color32::from_rgb(0xb0, 0xb0, 0xb0);
+impl ScriptFunctions {
+    pub fn read(&self) -> RwLockReadGuard<'_, FunctionRegistry> {
+        self.0.read().unwrap_or_else(PoisonError::into_inner)
+    }
 
-pub trait PinInfoTrait {
-    fn triangle_pin(&self) -> PinInfo;
-    fn circle_pin(&self, data_type: (&dyn PartialReflect, Ownership)) -> PinInfo;
-}
-impl PinInfoTrait for InPin {
-    fn triangle_pin(&self) -> PinInfo {
-        let mut p = PinInfo::triangle();
-        if self.remotes.is_empty() { p.with_fill(UNTYPED_COLOR) } else { p.with_fill(FLOW_COLOR) }
+    pub fn write(&self) -> RwLockWriteGuard<'_, FunctionRegistry> {
+        self.0.write().unwrap_or_else(PoisonError::into_inner)
     }
+}
 
-    fn circle_pin(&self, data_type: (&dyn PartialReflect, Ownership)) -> PinInfo {
-        let mut hasher = DefaultHasher::new();
-        let mut d = match data_type.1 {
-            Ownership::Ref => String::from("&"),
-            Ownership::Mut => String::from("&mut "),
-            Ownership::Owned => String::from(""),
-        };
-        d.push_str(data_type.0.reflect_type_path());
-        d.hash(&mut hasher);
-        let awa = hasher.finish();
-        let (r, g, b) = split_u64_to_u8s(awa);
-        PinInfo::circle().with_fill(Color32::from_rgb(r, g, b))
+/// Imports functions registered with bevy since the last import, whenever they're registered.
+pub(crate) fn import_functions(app_functions: Res<AppFunctionRegistry>, type_registry: Res<AppTypeRegistry>, functions: Res<ScriptFunctions>, mut imported: Local<usize>) {
+    let app_functions = app_functions.read();
+    if app_functions.len() == *imported {
+        return;
     }
+    *imported = app_functions.len();
+    functions.write().import(&app_functions, &type_registry.read());
+}
+
+pub(crate) fn setup(mut commands: Commands) {
+    commands.spawn(Camera2d);
 }
 
 pub fn split_u64_to_u8s(value: u64) -> (u8, u8, u8) {
@@ -188,82 +213,188 @@ pub fn split_u64_to_u8s(value: u64) -> (u8, u8, u8) {
     (first_u8, second_u8, third_u8)
 }
 
-impl PinInfoTrait for OutPin {
-    fn triangle_pin(&self) -> PinInfo {
-        let mut p = PinInfo::triangle();
-        if self.remotes.is_empty() { p.with_fill(UNTYPED_COLOR) } else { p.with_fill(FLOW_COLOR) }
-    }
-
-    fn circle_pin(&self, data_type: (&dyn PartialReflect, Ownership)) -> PinInfo {
-        let mut hasher = DefaultHasher::new();
-        let mut d = match data_type.1 {
-            Ownership::Ref => String::from("&"),
-            Ownership::Mut => String::from("&mut "),
-            Ownership::Owned => String::from(""),
-        };
-        d.push_str(data_type.0.reflect_type_path());
-        d.hash(&mut hasher);
-        let awa = hasher.finish();
-        let (r, g, b) = split_u64_to_u8s(awa);
-        PinInfo::circle().with_fill(Color32::from_rgb(r, g, b))
-    }
-}
-
-fn set_x(vec3: &mut Vec3, val: f32) {
-    vec3.x = val;
-}
-
-fn set_float(f: &mut f32, val: f32) {
-    *f = val;
-}
-
 #[derive(Resource, Default)]
 pub struct SnarlResource(pub Snarl<Box<dyn Node>>);
 
 unsafe impl Send for SnarlResource {}
 unsafe impl Sync for SnarlResource {}
 
-fn compile_thing(world: &mut World) {
-    world.resource_scope(|world, snarl: Mut<SnarlResource>| {
-        /*let bytecode = compiler::compile(world, &mut Default::default(), &snarl.0);
-        Bytecode::run(world, bytecode);*/
+/// What the last run of the graph found wrong with it.
+#[derive(Resource, Default)]
+pub struct GraphDiagnostics(pub Diagnostics);
+
+/// The bytecode of the graph in the editor as of the last time it was disassembled, `None` while it isn't shown.
+#[derive(Resource, Default)]
+pub struct GraphBytecode(pub Option<String>);
+
+/// Compiles the graph in [`SnarlResource`] and installs its entry points, reporting into [`GraphDiagnostics`].
+pub fn compile_graph(world: &mut World) {
+    world.resource_scope(|world, mut snarl: Mut<SnarlResource>| {
+        world.resource_mut::<GraphDiagnostics>().0 = runtime::load_graph(world, GraphSource::Editor, &mut snarl.0);
     });
 }
 
-fn ui_system(mut commands: Commands, mut contexts: EguiContexts, mut snarl: ResMut<SnarlResource>, app_type_registry: Res<AppTypeRegistry>) {
-    use std::ops::DerefMut;
+const SNARL_ID: &str = "snarl-demo";
+/// How far pasted and duplicated nodes are moved from where they were copied.
+const PASTE_OFFSET: f32 = 30.0;
 
-    let mut input_list = HashMap::new();
-    let mut output_list = HashMap::new();
+/// The selection last copied in the editor.
+#[derive(Resource, Default)]
+pub struct GraphClipboard(pub Option<GraphFile>);
 
-    let mut node_viewer = Viewer {
-        registry: app_type_registry.clone(),
-        inputs_list: Default::default(),
-        outputs_list: Default::default(),
-    };
+/// Where the editor saves and opens the graph.
+#[derive(Resource)]
+pub struct GraphPath(pub String);
 
-    for (id, node) in snarl.0.node_ids() {
-        input_list.insert(id, node.inputs_2(id, &mut node_viewer, &snarl.0));
-        output_list.insert(id, node.outputs_2(id, &mut node_viewer, &snarl.0));
+impl Default for GraphPath {
+    fn default() -> Self {
+        GraphPath("assets/main.graph.ron".to_string())
     }
-    for (id, node) in snarl.0.nodes_ids_mut() {
-        node.set_node_id(id);
+}
+
+fn save_graph(world: &mut World) {
+    let path = world.resource::<GraphPath>().0.clone();
+    let result = GraphFile::from_snarl(&world.resource::<SnarlResource>().0).and_then(|file| file.save_to(&path));
+    let mut diagnostics = Diagnostics::default();
+    if let Err(error) = result {
+        diagnostics.error(Location::Graph, format!("couldn't save {path}: {error}"));
     }
-    node_viewer.inputs_list = input_list;
-    node_viewer.outputs_list = output_list;
+    world.resource_mut::<GraphDiagnostics>().0 = diagnostics;
+}
 
-    egui::Window::new("Hello").show(contexts.ctx_mut(), |ui| {
+/// Compiles the graph and saves its programs next to it as a `.program.ron` file, which runs without the editor.
+fn export_program(world: &mut World) {
+    let path = world.resource::<GraphPath>().0.clone();
+    let path = format!("{}.program.ron", path.strip_suffix(".graph.ron").unwrap_or(&path));
+    let (compiled, mut diagnostics) = world.resource_scope(|world, mut snarl: Mut<SnarlResource>| runtime::compile_graph(world, &mut snarl.0));
+    if let Some(compiled) = compiled
+        && let Err(error) = runtime::export_programs(world, &compiled).and_then(|file| file.save_to(&path))
+    {
+        diagnostics.error(Location::Graph, format!("couldn't export {path}: {error}"));
+    }
+    world.resource_mut::<GraphDiagnostics>().0 = diagnostics;
+}
+
+/// Compiles the graph without installing it and shows its bytecode, or hides the bytecode if it's shown.
+fn toggle_bytecode(world: &mut World) {
+    if world.resource_mut::<GraphBytecode>().0.take().is_some() {
+        return;
+    }
+    let (compiled, diagnostics) = world.resource_scope(|world, mut snarl: Mut<SnarlResource>| runtime::compile_graph(world, &mut snarl.0));
+    let text = compiled.map(|compiled| compiled.disassemble(&world.resource::<AppTypeRegistry>().read()));
+    world.resource_mut::<GraphBytecode>().0 = Some(text.unwrap_or_else(|| "; the graph doesn't compile".to_string()));
+    world.resource_mut::<GraphDiagnostics>().0 = diagnostics;
+}
+
+fn open_graph(world: &mut World) {
+    let path = world.resource::<GraphPath>().0.clone();
+    let mut diagnostics = Diagnostics::default();
+    let functions = world.resource::<ScriptFunctions>().clone();
+    match GraphFile::open(&path).and_then(|file| file.upgrade(&functions.read()).load()) {
+        Ok(snarl) => {
+            world.resource_mut::<SnarlResource>().0 = snarl;
+            world.resource_mut::<History>().clear();
+        }
+        Err(error) => diagnostics.error(Location::Graph, format!("couldn't open {path}: {error}")),
+    }
+    world.resource_mut::<GraphDiagnostics>().0 = diagnostics;
+}
+
+/// The editor's own resources, as one system param.
+#[derive(SystemParam)]
+pub(crate) struct Editor<'w> {
+    snarl: ResMut<'w, SnarlResource>,
+    diagnostics: Res<'w, GraphDiagnostics>,
+    bytecode: Res<'w, GraphBytecode>,
+    graph_path: ResMut<'w, GraphPath>,
+    history: ResMut<'w, History>,
+    clipboard: ResMut<'w, GraphClipboard>,
+}
+
+pub(crate) fn ui_system(mut commands: Commands, mut contexts: EguiContexts, app_type_registry: Res<AppTypeRegistry>, functions: Res<ScriptFunctions>, editor: Editor) -> Result {
+    let Editor { mut snarl, diagnostics, bytecode, mut graph_path, mut history, mut clipboard } = editor;
+    let ctx = contexts.ctx_mut()?.clone();
+    let mut edits = Edits::default();
+    // a focused text field gets the shortcuts for itself
+    if !ctx.wants_keyboard_input() {
+        // redo first, Ctrl+Z alone also matches with shift held
+        let (redo, undo) = ctx.input_mut(|input| (input.consume_key(Modifiers::COMMAND | Modifiers::SHIFT, Key::Z), input.consume_key(Modifiers::COMMAND, Key::Z)));
+        if redo {
+            history.redo(&mut snarl.0);
+        } else if undo {
+            history.undo(&mut snarl.0);
+        }
+        let selected = get_selected_nodes(Id::new(SNARL_ID), &ctx);
+        let (copy, paste, duplicate) = ctx.input_mut(|input| {
+            let copy = input.events.iter().any(|event| matches!(event, Event::Copy));
+            let paste = input.events.iter().find_map(|event| if let Event::Paste(text) = event { Some(text.clone()) } else { None });
+            (copy, paste, input.consume_key(Modifiers::COMMAND, Key::D))
+        });
+        if copy
+            && !selected.is_empty()
+            && let Ok(file) = GraphFile::from_nodes(&snarl.0, &selected)
+        {
+            if let Ok(text) = file.to_ron() {
+                ctx.copy_text(text);
+            }
+            clipboard.0 = Some(file);
+        }
+        // anything that isn't a graph was meant for somewhere else
+        if let Some(file) = paste.and_then(|text| GraphFile::from_ron(&text).ok()).map(|file| file.upgrade(&functions.read()))
+            && edits.insert_file(&mut snarl.0, &file, Vec2::splat(PASTE_OFFSET)).is_ok()
+        {
+            clipboard.0 = Some(file);
+        }
+        if duplicate
+            && !selected.is_empty()
+            && let Ok(file) = GraphFile::from_nodes(&snarl.0, &selected)
+        {
+            let _ = edits.insert_file(&mut snarl.0, &file, Vec2::splat(PASTE_OFFSET));
+        }
+    }
+    let mut node_viewer = Viewer::new(app_type_registry.clone(), functions.clone(), &mut snarl.0);
+    node_viewer.diagnostics = diagnostics.0.clone();
+    node_viewer.clipboard = clipboard.0.clone();
+    node_viewer.edits = edits;
+    // egui-snarl drags nodes without telling the viewer
+    let positions: HashMap<NodeId, Pos2> = snarl.0.nodes_pos_ids().map(|(node, pos, _)| (node, pos)).collect();
+
+    egui::Window::new("Hello").show(&ctx, |ui| {
         ui.label("world");
-        if ui.button("run").clicked() {
-            commands.run_system_cached(compile_thing);
+        ui.horizontal(|ui| {
+            if ui.button("run").clicked() {
+                commands.run_system_cached(compile_graph);
+            }
+            ui.text_edit_singleline(&mut graph_path.0);
+            if ui.button("Save").clicked() {
+                commands.run_system_cached(save_graph);
+            }
+            if ui.button("Open").clicked() {
+                commands.run_system_cached(open_graph);
+            }
+            if ui.button("Export").clicked() {
+                commands.run_system_cached(export_program);
+            }
+            if ui.button("Bytecode").clicked() {
+                commands.run_system_cached(toggle_bytecode);
+            }
+        });
+        if let Some(bytecode) = &bytecode.0 {
+            egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| ui.monospace(bytecode));
         }
-        SnarlWidget::new().id(Id::new("snarl-demo")).style(default_style()).show(&mut snarl.0, &mut node_viewer, ui);
+        for diagnostic in diagnostics.0.0.iter().filter(|diagnostic| diagnostic.location == Location::Graph) {
+            ui.colored_label(diagnostic.severity.color(), &diagnostic.message);
+        }
+        SnarlWidget::new().id(Id::new(SNARL_ID)).style(default_style()).show(&mut snarl.0, &mut node_viewer, ui);
     });
+    node_viewer.edits.moved(&snarl.0, &positions);
+    history.record(node_viewer.edits);
+    Ok(())
 }
 
 const fn default_style() -> SnarlStyle {
     SnarlStyle {
-        node_layout: Some(NodeLayout::Basic),
+        node_layout: Some(NodeLayout::coil()),
         pin_placement: Some(PinPlacement::Edge),
         pin_size: Some(7.0),
         node_frame: Some(egui::Frame {
@@ -285,109 +416,3 @@ const fn default_style() -> SnarlStyle {
         ..SnarlStyle::new()
     }
 }
-
-pub struct NodeViewer {
-    pub(crate) structs: Vec<Box<dyn Struct>>,
-    pub(crate) functions: Vec<DynamicFunction<'static>>,
-    pub components: Vec<Box<dyn PartialReflect>>,
-    pub function_registry: FunctionRegistry,
-    pub registry: AppTypeRegistry,
-}
-
-impl Default for NodeViewer {
-    fn default() -> Self {
-        use bevy::prelude::Reflect;
-        let add: fn(Vec3, Vec3) -> Vec3 = Vec3::add;
-        let vec3_print = (|vec3: Vec3| println!("{}", vec3)).into_function().with_name("print_vec3").try_with_overload(|vec3: &Vec3| println!("{}", vec3)).unwrap().try_with_overload(|vec3: &mut Vec3| println!("{}", vec3)).unwrap();
-        NodeViewer {
-            structs: vec![PartialReflect::reflect_owned(Box::new(Transform::default())).into_struct().unwrap(), PartialReflect::reflect_owned(Box::new(Vec3::default())).into_struct().unwrap()],
-            functions: vec![
-                (|vec3: &mut Vec3| println!("{}", vec3)).into_function().with_name("print_vec3_mut"),
-                set_float.into_function().with_name("set_float"),
-                set_x.into_function().with_name("set_x"),
-                add.into_function().with_name("add"),
-                (|f32: f32| println!("{}", f32)).into_function().with_name("print_f32"),
-                (|| println!("hello world")).into_function().with_name("hello_world"),
-                (|| println!("owo")).into_function().with_name("owo"),
-            ],
-            components: vec![Box::new(Transform::default()).into_reflect()],
-            function_registry: Default::default(),
-            registry: AppTypeRegistry::default(),
-        }
-    }
-}
-
-impl SnarlViewer<GraphNode> for NodeViewer {
-    fn title(&mut self, node: &GraphNode) -> String {
-        node.get_marker().title(node, self)
-    }
-
-    fn show_header(&mut self, node: NodeId, inputs: &[InPin], outputs: &[OutPin], ui: &mut Ui, snarl: &mut Snarl<GraphNode>) {
-        let marker = snarl.get_node(node).unwrap().get_marker();
-        if marker.has_header(self, snarl.get_node(node).unwrap()) {
-            marker.show_header(self, node, inputs, outputs, ui, snarl);
-        } else {
-            ui.label(self.title(&snarl[node]));
-        }
-    }
-
-    fn inputs(&mut self, node: &GraphNode) -> usize {
-        node.get_marker().inputs(node, self)
-    }
-
-    fn show_input(&mut self, pin: &InPin, ui: &mut Ui, snarl: &mut Snarl<GraphNode>) -> impl SnarlPin + 'static {
-        snarl.get_node(pin.id.node).unwrap().get_marker().show_input(self, pin, ui, snarl).with_wire_style(WireStyle::AxisAligned { corner_radius: 10.0 })
-    }
-
-    fn outputs(&mut self, node: &GraphNode) -> usize {
-        node.get_marker().outputs(node, self)
-    }
-
-    fn show_output(&mut self, pin: &OutPin, ui: &mut Ui, snarl: &mut Snarl<GraphNode>) -> impl SnarlPin + 'static {
-        snarl.get_node(pin.id.node).unwrap().get_marker().show_output(self, pin, ui, snarl).with_wire_style(WireStyle::AxisAligned { corner_radius: 10.0 })
-    }
-
-    fn has_body(&mut self, node: &GraphNode) -> bool {
-        node.get_marker().has_body(self, node)
-    }
-
-    fn show_body(&mut self, node: NodeId, inputs: &[InPin], outputs: &[OutPin], ui: &mut Ui, snarl: &mut Snarl<GraphNode>) {
-        snarl.get_node(node).unwrap().get_marker().show_body(self, node, inputs, outputs, ui, snarl);
-    }
-
-    fn has_footer(&mut self, node: &GraphNode) -> bool {
-        node.get_marker().has_footer(self, node)
-    }
-
-    fn show_footer(&mut self, node: NodeId, inputs: &[InPin], outputs: &[OutPin], ui: &mut Ui, snarl: &mut Snarl<GraphNode>) {
-        snarl.get_node(node).unwrap().get_marker().show_footer(self, node, inputs, outputs, ui, snarl);
-    }
-
-    fn has_graph_menu(&mut self, _pos: egui::Pos2, _snarl: &mut Snarl<GraphNode>) -> bool {
-        true
-    }
-
-    fn show_graph_menu(&mut self, pos: egui::Pos2, ui: &mut Ui, snarl: &mut Snarl<GraphNode>) {
-        ui.label("Add node");
-        for node in GraphNode::list() {
-            let marker = node.get_marker();
-            if ui.button(marker.title(&node, self)).clicked() {
-                snarl.insert_node(pos, node);
-                ui.close_menu();
-            }
-        }
-    }
-
-    fn has_node_menu(&mut self, node: &GraphNode) -> bool {
-        true
-    }
-
-    fn show_node_menu(&mut self, node: NodeId, inputs: &[InPin], outputs: &[OutPin], ui: &mut Ui, snarl: &mut Snarl<GraphNode>) {
-        if ui.button("Delete").clicked() {
-            snarl.remove_node(node);
-            ui.close_menu();
-            return;
-        }
-        snarl.get_node(node).unwrap().get_marker().show_node_menu(self, node, inputs, outputs, ui, snarl);
-    }
-}
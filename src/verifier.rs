@@ -0,0 +1,550 @@
+use crate::{Bytecode, Literal, QueryDataType, QueryWrapper};
+use bevy::ecs::component::ComponentId;
+use bevy::ecs::query::FilteredAccess;
+use bevy::prelude::Entity;
+use bevy::reflect::func::args::{ArgInfo, Ownership};
+use bevy::reflect::{TypeInfo, TypeRegistry, Typed};
+use std::any::TypeId;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+
+// The verifier runs the program abstractly: instead of values the stack holds their `Kind`.
+// Every reachable instruction is visited with the stack it would see on every path leading to it,
+// so a loop body that doesn't pop what it pushed shows up as two different stack depths at the loop head.
+
+/// What the verifier knows about a stack slot.
+#[derive(Clone, Debug)]
+pub enum Kind {
+    Value(Ownership, Option<&'static TypeInfo>),
+    List(Vec<Kind>),
+    /// The items, and the `Bytecode::Query`s the iterator may have come from.
+    Iterator(Vec<Kind>, Vec<usize>),
+    Unknown,
+}
+
+impl PartialEq for Kind {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Kind::Value(a, a_info), Kind::Value(b, b_info)) => a == b && a_info.map(TypeInfo::type_id) == b_info.map(TypeInfo::type_id),
+            (Kind::List(a), Kind::List(b)) => a == b,
+            (Kind::Iterator(a, a_from), Kind::Iterator(b, b_from)) => a == b && a_from == b_from,
+            (Kind::Unknown, Kind::Unknown) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Display for Kind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Kind::Value(ownership, info) => {
+                match ownership {
+                    Ownership::Ref => f.write_str("&")?,
+                    Ownership::Mut => f.write_str("&mut ")?,
+                    Ownership::Owned => {}
+                };
+                f.write_str(info.map(TypeInfo::type_path).unwrap_or("_"))
+            }
+            Kind::List(items) => write!(f, "list of {}", items.len()),
+            Kind::Iterator(items, _) => write!(f, "iterator of {}", items.len()),
+            Kind::Unknown => f.write_str("unknown"),
+        }
+    }
+}
+
+impl Kind {
+    fn of(literal: &Literal) -> Kind {
+        match literal {
+            Literal::Box(val) => Kind::Value(Ownership::Owned, val.get_represented_type_info()),
+            Literal::List(vals) => Kind::List(vals.iter().map(Kind::of).collect()),
+        }
+    }
+
+    fn owned(&self) -> Result<Kind, VerifyErrorKind> {
+        match self {
+            Kind::Value(_, info) => Ok(Kind::Value(Ownership::Owned, *info)),
+            Kind::List(items) => Ok(Kind::List(items.iter().map(Kind::owned).collect::<Result<_, _>>()?)),
+            Kind::Iterator(..) => Err(VerifyErrorKind::NotClonable),
+            Kind::Unknown => Ok(Kind::Unknown),
+        }
+    }
+
+    fn borrowed(&self, ownership: Ownership) -> Result<Kind, VerifyErrorKind> {
+        match self {
+            Kind::Value(Ownership::Ref, _) if ownership == Ownership::Mut => Err(VerifyErrorKind::MutableBorrowOfRef),
+            Kind::Value(_, info) => Ok(Kind::Value(ownership, *info)),
+            Kind::List(items) => Ok(Kind::List(items.iter().map(|item| item.borrowed(ownership)).collect::<Result<_, _>>()?)),
+            Kind::Iterator(items, from) if ownership == Ownership::Mut => Ok(Kind::Iterator(items.clone(), from.clone())),
+            Kind::Iterator(..) => Ok(Kind::Value(ownership, None)),
+            Kind::Unknown => Ok(Kind::Unknown),
+        }
+    }
+
+    /// Whether this is or holds a `&mut`, which can only be reborrowed.
+    fn is_exclusive(&self) -> bool {
+        match self {
+            Kind::Value(ownership, _) => *ownership == Ownership::Mut,
+            Kind::List(items) => items.iter().any(Kind::is_exclusive),
+            _ => false,
+        }
+    }
+
+    fn into_shared(self) -> Kind {
+        match self {
+            Kind::Value(Ownership::Mut, info) => Kind::Value(Ownership::Ref, info),
+            Kind::List(items) => Kind::List(items.into_iter().map(Kind::into_shared).collect()),
+            kind => kind,
+        }
+    }
+
+    fn field(&self, field: usize, ownership: Ownership) -> Result<Kind, VerifyErrorKind> {
+        match self {
+            Kind::List(items) => {
+                let item = items.get(field).ok_or(VerifyErrorKind::FieldOutOfRange { field, len: items.len() })?;
+                match ownership {
+                    Ownership::Owned => Ok(item.clone()),
+                    ownership => item.borrowed(ownership),
+                }
+            }
+            Kind::Value(Ownership::Ref, _) if ownership == Ownership::Mut => Err(VerifyErrorKind::MutableBorrowOfRef),
+            Kind::Value(_, None) | Kind::Unknown => Ok(Kind::Value(ownership, None)),
+            Kind::Value(_, Some(info)) => Ok(Kind::Value(ownership, field_type_info(info, field)?)),
+            Kind::Iterator(..) => Err(VerifyErrorKind::NotIndexable("iterator".to_string())),
+        }
+    }
+
+    fn join(&self, other: &Kind) -> Kind {
+        match (self, other) {
+            (a, b) if a == b => a.clone(),
+            (Kind::Value(a, _), Kind::Value(b, _)) if a == b => Kind::Value(*a, None),
+            (Kind::List(a), Kind::List(b)) if a.len() == b.len() => Kind::List(a.iter().zip(b).map(|(a, b)| a.join(b)).collect()),
+            (Kind::Iterator(a, a_from), Kind::Iterator(b, b_from)) if a.len() == b.len() => {
+                let mut from: Vec<usize> = a_from.iter().chain(b_from).copied().collect();
+                from.sort();
+                from.dedup();
+                Kind::Iterator(a.iter().zip(b).map(|(a, b)| a.join(b)).collect(), from)
+            }
+            _ => Kind::Unknown,
+        }
+    }
+
+    /// The `Bytecode::Query`s whose iterators are in this slot.
+    fn queries(&self, out: &mut Vec<usize>) {
+        match self {
+            Kind::Iterator(items, from) => {
+                out.extend(from);
+                items.iter().for_each(|item| item.queries(out));
+            }
+            Kind::List(items) => items.iter().for_each(|item| item.queries(out)),
+            Kind::Value(..) | Kind::Unknown => {}
+        }
+    }
+
+    fn accepted_by(&self, arg: &ArgInfo) -> bool {
+        let (ownership, info) = match self {
+            Kind::Value(ownership, info) => (*ownership, *info),
+            Kind::Unknown => return true,
+            Kind::List(_) | Kind::Iterator(..) => return false,
+        };
+        // owned values are lent by reference when the function wants one
+        (ownership == Ownership::Owned || ownership == arg.ownership()) && info.is_none_or(|info| info.type_id() == arg.type_id())
+    }
+}
+
+fn field_type_info(info: &'static TypeInfo, field: usize) -> Result<Option<&'static TypeInfo>, VerifyErrorKind> {
+    match info {
+        TypeInfo::Struct(s) => s.field_at(field).map(|f| f.type_info()).ok_or(VerifyErrorKind::FieldOutOfRange { field, len: s.field_len() }),
+        TypeInfo::TupleStruct(s) => s.field_at(field).map(|f| f.type_info()).ok_or(VerifyErrorKind::FieldOutOfRange { field, len: s.field_len() }),
+        TypeInfo::Tuple(s) => s.field_at(field).map(|f| f.type_info()).ok_or(VerifyErrorKind::FieldOutOfRange { field, len: s.field_len() }),
+        // lists and arrays are only bounds checked at runtime
+        TypeInfo::List(_) | TypeInfo::Array(_) => Ok(None),
+        info => Err(VerifyErrorKind::NotIndexable(info.type_path().to_string())),
+    }
+}
+
+#[derive(Debug)]
+pub enum VerifyErrorKind {
+    StackUnderflow,
+    StackIndexOutOfRange {
+        index: usize,
+        depth: usize,
+    },
+    JumpOutOfRange(usize),
+    FieldOutOfRange {
+        field: usize,
+        len: usize,
+    },
+    NotIndexable(String),
+    NotClonable,
+    DuplicateMut,
+    ExpectedList(Kind),
+    ListLengthMismatch {
+        expected: usize,
+        found: usize,
+    },
+    MutableBorrowOfRef,
+    NotAnIterator(Kind),
+    MissingLoopExit,
+    NoSuchOverload {
+        function: String,
+        overload: usize,
+    },
+    ArityMismatch {
+        function: String,
+        expected: usize,
+        found: usize,
+    },
+    ArgumentMismatch {
+        function: String,
+        found: Vec<Kind>,
+    },
+    NotApplicable(Kind),
+    ExpectedEntity(Kind),
+    ExpectedBool(Kind),
+    UnbalancedStack {
+        target: usize,
+        expected: usize,
+        found: usize,
+    },
+    /// The query at `other` is still iterating and one of the two writes a component the other accesses.
+    QueryConflict {
+        other: usize,
+    },
+    /// A component the query names isn't registered, so what it accesses isn't known.
+    UnresolvedQuery(String),
+    MissingTypeRegistry,
+}
+
+impl Display for VerifyErrorKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            VerifyErrorKind::StackUnderflow => f.write_str("stack underflow"),
+            VerifyErrorKind::StackIndexOutOfRange { index, depth } => write!(f, "stack index {index} is out of range for a stack of {depth}"),
+            VerifyErrorKind::JumpOutOfRange(target) => write!(f, "jump target {target} is out of range"),
+            VerifyErrorKind::FieldOutOfRange { field, len } => write!(f, "field {field} is out of range for {len} fields"),
+            VerifyErrorKind::NotIndexable(type_path) => write!(f, "`{type_path}` has no indexable fields"),
+            VerifyErrorKind::NotClonable => f.write_str("iterators can't be cloned"),
+            VerifyErrorKind::DuplicateMut => f.write_str("a `&mut` can't be duplicated, reborrow it with `mut` instead"),
+            VerifyErrorKind::ExpectedList(kind) => write!(f, "expected a list but found {kind}"),
+            VerifyErrorKind::ListLengthMismatch { expected, found } => write!(f, "expected a list of {expected} values but found {found}"),
+            VerifyErrorKind::MutableBorrowOfRef => f.write_str("cannot mutably borrow through a shared reference"),
+            VerifyErrorKind::NotAnIterator(kind) => write!(f, "expected an iterator but found {kind}"),
+            VerifyErrorKind::MissingLoopExit => f.write_str("loop is not followed by the jump out of it"),
+            VerifyErrorKind::NoSuchOverload { function, overload } => write!(f, "`{function}` has no overload {overload}"),
+            VerifyErrorKind::ArityMismatch { function, expected, found } => write!(f, "`{function}` takes {expected} arguments but the stack only has {found}"),
+            VerifyErrorKind::ArgumentMismatch { function, found } => {
+                write!(f, "no signature of `{function}` accepts (")?;
+                for (i, kind) in found.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{kind}")?;
+                }
+                f.write_str(")")
+            }
+            VerifyErrorKind::NotApplicable(kind) => write!(f, "cannot apply to {kind}"),
+            VerifyErrorKind::ExpectedEntity(kind) => write!(f, "expected an owned entity but found {kind}"),
+            VerifyErrorKind::ExpectedBool(kind) => write!(f, "expected an owned bool but found {kind}"),
+            VerifyErrorKind::UnbalancedStack { target, expected, found } => write!(f, "stack depth at {target} is {found} on one path and {expected} on another"),
+            VerifyErrorKind::UnresolvedQuery(error) => f.write_str(error),
+            VerifyErrorKind::MissingTypeRegistry => f.write_str("world has no `AppTypeRegistry`"),
+            VerifyErrorKind::QueryConflict { other } => write!(f, "query conflicts with the query at {other} that is still iterating, add `without` filters to keep them apart"),
+        }
+    }
+}
+
+/// A program rejected by [`verify`].
+#[derive(Debug)]
+pub struct VerifyError {
+    pub ip: usize,
+    pub opcode: String,
+    pub kind: VerifyErrorKind,
+}
+
+impl Display for VerifyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at {} ({})", self.kind, self.ip, self.opcode)
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+fn query_items(queries: &[QueryDataType]) -> Vec<Kind> {
+    queries
+        .iter()
+        .map(|query| match query {
+            QueryDataType::Entity => Kind::Value(Ownership::Owned, Some(Entity::type_info())),
+            QueryDataType::Ref(info) => Kind::Value(Ownership::Ref, Some(*info)),
+            QueryDataType::Mut(info) => Kind::Value(Ownership::Mut, Some(*info)),
+            QueryDataType::OptionalRef(info) => Kind::Iterator(vec![Kind::Value(Ownership::Ref, Some(*info))], vec![]),
+            QueryDataType::OptionalMut(info) => Kind::Iterator(vec![Kind::Value(Ownership::Mut, Some(*info))], vec![]),
+        })
+        .collect()
+}
+
+/// An iterator on the stack may still be fetching, so the query or `Get` at `ip` can't touch what it writes or the other way around.
+fn check_conflicts(ip: usize, stack: &[Kind], accesses: &HashMap<usize, FilteredAccess<ComponentId>>) -> Result<(), VerifyErrorKind> {
+    let Some(access) = accesses.get(&ip) else {
+        return Ok(());
+    };
+    let mut live = vec![];
+    stack.iter().for_each(|kind| kind.queries(&mut live));
+    match live.into_iter().find(|other| accesses.get(other).is_some_and(|other| !access.is_compatible(other))) {
+        Some(other) => Err(VerifyErrorKind::QueryConflict { other }),
+        None => Ok(()),
+    }
+}
+
+/// Checks that `bytecode` can't misuse the stack on any control path, so [`Bytecode::run`] only fails on runtime conditions.
+/// `inputs` are the values the program starts with on the stack, `accesses` what each `Bytecode::Query` and `Bytecode::Get` accesses by its index.
+pub fn verify(bytecode: &[Bytecode], inputs: Vec<Kind>, type_registry: &TypeRegistry, accesses: &HashMap<usize, FilteredAccess<ComponentId>>) -> Result<(), VerifyError> {
+    let mut states: Vec<Option<Vec<Kind>>> = vec![None; bytecode.len() + 1];
+    states[0] = Some(inputs);
+    let mut worklist = vec![0];
+    while let Some(ip) = worklist.pop() {
+        let (Some(op), Some(stack)) = (bytecode.get(ip), states[ip].clone()) else {
+            continue;
+        };
+        let error = |kind| VerifyError { ip, opcode: format!("{:?}", op), kind };
+        for (next, stack) in step(op, ip, stack, bytecode, type_registry, accesses).map_err(error)? {
+            match &mut states[next] {
+                None => {
+                    states[next] = Some(stack);
+                    worklist.push(next);
+                }
+                Some(existing) => {
+                    if existing.len() != stack.len() {
+                        return Err(error(VerifyErrorKind::UnbalancedStack { target: next, expected: existing.len(), found: stack.len() }));
+                    }
+                    let joined: Vec<Kind> = existing.iter().zip(&stack).map(|(a, b)| a.join(b)).collect();
+                    if joined != *existing {
+                        *existing = joined;
+                        worklist.push(next);
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn step(op: &Bytecode, ip: usize, mut stack: Vec<Kind>, bytecode: &[Bytecode], type_registry: &TypeRegistry, accesses: &HashMap<usize, FilteredAccess<ComponentId>>) -> Result<Vec<(usize, Vec<Kind>)>, VerifyErrorKind> {
+    let get = |stack: &Vec<Kind>, index: usize| stack.get(index).cloned().ok_or(VerifyErrorKind::StackIndexOutOfRange { index, depth: stack.len() });
+    match op {
+        Bytecode::Pop => {
+            stack.pop().ok_or(VerifyErrorKind::StackUnderflow)?;
+        }
+        Bytecode::Push(value) => stack.push(Kind::of(value)),
+        Bytecode::Clone(index) => {
+            let kind = get(&stack, *index)?.owned()?;
+            stack.push(kind);
+        }
+        Bytecode::Dup(index) => {
+            let kind = get(&stack, *index)?;
+            if let Kind::Iterator(..) = kind {
+                return Err(VerifyErrorKind::NotClonable);
+            }
+            if kind.is_exclusive() {
+                return Err(VerifyErrorKind::DuplicateMut);
+            }
+            stack.push(kind);
+        }
+        Bytecode::Ref(index) => {
+            let kind = get(&stack, *index)?.borrowed(Ownership::Ref)?;
+            stack.push(kind);
+        }
+        Bytecode::Mut(index) => {
+            let kind = get(&stack, *index)?.borrowed(Ownership::Mut)?;
+            stack.push(kind);
+        }
+        Bytecode::DupField(index, field) => {
+            let kind = get(&stack, *index)?.field(*field, Ownership::Owned)?;
+            stack.push(kind);
+        }
+        Bytecode::RefField(index, field) => {
+            let kind = get(&stack, *index)?.field(*field, Ownership::Ref)?;
+            stack.push(kind);
+        }
+        Bytecode::MutField(index, field) => {
+            let kind = get(&stack, *index)?.field(*field, Ownership::Mut)?;
+            stack.push(kind);
+        }
+        Bytecode::ListBreakdown(length) => match stack.pop().ok_or(VerifyErrorKind::StackUnderflow)? {
+            Kind::List(items) if items.len() == *length => stack.extend(items),
+            Kind::List(items) => return Err(VerifyErrorKind::ListLengthMismatch { expected: *length, found: items.len() }),
+            Kind::Unknown => stack.extend(std::iter::repeat_n(Kind::Unknown, *length)),
+            kind => return Err(VerifyErrorKind::ExpectedList(kind)),
+        },
+        Bytecode::Call(function, overload) => {
+            let name = function.name().map(|name| name.to_string()).unwrap_or_else(|| "<anonymous>".to_string());
+            let Some(signature) = function.info().signatures().get(*overload) else {
+                return Err(VerifyErrorKind::NoSuchOverload { function: name, overload: *overload });
+            };
+            let arity = signature.arg_count();
+            if stack.len() < arity {
+                return Err(VerifyErrorKind::ArityMismatch { function: name, expected: arity, found: stack.len() });
+            }
+            let args = stack.split_off(stack.len() - arity);
+            if !signature.args().iter().zip(&args).all(|(arg, kind)| kind.accepted_by(arg)) {
+                return Err(VerifyErrorKind::ArgumentMismatch { function: name, found: args });
+            }
+            let ret = signature.return_info();
+            stack.push(Kind::Value(ret.ownership(), type_registry.get_type_info(ret.type_id())));
+        }
+        Bytecode::Query(QueryWrapper { queries, .. }) => {
+            check_conflicts(ip, &stack, accesses)?;
+            stack.push(Kind::Iterator(query_items(queries), vec![ip]));
+        }
+        Bytecode::Get(QueryWrapper { queries, .. }) => {
+            check_conflicts(ip, &stack, accesses)?;
+            match stack.pop().ok_or(VerifyErrorKind::StackUnderflow)? {
+                kind @ Kind::Value(Ownership::Owned, Some(info)) if info.type_id() != TypeId::of::<Entity>() => return Err(VerifyErrorKind::ExpectedEntity(kind)),
+                Kind::Value(Ownership::Owned, _) | Kind::Unknown => {}
+                kind => return Err(VerifyErrorKind::ExpectedEntity(kind)),
+            }
+            stack.push(Kind::List(query_items(queries)));
+        }
+        Bytecode::IterRef | Bytecode::NextMut => {
+            let item = match stack.last() {
+                Some(Kind::Iterator(items, _)) => Kind::List(items.clone()),
+                Some(Kind::Unknown) => Kind::Unknown,
+                Some(kind) => return Err(VerifyErrorKind::NotAnIterator(kind.clone())),
+                None => return Err(VerifyErrorKind::StackUnderflow),
+            };
+            let item = if let Bytecode::IterRef = op { item.into_shared() } else { item };
+            // the instruction after is the jump out of the loop, the one after that starts the body
+            if !matches!(bytecode.get(ip + 1), Some(Bytecode::Jump(_))) {
+                return Err(VerifyErrorKind::MissingLoopExit);
+            }
+            let mut body = stack.clone();
+            body.push(item);
+            return Ok(vec![(ip + 1, stack), (ip + 2, body)]);
+        }
+        Bytecode::Apply => {
+            let applier = stack.pop().ok_or(VerifyErrorKind::StackUnderflow)?;
+            let receiver = stack.pop().ok_or(VerifyErrorKind::StackUnderflow)?;
+            match receiver {
+                Kind::Value(Ownership::Mut | Ownership::Owned, _) | Kind::Unknown => {}
+                kind => return Err(VerifyErrorKind::NotApplicable(kind)),
+            }
+            if let Kind::List(_) | Kind::Iterator(..) = applier {
+                return Err(VerifyErrorKind::NotApplicable(applier));
+            }
+        }
+        Bytecode::Jump(target) => {
+            if *target > bytecode.len() {
+                return Err(VerifyErrorKind::JumpOutOfRange(*target));
+            }
+            return Ok(vec![(*target, stack)]);
+        }
+        Bytecode::JumpIfNot(target) => {
+            if *target > bytecode.len() {
+                return Err(VerifyErrorKind::JumpOutOfRange(*target));
+            }
+            match stack.pop().ok_or(VerifyErrorKind::StackUnderflow)? {
+                kind @ Kind::Value(Ownership::Owned, Some(info)) if info.type_id() != TypeId::of::<bool>() => return Err(VerifyErrorKind::ExpectedBool(kind)),
+                Kind::Value(Ownership::Owned, _) | Kind::Unknown => {}
+                kind => return Err(VerifyErrorKind::ExpectedBool(kind)),
+            }
+            return Ok(vec![(ip + 1, stack.clone()), (*target, stack)]);
+        }
+    }
+    Ok(vec![(ip + 1, stack)])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assembly::assemble;
+    use crate::ui::FunctionRegistry;
+    use bevy::prelude::*;
+    use bevy::reflect::TypePath;
+
+    #[derive(Component, Reflect, Default)]
+    #[reflect(Component, Default)]
+    struct Speed(f32);
+
+    #[derive(Component, Reflect, Default)]
+    #[reflect(Component, Default)]
+    struct Frozen;
+
+    #[derive(Component, Reflect)]
+    #[reflect(Component)]
+    struct Unspawned;
+
+    fn world() -> World {
+        let mut world = World::new();
+        let registry = AppTypeRegistry::default();
+        {
+            let mut registry = registry.write();
+            registry.register::<f32>();
+            registry.register::<bool>();
+            registry.register::<Speed>();
+            registry.register::<Frozen>();
+        }
+        world.insert_resource(registry);
+        world.register_component::<Speed>();
+        world.register_component::<Frozen>();
+        world
+    }
+
+    fn verify_source(source: &str) -> Result<(), VerifyError> {
+        let world = world();
+        let bytecode = assemble(source, &FunctionRegistry::default(), &world.resource::<AppTypeRegistry>().read()).unwrap();
+        Bytecode::verify(&world, &bytecode, &[])
+    }
+
+    #[test]
+    fn branches_have_to_agree_on_the_stack() {
+        let branch = |body: &str| format!("push {{\"bool\": true}}\njump_if_not L0\n{body}\nL0:\n");
+        verify_source(&branch("push {\"f32\": 1.0}\npop")).unwrap();
+        let error = verify_source(&branch("push {\"f32\": 1.0}")).unwrap_err();
+        assert_eq!(error.ip, 2);
+        assert!(matches!(error.kind, VerifyErrorKind::UnbalancedStack { target: 3, expected: 0, found: 1 }));
+    }
+
+    #[test]
+    fn loops_that_grow_the_stack_are_rejected() {
+        let error = verify_source("L0:\npush {\"f32\": 1.0}\njump L0\n").unwrap_err();
+        assert!(matches!(error.kind, VerifyErrorKind::UnbalancedStack { target: 0, .. }));
+    }
+
+    #[test]
+    fn stack_misuse_is_caught_before_running() {
+        assert!(matches!(verify_source("pop\n").unwrap_err().kind, VerifyErrorKind::StackUnderflow));
+        assert!(matches!(verify_source("push {\"f32\": 1.0}\nmut 0\ndup 1\n").unwrap_err().kind, VerifyErrorKind::DuplicateMut));
+        assert!(matches!(verify_source("ref 2\n").unwrap_err().kind, VerifyErrorKind::StackIndexOutOfRange { index: 2, depth: 0 }));
+        assert!(matches!(verify_source("push {\"f32\": 1.0}\nlist_breakdown 1\n").unwrap_err().kind, VerifyErrorKind::ExpectedList(_)));
+        assert!(matches!(verify_source("push {\"f32\": 1.0}\njump_if_not L0\nL0:\n").unwrap_err().kind, VerifyErrorKind::ExpectedBool(_)));
+    }
+
+    /// A query over `outer` with a query over `inner` run in its loop body.
+    fn nested_queries(outer: &str, inner: &str) -> String {
+        format!("query {outer}\nL0:\nnext_mut\njump L1\nquery {inner}\npop\npop\njump L0\nL1:\npop\n")
+    }
+
+    #[test]
+    fn queries_conflict_with_the_loops_they_run_in() {
+        let speed = Speed::type_path();
+        let frozen = Frozen::type_path();
+        let error = verify_source(&nested_queries(&format!("&mut {speed}"), &format!("&{speed}"))).unwrap_err();
+        assert_eq!(error.ip, 3);
+        assert!(matches!(error.kind, VerifyErrorKind::QueryConflict { other: 0 }));
+        verify_source(&nested_queries(&format!("&{speed}"), &format!("&{speed}"))).unwrap();
+        verify_source(&nested_queries(&format!("&mut {speed} where without {frozen}"), &format!("&{speed} where with {frozen}"))).unwrap();
+    }
+
+    #[test]
+    fn queries_need_their_components_registered() {
+        let mut world = world();
+        let type_registry = world.resource::<AppTypeRegistry>().clone();
+        type_registry.write().register::<Unspawned>();
+        let bytecode = assemble(&format!("query &{}\npop\n", Unspawned::type_path()), &FunctionRegistry::default(), &type_registry.read()).unwrap();
+        let error = Bytecode::verify(&world, &bytecode, &[]).unwrap_err();
+        assert!(matches!(error.kind, VerifyErrorKind::UnresolvedQuery(_)));
+        assert!(error.to_string().contains(Unspawned::type_path()));
+        Bytecode::register_components(&mut world, &bytecode);
+        Bytecode::verify(&world, &bytecode, &[]).unwrap();
+        assert!(matches!(Bytecode::verify(&World::new(), &bytecode, &[]).unwrap_err().kind, VerifyErrorKind::MissingTypeRegistry));
+    }
+}
@@ -0,0 +1,21 @@
+use bevy_node_scripting::script_fn;
+use bevy_node_scripting::ui::FunctionRegistry;
+
+/// Doubles a number.
+#[script_fn(name = "Double", category = "Math", args("value"), returns = "doubled")]
+fn double(value: f32) -> f32 {
+    value * 2.0
+}
+
+#[test]
+fn script_fns_from_other_crates_are_registered() {
+    let mut functions = FunctionRegistry::default();
+    functions.register_script_fns();
+    let listed = functions.listed();
+    let listing = listed.iter().find(|listing| listing.label == "Double").unwrap();
+    assert_eq!(listing.category, "Math");
+    let meta = listing.meta.unwrap();
+    assert_eq!(meta.args, ["value"]);
+    assert_eq!(meta.returns, "doubled");
+    assert_eq!(meta.doc, "Doubles a number.");
+}